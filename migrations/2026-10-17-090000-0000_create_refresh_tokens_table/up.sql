@@ -0,0 +1,17 @@
+-- Create refresh_tokens table for rotating refresh tokens
+CREATE TABLE refresh_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    family_id UUID NOT NULL,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP,
+    revoked_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create index on family_id for revoking a whole token family
+CREATE INDEX idx_refresh_tokens_family ON refresh_tokens(family_id);
+
+-- Create index on user_id for revoking every token of a user
+CREATE INDEX idx_refresh_tokens_user ON refresh_tokens(user_id);
@@ -0,0 +1,3 @@
+-- Drop revoked_tokens table and the per-user token cutoff
+ALTER TABLE users DROP COLUMN IF EXISTS tokens_valid_after;
+DROP TABLE IF EXISTS revoked_tokens;
@@ -0,0 +1,13 @@
+-- Create revoked_tokens table for access tokens revoked before their expiry
+CREATE TABLE revoked_tokens (
+    jti UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create index on expires_at for purging entries that can no longer be used
+CREATE INDEX idx_revoked_tokens_expires ON revoked_tokens(expires_at);
+
+-- Tokens issued to a user before this timestamp are rejected ("log out everywhere")
+ALTER TABLE users ADD COLUMN tokens_valid_after TIMESTAMP;
@@ -0,0 +1,24 @@
+-- Restore the single OAuth identity columns on users
+ALTER TABLE users ADD COLUMN oauth_provider VARCHAR(50);
+ALTER TABLE users ADD COLUMN oauth_id VARCHAR(255);
+
+-- Passwordless users get back their oldest identity; others stay password-only
+UPDATE users u
+SET oauth_provider = i.provider, oauth_id = i.provider_user_id
+FROM (
+    SELECT DISTINCT ON (user_id) user_id, provider, provider_user_id
+    FROM user_identities
+    ORDER BY user_id, created_at
+) i
+WHERE u.id = i.user_id AND u.password_hash IS NULL;
+
+CREATE INDEX idx_users_oauth ON users(oauth_provider, oauth_id) WHERE oauth_provider IS NOT NULL;
+
+-- NOT VALID: rows created while identities existed may not satisfy it
+ALTER TABLE users ADD CONSTRAINT check_auth_method
+    CHECK (
+        (password_hash IS NOT NULL AND oauth_provider IS NULL AND oauth_id IS NULL) OR
+        (password_hash IS NULL AND oauth_provider IS NOT NULL AND oauth_id IS NOT NULL)
+    ) NOT VALID;
+
+DROP TABLE IF EXISTS user_identities;
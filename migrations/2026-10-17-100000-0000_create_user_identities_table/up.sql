@@ -0,0 +1,28 @@
+-- Create user_identities table so one user can hold several OAuth logins
+CREATE TABLE user_identities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(50) NOT NULL,
+    provider_user_id VARCHAR(255) NOT NULL,
+    email VARCHAR(255),
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- A provider account can only ever belong to one user
+    CONSTRAINT uq_user_identities_provider UNIQUE (provider, provider_user_id),
+    -- A user links at most one account per provider
+    CONSTRAINT uq_user_identities_user_provider UNIQUE (user_id, provider)
+);
+
+-- Create index on user_id for listing a user's identities
+CREATE INDEX idx_user_identities_user ON user_identities(user_id);
+
+-- Move existing OAuth logins over
+INSERT INTO user_identities (user_id, provider, provider_user_id, email, created_at)
+SELECT id, oauth_provider, oauth_id, email, created_at
+FROM users
+WHERE oauth_provider IS NOT NULL AND oauth_id IS NOT NULL;
+
+-- A user may now have a password and identities at the same time
+ALTER TABLE users DROP CONSTRAINT IF EXISTS check_auth_method;
+DROP INDEX IF EXISTS idx_users_oauth;
+ALTER TABLE users DROP COLUMN oauth_provider;
+ALTER TABLE users DROP COLUMN oauth_id;
@@ -0,0 +1,13 @@
+-- Create signing_keys table for rotating the keys access tokens are signed with
+CREATE TABLE signing_keys (
+    kid VARCHAR(100) PRIMARY KEY,
+    algorithm VARCHAR(10) NOT NULL,
+    private_key_path VARCHAR(500),
+    public_key_path VARCHAR(500),
+    active BOOLEAN NOT NULL DEFAULT FALSE,
+    not_after TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- At most one key signs new tokens
+CREATE UNIQUE INDEX idx_signing_keys_active ON signing_keys(active) WHERE active;
@@ -0,0 +1,2 @@
+-- Drop webauthn_credentials table
+DROP TABLE IF EXISTS webauthn_credentials;
@@ -0,0 +1,12 @@
+-- Create password_reset_tokens table for emailed single-use reset links
+CREATE TABLE password_reset_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create index on user_id for replacing a user's earlier tokens
+CREATE INDEX idx_password_reset_tokens_user ON password_reset_tokens(user_id);
@@ -0,0 +1,3 @@
+-- Drop email_verification_tokens table and the verification timestamp
+ALTER TABLE users DROP COLUMN IF EXISTS email_verified_at;
+DROP TABLE IF EXISTS email_verification_tokens;
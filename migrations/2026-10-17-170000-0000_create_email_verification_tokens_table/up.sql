@@ -0,0 +1,18 @@
+-- Create email_verification_tokens table for emailed verification links
+CREATE TABLE email_verification_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create index on user_id for replacing a user's earlier tokens
+CREATE INDEX idx_email_verification_tokens_user ON email_verification_tokens(user_id);
+
+-- Set once the user proves they own the address
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMP;
+
+-- Accounts predating verification count as verified, so turning on
+-- REQUIRE_EMAIL_VERIFICATION doesn't lock them out
+UPDATE users SET email_verified_at = created_at;
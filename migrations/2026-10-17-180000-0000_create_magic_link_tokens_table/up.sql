@@ -0,0 +1,11 @@
+-- Create magic_link_tokens table for emailed single-use login links
+CREATE TABLE magic_link_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    email VARCHAR(255) NOT NULL,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create index on email for replacing the earlier links sent to an address
+CREATE INDEX idx_magic_link_tokens_email ON magic_link_tokens(email);
@@ -0,0 +1,201 @@
+use chrono::{Duration, Utc};
+use reqwest::Url;
+use std::sync::Arc;
+use crate::config::AppConfig;
+use crate::db::{DieselStore, OAuthClientStore, RoleStore, SigningKeyStore, UserStore, create_pool};
+use crate::error::AppError;
+use crate::models::oauth_client::OAuthClient;
+use crate::models::signing_key::SigningKey;
+use crate::utils::{hashing, jwt::JwtKeys, token};
+
+const KEYS_USAGE: &str = "\
+Usage: auth_session keys <command>
+
+  list                                      Show the keyset
+  add <kid> <alg> <private_pem> <public_pem>
+                                            Add a verify-only key (RS256, ES256, EdDSA)
+  add <kid> <alg> <secret_file>             Add a verify-only HMAC key (HS256)
+  promote <kid>                             Sign with <kid>; the current key keeps
+                                            verifying for JWT_EXPIRATION seconds
+  retire <kid>                              Stop accepting tokens signed with <kid>
+
+Running servers pick up changes within 30 seconds.";
+
+const ROLES_USAGE: &str = "\
+Usage: auth_session roles <command>
+
+  show <email>                              Show a user's roles and permissions
+  grant <email> <role>                      Give a user a role, e.g. admin
+  revoke <email> <role>                     Take a role away
+
+Changes apply from the user's next login or token refresh.";
+
+const CLIENTS_USAGE: &str = "\
+Usage: auth_session clients <command>
+
+  show <client_id>                          Show a registered client
+  add <client_id> <name> <redirect_uris> [scopes]
+                                            Register a confidential client and print
+                                            its secret, which is not stored
+  add-public <client_id> <name> <redirect_uris> [scopes]
+                                            Register a public client (PKCE only)
+
+Lists are comma-separated; scopes default to openid,email,profile.";
+
+/// `auth_session keys ...`: manages the JWT signing keys shared by every
+/// instance through the `signing_keys` table.
+pub async fn keys(config: AppConfig, args: &[String]) -> Result<(), AppError> {
+    let pool = create_pool(&config.database.url, 1).await?;
+    let store: Arc<dyn SigningKeyStore> = Arc::new(DieselStore::new(pool));
+
+    // Records the configured key first if the table is still empty
+    let jwt_keys = JwtKeys::new(&config.jwt, store.clone())?;
+    jwt_keys.sync().await?;
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["list"] => {
+            let now = Utc::now().naive_utc();
+            for key in store.list_signing_keys().await? {
+                let status = match key.not_after {
+                    _ if key.active => "active".to_string(),
+                    Some(not_after) if not_after <= now => "retired".to_string(),
+                    Some(not_after) => format!("verify-only until {}", not_after.format("%Y-%m-%d %H:%M:%S UTC")),
+                    None => "verify-only".to_string(),
+                };
+                println!("{}\t{}\t{}", key.kid, key.algorithm, status);
+            }
+        }
+        ["add", kid, algorithm, key_paths @ ..] => {
+            let (private_key_path, public_key_path) = match key_paths {
+                [secret] => (Some(secret.to_string()), None),
+                [private, public] => (Some(private.to_string()), Some(public.to_string())),
+                _ => return Err(AppError::BadRequest(KEYS_USAGE.to_string())),
+            };
+
+            // Refuse keys the servers would fail to load
+            jwt_keys.load(&SigningKey {
+                kid: kid.to_string(),
+                algorithm: algorithm.to_string(),
+                private_key_path: private_key_path.clone(),
+                public_key_path: public_key_path.clone(),
+                active: false,
+                not_after: None,
+                created_at: Utc::now().naive_utc(),
+            })?;
+
+            store.create_signing_key(
+                kid.to_string(),
+                algorithm.to_string(),
+                private_key_path,
+                public_key_path,
+                false,
+            ).await?;
+            println!("Added verify-only key {}", kid);
+        }
+        ["promote", kid] => {
+            // Tokens signed by the old key stay valid until they expire
+            let previous_not_after = (Utc::now() + Duration::seconds(config.jwt.expiration)).naive_utc();
+            store.promote_signing_key(kid, previous_not_after).await?;
+            println!("Key {} is now active; the previous key verifies until {}", kid, previous_not_after);
+        }
+        ["retire", kid] => {
+            store.retire_signing_key(kid, Utc::now().naive_utc()).await?;
+            println!("Key {} is retired", kid);
+        }
+        _ => return Err(AppError::BadRequest(KEYS_USAGE.to_string())),
+    }
+
+    Ok(())
+}
+
+/// `auth_session roles ...`: assigns roles outside the API, e.g. to create
+/// the first admin.
+pub async fn roles(config: AppConfig, args: &[String]) -> Result<(), AppError> {
+    let pool = create_pool(&config.database.url, 1).await?;
+    let store = DieselStore::new(pool);
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (command, email, role) = match args.as_slice() {
+        ["show", email] => ("show", *email, None),
+        [command @ ("grant" | "revoke"), email, role] => (*command, *email, Some(*role)),
+        _ => return Err(AppError::BadRequest(ROLES_USAGE.to_string())),
+    };
+
+    let user = store.find_user_by_email(email).await?.ok_or(AppError::NotFound)?;
+    match (command, role) {
+        ("grant", Some(role)) => store.assign_role(user.id, role).await?,
+        ("revoke", Some(role)) => store.revoke_role(user.id, role).await?,
+        _ => {}
+    }
+
+    let grants = store.find_user_grants(user.id).await?;
+    println!("{}\troles: {}\tpermissions: {}", user.email, grants.roles.join(","), grants.permissions.join(","));
+
+    Ok(())
+}
+
+/// `auth_session clients ...`: registers applications that sign users in
+/// through `/oauth/authorize`.
+pub async fn clients(config: AppConfig, args: &[String]) -> Result<(), AppError> {
+    let pool = create_pool(&config.database.url, 1).await?;
+    let store = DieselStore::new(pool);
+
+    let list = |value: &str| -> Vec<String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["show", client_id] => {
+            let client = store.find_oauth_client(client_id).await?.ok_or(AppError::NotFound)?;
+            print_client(&client);
+        }
+        [command @ ("add" | "add-public"), client_id, name, redirect_uris, scopes @ ..] if scopes.len() <= 1 => {
+            let redirect_uris = list(redirect_uris);
+            if redirect_uris.is_empty() {
+                return Err(AppError::BadRequest("At least one redirect URI is required".to_string()));
+            }
+            // Refuse URIs the authorization endpoint couldn't redirect to
+            for uri in &redirect_uris {
+                Url::parse(uri).map_err(|e| AppError::BadRequest(format!("Invalid redirect URI {}: {}", uri, e)))?;
+            }
+            let allowed_scopes = list(scopes.first().copied().unwrap_or("openid,email,profile"));
+
+            let secret = (*command == "add").then(token::generate_opaque_token);
+            let secret_hash = secret.as_deref().map(hashing::hash_password).transpose()?;
+
+            let client = store.create_oauth_client(
+                client_id.to_string(),
+                name.to_string(),
+                secret_hash,
+                redirect_uris,
+                allowed_scopes,
+            ).await?;
+            print_client(&client);
+            if let Some(secret) = secret {
+                println!("Client secret (shown once): {}", secret);
+            }
+        }
+        _ => return Err(AppError::BadRequest(CLIENTS_USAGE.to_string())),
+    }
+
+    Ok(())
+}
+
+fn print_client(client: &OAuthClient) {
+    let kind = if client.client_secret_hash.is_some() { "confidential" } else { "public" };
+    println!(
+        "{}\t{}\t{}\tredirect_uris: {}\tscopes: {}",
+        client.client_id,
+        client.name,
+        kind,
+        client.redirect_uris.join(","),
+        client.allowed_scopes.join(","),
+    );
+}
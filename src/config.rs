@@ -1,12 +1,25 @@
 use std::env;
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use axum::http::{HeaderName, Method};
+use axum_extra::extract::cookie::SameSite;
+use jsonwebtoken::Algorithm;
+use reqwest::Url;
 
 #[derive(Debug, Clone)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub jwt: JwtConfig,
-    pub google_oauth: GoogleOAuthConfig,
+    pub session: SessionConfig,
+    pub cors: CorsConfig,
+    pub mfa: MfaConfig,
+    pub webauthn: WebAuthnConfig,
+    pub registration: RegistrationConfig,
+    pub password_reset: PasswordResetConfig,
+    pub email_verification: EmailVerificationConfig,
+    pub magic_link: MagicLinkConfig,
+    pub mail: MailConfig,
+    pub oauth_providers: Vec<OAuthProviderConfig>,
 }
 
 #[derive(Debug, Clone)]
@@ -23,21 +36,178 @@ pub struct DatabaseConfig {
 
 #[derive(Debug, Clone)]
 pub struct JwtConfig {
+    /// Only used by the HS* algorithms
     pub secret: String,
+    pub algorithm: Algorithm,
+    /// PKCS#8 PEM files for RS256, ES256 and EdDSA
+    pub private_key_path: Option<String>,
+    pub public_key_path: Option<String>,
+    /// `kid` header value; defaults to the key's JWK thumbprint
+    pub key_id: Option<String>,
     pub expiration: i64,
+    pub refresh_expiration: i64,
+    /// Public base URL of this server, advertised as the OpenID Connect issuer
+    /// and required in the `iss` claim
+    pub issuer: String,
+    /// `aud` of the tokens we issue for our own API
+    pub audience: String,
+    /// Audiences this service accepts; other services' tokens are rejected
+    pub allowed_audiences: Vec<String>,
+    /// Clock skew tolerated on `exp` and `nbf`, in seconds
+    pub leeway: u64,
 }
 
+/// Browser sessions: when enabled, the tokens issued on login are also set as
+/// `HttpOnly` cookies so a SPA never has to store them.
 #[derive(Debug, Clone)]
-pub struct GoogleOAuthConfig {
+pub struct SessionConfig {
+    pub enabled: bool,
+    /// Holds the access token; the refresh token goes in `<name>_refresh`
+    pub cookie_name: String,
+    pub domain: Option<String>,
+    /// Cookie `Max-Age` in seconds; unset means the cookies end with the browser session
+    pub max_age: Option<u64>,
+    pub secure: bool,
+    pub same_site: SameSite,
+}
+
+#[derive(Debug, Clone)]
+pub struct MfaConfig {
+    /// Name authenticator apps show next to the account
+    pub issuer: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RegistrationConfig {
+    /// Lets anyone create an account: through `/api/auth/register`, a first
+    /// OAuth login or a first magic link. When off, accounts must already exist.
+    pub open: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PasswordResetConfig {
+    /// Seconds an emailed reset token stays valid
+    pub expiration: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct EmailVerificationConfig {
+    /// Refuse password logins until the email address is verified
+    pub required: bool,
+    /// Seconds an emailed verification link stays valid
+    pub expiration: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MagicLinkConfig {
+    /// Seconds an emailed login link stays valid
+    pub expiration: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MailConfig {
+    pub transport: MailTransport,
+    /// `From` of every message, e.g. `AuthSession <no-reply@example.com>`
+    pub from: String,
+    /// Directory holding the `<name>.txt` and `<name>.html` message templates
+    pub templates_dir: String,
+    /// Public base URL of the pages linked from emails
+    pub app_url: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum MailTransport {
+    Smtp(SmtpConfig),
+    /// Writes each message as an `.eml` file into the directory
+    File { dir: String },
+    /// Logs messages instead of sending them; the development default
+    Log,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub security: SmtpSecurity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// Upgrades a plain connection; usually port 587
+    StartTls,
+    /// Implicit TLS; usually port 465
+    Tls,
+    /// Only for a relay on a trusted network
+    None,
+}
+
+/// The WebAuthn relying party. Passkeys are bound to `rp_id` and stop
+/// working if it changes.
+#[derive(Debug, Clone)]
+pub struct WebAuthnConfig {
+    /// Registrable domain, e.g. `example.com`
+    pub rp_id: String,
+    /// Name authenticators show when creating a passkey
+    pub rp_name: String,
+    /// Exact origins of the pages running the ceremonies, e.g. `https://app.example.com`
+    pub origins: Vec<String>,
+}
+
+/// Which browser origins may call the API. With no origins configured only
+/// same-origin pages can.
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    /// Exact origins, `https://*.example.com` for any subdomain, or `*`
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<Method>,
+    pub allowed_headers: Vec<HeaderName>,
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight response, in seconds
+    pub max_age: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OAuthProviderKind {
+    Google,
+    GitHub,
+    /// Any OpenID Connect provider configured from its issuer URL
+    Oidc,
+}
+
+#[derive(Debug, Clone)]
+pub struct OAuthProviderConfig {
+    /// Route segment and `user_identities.provider` value, e.g. "github"
+    pub name: String,
+    pub kind: OAuthProviderKind,
     pub client_id: String,
     pub client_secret: String,
     pub redirect_url: String,
-    pub auth_url: String,
-    pub token_url: String,
+    pub scopes: Vec<String>,
+    pub use_pkce: bool,
+    /// OIDC only
+    pub issuer_url: Option<String>,
+    /// Overrides for non-OIDC providers, e.g. GitHub Enterprise
+    pub auth_url: Option<String>,
+    pub token_url: Option<String>,
+    pub api_url: Option<String>,
 }
 
 impl AppConfig {
     pub fn new() -> Result<Self> {
+        let audience = env::var("JWT_AUDIENCE").unwrap_or_else(|_| "auth_session".to_string());
+        let mut allowed_audiences: Vec<String> = env::var("JWT_ALLOWED_AUDIENCES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|aud| !aud.is_empty())
+            .map(str::to_string)
+            .collect();
+        if allowed_audiences.is_empty() {
+            allowed_audiences.push(audience.clone());
+        }
+
         Ok(AppConfig {
             server: ServerConfig {
                 host: env::var("SERVER_HOST").context("SERVER_HOST must be set")?,
@@ -54,24 +224,322 @@ impl AppConfig {
                     .context("DATABASE_MAX_CONNECTIONS must be a valid number")?,
             },
             jwt: JwtConfig {
-                secret: env::var("JWT_SECRET").context("JWT_SECRET must be set")?,
+                secret: env::var("JWT_SECRET").unwrap_or_default(),
+                algorithm: env::var("JWT_ALGORITHM")
+                    .unwrap_or_else(|_| "HS256".to_string())
+                    .parse()
+                    .context("JWT_ALGORITHM must be HS256, RS256, ES256 or EdDSA")?,
+                private_key_path: env::var("JWT_PRIVATE_KEY_PATH").ok(),
+                public_key_path: env::var("JWT_PUBLIC_KEY_PATH").ok(),
+                key_id: env::var("JWT_KEY_ID").ok(),
                 expiration: env::var("JWT_EXPIRATION")
                     .context("JWT_EXPIRATION must be set")?
                     .parse()
                     .context("JWT_EXPIRATION must be a valid number")?,
+                // Refresh tokens default to 14 days
+                refresh_expiration: env::var("JWT_REFRESH_EXPIRATION")
+                    .unwrap_or_else(|_| "1209600".to_string())
+                    .parse()
+                    .context("JWT_REFRESH_EXPIRATION must be a valid number")?,
+                issuer: env::var("JWT_ISSUER")
+                    .unwrap_or_else(|_| "http://localhost:8000".to_string())
+                    .trim_end_matches('/')
+                    .to_string(),
+                audience,
+                allowed_audiences,
+                leeway: env::var("JWT_LEEWAY")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .context("JWT_LEEWAY must be a valid number")?,
             },
-            google_oauth: GoogleOAuthConfig {
-                client_id: env::var("GOOGLE_OAUTH_CLIENT_ID")
-                    .context("GOOGLE_OAUTH_CLIENT_ID must be set")?,
-                client_secret: env::var("GOOGLE_OAUTH_CLIENT_SECRET")
-                    .context("GOOGLE_OAUTH_CLIENT_SECRET must be set")?,
-                redirect_url: env::var("GOOGLE_OAUTH_REDIRECT_URL")
-                    .context("GOOGLE_OAUTH_REDIRECT_URL must be set")?,
-                auth_url: env::var("GOOGLE_OAUTH_AUTH_URL")
-                    .context("GOOGLE_OAUTH_AUTH_URL must be set")?,
-                token_url: env::var("GOOGLE_OAUTH_TOKEN_URL")
-                    .context("GOOGLE_OAUTH_TOKEN_URL must be set")?,
+            session: SessionConfig {
+                enabled: env::var("SESSION_COOKIES").is_ok_and(|v| v == "true"),
+                cookie_name: env::var("SESSION_COOKIE_NAME").unwrap_or_else(|_| "session".to_string()),
+                domain: env::var("SESSION_COOKIE_DOMAIN").ok().filter(|d| !d.is_empty()),
+                max_age: env::var("SESSION_COOKIE_MAX_AGE")
+                    .ok()
+                    .filter(|v| !v.is_empty())
+                    .map(|v| v.parse())
+                    .transpose()
+                    .context("SESSION_COOKIE_MAX_AGE must be a valid number")?,
+                // Only turn off for plain-HTTP development
+                secure: env::var("SESSION_COOKIE_SECURE").map_or(true, |v| v != "false"),
+                same_site: match env::var("SESSION_COOKIE_SAME_SITE")
+                    .unwrap_or_else(|_| "lax".to_string())
+                    .to_lowercase()
+                    .as_str()
+                {
+                    "lax" => SameSite::Lax,
+                    "strict" => SameSite::Strict,
+                    "none" => SameSite::None,
+                    other => bail!("SESSION_COOKIE_SAME_SITE must be strict, lax or none, got {}", other),
+                },
             },
+            cors: CorsConfig::from_env()?,
+            mfa: MfaConfig {
+                issuer: env::var("MFA_ISSUER").unwrap_or_else(|_| "auth_session".to_string()),
+            },
+            webauthn: WebAuthnConfig::from_env()?,
+            registration: RegistrationConfig {
+                open: env::var("OPEN_REGISTRATION").map_or(true, |v| v != "false"),
+            },
+            password_reset: PasswordResetConfig {
+                // Reset links default to one hour
+                expiration: env::var("PASSWORD_RESET_EXPIRATION")
+                    .unwrap_or_else(|_| "3600".to_string())
+                    .parse()
+                    .context("PASSWORD_RESET_EXPIRATION must be a valid number")?,
+            },
+            email_verification: EmailVerificationConfig {
+                required: env::var("REQUIRE_EMAIL_VERIFICATION").is_ok_and(|v| v == "true"),
+                // Verification links default to one day
+                expiration: env::var("EMAIL_VERIFICATION_EXPIRATION")
+                    .unwrap_or_else(|_| "86400".to_string())
+                    .parse()
+                    .context("EMAIL_VERIFICATION_EXPIRATION must be a valid number")?,
+            },
+            magic_link: MagicLinkConfig {
+                // Login links default to 15 minutes
+                expiration: env::var("MAGIC_LINK_EXPIRATION")
+                    .unwrap_or_else(|_| "900".to_string())
+                    .parse()
+                    .context("MAGIC_LINK_EXPIRATION must be a valid number")?,
+            },
+            mail: MailConfig::from_env()?,
+            oauth_providers: OAuthProviderConfig::all_from_env()?,
+        })
+    }
+}
+
+impl MailConfig {
+    fn from_env() -> Result<Self> {
+        let transport = match env::var("MAIL_TRANSPORT").unwrap_or_else(|_| "log".to_string()).as_str() {
+            "smtp" => MailTransport::Smtp(SmtpConfig {
+                host: env::var("SMTP_HOST").context("SMTP_HOST must be set for the smtp transport")?,
+                port: env::var("SMTP_PORT")
+                    .unwrap_or_else(|_| "587".to_string())
+                    .parse()
+                    .context("SMTP_PORT must be a valid number")?,
+                username: env::var("SMTP_USERNAME").ok().filter(|v| !v.is_empty()),
+                password: env::var("SMTP_PASSWORD").ok().filter(|v| !v.is_empty()),
+                security: match env::var("SMTP_SECURITY")
+                    .unwrap_or_else(|_| "starttls".to_string())
+                    .to_lowercase()
+                    .as_str()
+                {
+                    "starttls" => SmtpSecurity::StartTls,
+                    "tls" => SmtpSecurity::Tls,
+                    "none" => SmtpSecurity::None,
+                    other => bail!("SMTP_SECURITY must be starttls, tls or none, got {}", other),
+                },
+            }),
+            "file" => MailTransport::File {
+                dir: env::var("MAIL_FILE_DIR").unwrap_or_else(|_| "mail".to_string()),
+            },
+            "log" => MailTransport::Log,
+            other => bail!("MAIL_TRANSPORT must be smtp, file or log, got {}", other),
+        };
+
+        Ok(Self {
+            transport,
+            from: env::var("MAIL_FROM").unwrap_or_else(|_| "AuthSession <no-reply@localhost>".to_string()),
+            templates_dir: env::var("MAIL_TEMPLATES_DIR").unwrap_or_else(|_| "src/templates/email".to_string()),
+            app_url: env::var("MAIL_APP_URL")
+                .unwrap_or_else(|_| "http://localhost:8000".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+        })
+    }
+}
+
+impl WebAuthnConfig {
+    fn from_env() -> Result<Self> {
+        let rp_id = env::var("WEBAUTHN_RP_ID").unwrap_or_else(|_| "localhost".to_string());
+        let origins: Vec<String> = env::var("WEBAUTHN_ORIGINS")
+            .unwrap_or_else(|_| "http://localhost:8000".to_string())
+            .split(',')
+            .map(|origin| origin.trim().trim_end_matches('/').to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+
+        // Browsers refuse an RP ID that isn't the origin's host or a parent of it
+        for origin in &origins {
+            let host = Url::parse(origin)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+                .with_context(|| format!("WEBAUTHN_ORIGINS contains an invalid origin: {}", origin))?;
+            if host != rp_id && !host.ends_with(&format!(".{}", rp_id)) {
+                bail!("WebAuthn origin {} is not on WEBAUTHN_RP_ID {}", origin, rp_id);
+            }
+        }
+
+        Ok(WebAuthnConfig {
+            rp_id,
+            rp_name: env::var("WEBAUTHN_RP_NAME").unwrap_or_else(|_| "auth_session".to_string()),
+            origins,
+        })
+    }
+}
+
+impl CorsConfig {
+    fn from_env() -> Result<Self> {
+        let list = |key: &str, default: &str| -> Vec<String> {
+            env::var(key)
+                .unwrap_or_else(|_| default.to_string())
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+
+        Self::from_lists(
+            list("CORS_ALLOWED_ORIGINS", ""),
+            list("CORS_ALLOWED_METHODS", "GET,POST,PUT,DELETE"),
+            list("CORS_ALLOWED_HEADERS", "authorization,content-type,x-csrf-token"),
+            env::var("CORS_ALLOW_CREDENTIALS").is_ok_and(|v| v == "true"),
+            env::var("CORS_MAX_AGE")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse()
+                .context("CORS_MAX_AGE must be a valid number")?,
+        )
+    }
+
+    /// Checks and parses the comma-separated `CORS_*` lists.
+    pub(crate) fn from_lists(
+        allowed_origins: Vec<String>,
+        allowed_methods: Vec<String>,
+        allowed_headers: Vec<String>,
+        allow_credentials: bool,
+        max_age: u64,
+    ) -> Result<Self> {
+        // Any site could then make requests with the user's cookies
+        if allow_credentials && allowed_origins.iter().any(|origin| origin == "*") {
+            bail!("CORS_ALLOW_CREDENTIALS cannot be combined with CORS_ALLOWED_ORIGINS=*");
+        }
+
+        // Browsers ignore wildcards on credentialed requests, and tower-http
+        // panics on the first request rather than serve them
+        for (key, values) in [("CORS_ALLOWED_METHODS", &allowed_methods), ("CORS_ALLOWED_HEADERS", &allowed_headers)] {
+            if allow_credentials && values.iter().any(|value| value == "*") {
+                bail!("CORS_ALLOW_CREDENTIALS cannot be combined with {}=*; list them explicitly", key);
+            }
+        }
+
+        Ok(CorsConfig {
+            allowed_origins,
+            allowed_methods: allowed_methods
+                .iter()
+                .map(|m| Method::from_bytes(m.to_uppercase().as_bytes()))
+                .collect::<Result<_, _>>()
+                .context("CORS_ALLOWED_METHODS must be HTTP methods")?,
+            allowed_headers: allowed_headers
+                .iter()
+                .map(|h| h.parse())
+                .collect::<Result<_, _>>()
+                .context("CORS_ALLOWED_HEADERS must be header names")?,
+            allow_credentials,
+            max_age,
+        })
+    }
+}
+
+impl OAuthProviderConfig {
+    /// Reads every provider listed in `OAUTH_PROVIDERS`, e.g. "google,github,keycloak".
+    /// The legacy `GOOGLE_OAUTH_*` variables still configure Google on their own.
+    fn all_from_env() -> Result<Vec<Self>> {
+        let mut providers = env::var("OAUTH_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(Self::from_env)
+            .collect::<Result<Vec<_>>>()?;
+
+        if !providers.iter().any(|p| p.name == "google") && env::var("GOOGLE_OAUTH_CLIENT_ID").is_ok() {
+            providers.push(Self::google_from_legacy_env()?);
+        }
+
+        Ok(providers)
+    }
+
+    /// Reads the `OAUTH_<NAME>_*` variables of one provider. The kind defaults
+    /// from well-known names; anything else is treated as OpenID Connect.
+    fn from_env(name: &str) -> Result<Self> {
+        let name = name.to_lowercase();
+        let prefix = format!("OAUTH_{}", name.to_uppercase().replace('-', "_"));
+        let var = |key: &str| {
+            let key = format!("{}_{}", prefix, key);
+            env::var(&key).with_context(|| format!("{} must be set", key))
+        };
+
+        let kind = match var("KIND").ok().as_deref().unwrap_or(name.as_str()) {
+            "google" => OAuthProviderKind::Google,
+            "github" => OAuthProviderKind::GitHub,
+            _ => OAuthProviderKind::Oidc,
+        };
+
+        let issuer_url = match (kind, name.as_str()) {
+            (OAuthProviderKind::Oidc, "microsoft") => Some(var("ISSUER_URL").unwrap_or_else(|_| {
+                let tenant = var("TENANT").unwrap_or_else(|_| "common".to_string());
+                format!("https://login.microsoftonline.com/{}/v2.0", tenant)
+            })),
+            (OAuthProviderKind::Oidc, "gitlab") => Some(var("ISSUER_URL")
+                .unwrap_or_else(|_| "https://gitlab.com".to_string())),
+            (OAuthProviderKind::Oidc, _) => Some(var("ISSUER_URL")?),
+            _ => None,
+        };
+
+        let default_scopes = match kind {
+            OAuthProviderKind::Google => "https://www.googleapis.com/auth/userinfo.email https://www.googleapis.com/auth/userinfo.profile",
+            OAuthProviderKind::GitHub => "read:user user:email",
+            OAuthProviderKind::Oidc => "openid email profile",
+        };
+
+        Ok(OAuthProviderConfig {
+            kind,
+            client_id: var("CLIENT_ID")?,
+            client_secret: var("CLIENT_SECRET")?,
+            redirect_url: var("REDIRECT_URL")?,
+            scopes: var("SCOPES")
+                .unwrap_or_else(|_| default_scopes.to_string())
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+            use_pkce: var("USE_PKCE")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .with_context(|| format!("{}_USE_PKCE must be true or false", prefix))?,
+            issuer_url,
+            auth_url: var("AUTH_URL").ok(),
+            token_url: var("TOKEN_URL").ok(),
+            api_url: var("API_URL").ok(),
+            name,
+        })
+    }
+
+    fn google_from_legacy_env() -> Result<Self> {
+        Ok(OAuthProviderConfig {
+            name: "google".to_string(),
+            kind: OAuthProviderKind::Google,
+            client_id: env::var("GOOGLE_OAUTH_CLIENT_ID")
+                .context("GOOGLE_OAUTH_CLIENT_ID must be set")?,
+            client_secret: env::var("GOOGLE_OAUTH_CLIENT_SECRET")
+                .context("GOOGLE_OAUTH_CLIENT_SECRET must be set")?,
+            redirect_url: env::var("GOOGLE_OAUTH_REDIRECT_URL")
+                .context("GOOGLE_OAUTH_REDIRECT_URL must be set")?,
+            scopes: vec![
+                "https://www.googleapis.com/auth/userinfo.email".to_string(),
+                "https://www.googleapis.com/auth/userinfo.profile".to_string(),
+            ],
+            use_pkce: env::var("GOOGLE_OAUTH_USE_PKCE")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .context("GOOGLE_OAUTH_USE_PKCE must be true or false")?,
+            issuer_url: None,
+            auth_url: env::var("GOOGLE_OAUTH_AUTH_URL").ok(),
+            token_url: env::var("GOOGLE_OAUTH_TOKEN_URL").ok(),
+            api_url: None,
         })
     }
 }
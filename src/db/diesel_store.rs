@@ -1,11 +1,26 @@
+use async_trait::async_trait;
 use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use diesel_async::scoped_futures::ScopedFutureExt;
 use uuid::Uuid;
 use chrono::{Utc, NaiveDateTime};
 use crate::models::user::User;
+use crate::models::user_identity::UserIdentity;
+use crate::models::refresh_token::RefreshToken;
+use crate::models::oauth_client::OAuthClient;
+use crate::models::signing_key::SigningKey;
+use crate::models::role::UserGrants;
+use crate::models::mfa::TotpSecret;
+use crate::models::webauthn_credential::WebAuthnCredential;
 use crate::error::AppError;
-use crate::schema::users;
-use crate::db::DbPool;
+use crate::schema::{
+    email_verification_tokens, magic_link_tokens, oauth_clients, password_reset_tokens, permissions, recovery_codes, refresh_tokens, revoked_tokens, role_permissions, roles,
+    signing_keys, totp_secrets, user_identities, user_roles, users, webauthn_credentials,
+};
+use crate::db::{
+    DbPool, EmailVerificationStore, IdentityStore, MagicLinkStore, MfaStore, OAuthClientStore, PasswordResetStore, RefreshTokenStore, RevocationStore,
+    RoleStore, SigningKeyStore, UserStore, WebAuthnStore,
+};
 
 #[derive(Clone)]
 pub struct DieselStore {
@@ -16,8 +31,11 @@ impl DieselStore {
     pub fn new(pool: DbPool) -> Self {
         Self { pool }
     }
+}
 
-    pub async fn create_user(
+#[async_trait]
+impl UserStore for DieselStore {
+    async fn create_user(
         &self,
         email: String,
         name: String,
@@ -37,7 +55,7 @@ impl DieselStore {
             .first::<User>(&mut conn)
             .await
             .optional()
-            .map_err(|e| AppError::Database(e))?;
+            .map_err(AppError::Database)?;
         tracing::debug!("DB: check email exists took {}ms", check_start.elapsed().as_millis());
 
         if existing.is_some() {
@@ -50,24 +68,44 @@ impl DieselStore {
             email,
             name,
             password_hash,
-            oauth_provider,
-            oauth_id,
             created_at: now,
             updated_at: now,
         };
 
+        // Insert the user and its first identity together so an OAuth signup
+        // never leaves a user without a way to log in
         let insert_start = std::time::Instant::now();
-        let user = diesel::insert_into(users::table)
-            .values(&new_user)
-            .get_result::<User>(&mut conn)
+        let user = conn
+            .transaction::<_, diesel::result::Error, _>(|conn| async move {
+                let user = diesel::insert_into(users::table)
+                    .values(&new_user)
+                    .get_result::<User>(conn)
+                    .await?;
+
+                if let (Some(provider), Some(provider_user_id)) = (oauth_provider, oauth_id) {
+                    diesel::insert_into(user_identities::table)
+                        .values(&NewUserIdentity {
+                            id: Uuid::new_v4(),
+                            user_id: user.id,
+                            provider,
+                            provider_user_id,
+                            email: Some(user.email.clone()),
+                            created_at: now,
+                        })
+                        .execute(conn)
+                        .await?;
+                }
+
+                Ok(user)
+            }.scope_boxed())
             .await
-            .map_err(|e| AppError::Database(e))?;
+            .map_err(AppError::Database)?;
         tracing::debug!("DB: insert user took {}ms", insert_start.elapsed().as_millis());
 
         Ok(user)
     }
 
-    pub async fn find_user_by_email(&self, email: &str) -> Result<Option<User>, AppError> {
+    async fn find_user_by_email(&self, email: &str) -> Result<Option<User>, AppError> {
         let pool_start = std::time::Instant::now();
         let mut conn = self.pool.get().await
             .map_err(|e| AppError::Pool(e.to_string()))?;
@@ -79,13 +117,13 @@ impl DieselStore {
             .first::<User>(&mut conn)
             .await
             .optional()
-            .map_err(|e| AppError::Database(e))?;
+            .map_err(AppError::Database)?;
         tracing::debug!("DB: find user query took {}ms", query_start.elapsed().as_millis());
 
         Ok(user)
     }
 
-    pub async fn find_user_by_oauth(
+    async fn find_user_by_oauth(
         &self,
         oauth_provider: &str,
         oauth_id: &str,
@@ -94,17 +132,19 @@ impl DieselStore {
             .map_err(|e| AppError::Pool(e.to_string()))?;
 
         let user = users::table
-            .filter(users::oauth_provider.eq(oauth_provider))
-            .filter(users::oauth_id.eq(oauth_id))
+            .inner_join(user_identities::table)
+            .filter(user_identities::provider.eq(oauth_provider))
+            .filter(user_identities::provider_user_id.eq(oauth_id))
+            .select(User::as_select())
             .first::<User>(&mut conn)
             .await
             .optional()
-            .map_err(|e| AppError::Database(e))?;
+            .map_err(AppError::Database)?;
 
         Ok(user)
     }
 
-    pub async fn find_user_by_id(&self, id: Uuid) -> Result<Option<User>, AppError> {
+    async fn find_user_by_id(&self, id: Uuid) -> Result<Option<User>, AppError> {
         let mut conn = self.pool.get().await
             .map_err(|e| AppError::Pool(e.to_string()))?;
 
@@ -113,12 +153,12 @@ impl DieselStore {
             .first::<User>(&mut conn)
             .await
             .optional()
-            .map_err(|e| AppError::Database(e))?;
+            .map_err(AppError::Database)?;
 
         Ok(user)
     }
 
-    pub async fn update_user_password(&self, id: Uuid, new_password_hash: String) -> Result<User, AppError> {
+    async fn update_user_password(&self, id: Uuid, new_password_hash: String) -> Result<User, AppError> {
         let mut conn = self.pool.get().await
             .map_err(|e| AppError::Pool(e.to_string()))?;
 
@@ -131,24 +171,1079 @@ impl DieselStore {
             ))
             .get_result::<User>(&mut conn)
             .await
-            .map_err(|e| AppError::Database(e))?;
+            .map_err(AppError::Database)?;
 
         Ok(user)
     }
 
-    pub async fn delete_user(&self, id: Uuid) -> Result<(), AppError> {
+    async fn clear_user_password(&self, id: Uuid) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        diesel::update(users::table.filter(users::id.eq(id)))
+            .set((
+                users::password_hash.eq(None::<String>),
+                users::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    async fn mark_email_verified(&self, id: Uuid) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let now = Utc::now().naive_utc();
+
+        diesel::update(
+            users::table
+                .filter(users::id.eq(id))
+                .filter(users::email_verified_at.is_null()),
+        )
+        .set((
+            users::email_verified_at.eq(Some(now)),
+            users::updated_at.eq(now),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    async fn delete_user(&self, id: Uuid) -> Result<(), AppError> {
         let mut conn = self.pool.get().await
             .map_err(|e| AppError::Pool(e.to_string()))?;
 
         diesel::delete(users::table.filter(users::id.eq(id)))
             .execute(&mut conn)
             .await
-            .map_err(|e| AppError::Database(e))?;
+            .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl IdentityStore for DieselStore {
+    async fn link_identity(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        provider_user_id: &str,
+        email: Option<String>,
+    ) -> Result<UserIdentity, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let existing = user_identities::table
+            .filter(user_identities::provider.eq(provider))
+            .filter(user_identities::provider_user_id.eq(provider_user_id))
+            .first::<UserIdentity>(&mut conn)
+            .await
+            .optional()
+            .map_err(AppError::Database)?;
+
+        if let Some(existing) = existing {
+            if existing.user_id == user_id {
+                return Ok(existing);
+            }
+            return Err(AppError::BadRequest(format!(
+                "This {} account is already linked to another user",
+                provider
+            )));
+        }
+
+        let already_linked = user_identities::table
+            .filter(user_identities::user_id.eq(user_id))
+            .filter(user_identities::provider.eq(provider))
+            .first::<UserIdentity>(&mut conn)
+            .await
+            .optional()
+            .map_err(AppError::Database)?;
+
+        if already_linked.is_some() {
+            return Err(AppError::BadRequest(format!("A {} account is already linked", provider)));
+        }
+
+        let identity = diesel::insert_into(user_identities::table)
+            .values(&NewUserIdentity {
+                id: Uuid::new_v4(),
+                user_id,
+                provider: provider.to_string(),
+                provider_user_id: provider_user_id.to_string(),
+                email,
+                created_at: Utc::now().naive_utc(),
+            })
+            .get_result::<UserIdentity>(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(identity)
+    }
+
+    async fn find_identities_by_user(&self, user_id: Uuid) -> Result<Vec<UserIdentity>, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let identities = user_identities::table
+            .filter(user_identities::user_id.eq(user_id))
+            .order(user_identities::created_at.asc())
+            .load::<UserIdentity>(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(identities)
+    }
+
+    async fn unlink_identity(&self, user_id: Uuid, provider: &str) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let provider = provider.to_string();
+        conn.transaction::<_, AppError, _>(|conn| async move {
+            // Lock the user row so concurrent unlinks can't both pass the check
+            let user = users::table
+                .find(user_id)
+                .for_update()
+                .first::<User>(conn)
+                .await
+                .optional()?
+                .ok_or(AppError::NotFound)?;
+
+            let identities = user_identities::table
+                .filter(user_identities::user_id.eq(user_id))
+                .select(user_identities::provider)
+                .load::<String>(conn)
+                .await?;
+
+            if !identities.contains(&provider) {
+                return Err(AppError::NotFound);
+            }
+
+            if identities.len() == 1 && user.password_hash.is_none() {
+                return Err(AppError::BadRequest("Cannot remove the last login method".to_string()));
+            }
+
+            diesel::delete(
+                user_identities::table
+                    .filter(user_identities::user_id.eq(user_id))
+                    .filter(user_identities::provider.eq(&provider)),
+            )
+            .execute(conn)
+            .await?;
+
+            Ok(())
+        }.scope_boxed())
+        .await
+    }
+}
+
+#[async_trait]
+impl RefreshTokenStore for DieselStore {
+    async fn create_refresh_token(
+        &self,
+        user_id: Uuid,
+        family_id: Uuid,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<RefreshToken, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let new_token = NewRefreshToken {
+            id: Uuid::new_v4(),
+            user_id,
+            family_id,
+            token_hash,
+            expires_at,
+            created_at: Utc::now().naive_utc(),
+        };
+
+        let token = diesel::insert_into(refresh_tokens::table)
+            .values(&new_token)
+            .get_result::<RefreshToken>(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(token)
+    }
+
+    async fn find_refresh_token_by_hash(&self, token_hash: &str) -> Result<Option<RefreshToken>, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let token = refresh_tokens::table
+            .filter(refresh_tokens::token_hash.eq(token_hash))
+            .first::<RefreshToken>(&mut conn)
+            .await
+            .optional()
+            .map_err(AppError::Database)?;
+
+        Ok(token)
+    }
+
+    async fn consume_refresh_token(&self, id: Uuid) -> Result<bool, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        // Conditional update so two concurrent refreshes can't both succeed
+        let updated = diesel::update(
+            refresh_tokens::table
+                .filter(refresh_tokens::id.eq(id))
+                .filter(refresh_tokens::used_at.is_null()),
+        )
+        .set(refresh_tokens::used_at.eq(Some(Utc::now().naive_utc())))
+        .execute(&mut conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(updated == 1)
+    }
+
+    async fn revoke_refresh_token_family(&self, family_id: Uuid) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        diesel::update(
+            refresh_tokens::table
+                .filter(refresh_tokens::family_id.eq(family_id))
+                .filter(refresh_tokens::revoked_at.is_null()),
+        )
+        .set(refresh_tokens::revoked_at.eq(Some(Utc::now().naive_utc())))
+        .execute(&mut conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    async fn revoke_user_refresh_tokens(&self, user_id: Uuid) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        diesel::update(
+            refresh_tokens::table
+                .filter(refresh_tokens::user_id.eq(user_id))
+                .filter(refresh_tokens::revoked_at.is_null()),
+        )
+        .set(refresh_tokens::revoked_at.eq(Some(Utc::now().naive_utc())))
+        .execute(&mut conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RevocationStore for DieselStore {
+    async fn revoke_token(&self, jti: Uuid, user_id: Uuid, expires_at: NaiveDateTime) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        diesel::insert_into(revoked_tokens::table)
+            .values((
+                revoked_tokens::jti.eq(jti),
+                revoked_tokens::user_id.eq(user_id),
+                revoked_tokens::expires_at.eq(expires_at),
+                revoked_tokens::revoked_at.eq(Utc::now().naive_utc()),
+            ))
+            .on_conflict_do_nothing()
+            .execute(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    async fn list_revoked_tokens(&self) -> Result<Vec<(Uuid, NaiveDateTime)>, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let revoked = revoked_tokens::table
+            .filter(revoked_tokens::expires_at.gt(Utc::now().naive_utc()))
+            .select((revoked_tokens::jti, revoked_tokens::expires_at))
+            .load::<(Uuid, NaiveDateTime)>(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(revoked)
+    }
+
+    async fn set_tokens_valid_after(&self, user_id: Uuid, valid_after: NaiveDateTime) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        diesel::update(users::table.filter(users::id.eq(user_id)))
+            .set(users::tokens_valid_after.eq(Some(valid_after)))
+            .execute(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    async fn list_token_cutoffs(&self) -> Result<Vec<(Uuid, NaiveDateTime)>, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let cutoffs = users::table
+            .filter(users::tokens_valid_after.is_not_null())
+            .select((users::id, users::tokens_valid_after.assume_not_null()))
+            .load::<(Uuid, NaiveDateTime)>(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(cutoffs)
+    }
+
+    async fn purge_expired_revocations(&self) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        diesel::delete(revoked_tokens::table.filter(revoked_tokens::expires_at.le(Utc::now().naive_utc())))
+            .execute(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OAuthClientStore for DieselStore {
+    async fn create_oauth_client(
+        &self,
+        client_id: String,
+        name: String,
+        client_secret_hash: Option<String>,
+        redirect_uris: Vec<String>,
+        allowed_scopes: Vec<String>,
+    ) -> Result<OAuthClient, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let existing = oauth_clients::table
+            .filter(oauth_clients::client_id.eq(&client_id))
+            .first::<OAuthClient>(&mut conn)
+            .await
+            .optional()
+            .map_err(AppError::Database)?;
+
+        if existing.is_some() {
+            return Err(AppError::BadRequest("Client ID already exists".to_string()));
+        }
+
+        let new_client = NewOAuthClient {
+            id: Uuid::new_v4(),
+            client_id,
+            name,
+            client_secret_hash,
+            redirect_uris,
+            allowed_scopes,
+            created_at: Utc::now().naive_utc(),
+        };
+
+        let client = diesel::insert_into(oauth_clients::table)
+            .values(&new_client)
+            .get_result::<OAuthClient>(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(client)
+    }
+
+    async fn find_oauth_client(&self, client_id: &str) -> Result<Option<OAuthClient>, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let client = oauth_clients::table
+            .filter(oauth_clients::client_id.eq(client_id))
+            .first::<OAuthClient>(&mut conn)
+            .await
+            .optional()
+            .map_err(AppError::Database)?;
+
+        Ok(client)
+    }
+}
+
+#[async_trait]
+impl SigningKeyStore for DieselStore {
+    async fn create_signing_key(
+        &self,
+        kid: String,
+        algorithm: String,
+        private_key_path: Option<String>,
+        public_key_path: Option<String>,
+        active: bool,
+    ) -> Result<SigningKey, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let existing = signing_keys::table
+            .find(&kid)
+            .first::<SigningKey>(&mut conn)
+            .await
+            .optional()
+            .map_err(AppError::Database)?;
+
+        if existing.is_some() {
+            return Err(AppError::BadRequest(format!("Signing key {} already exists", kid)));
+        }
+
+        let new_key = NewSigningKey {
+            kid,
+            algorithm,
+            private_key_path,
+            public_key_path,
+            active,
+            created_at: Utc::now().naive_utc(),
+        };
+
+        let key = diesel::insert_into(signing_keys::table)
+            .values(&new_key)
+            .get_result::<SigningKey>(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(key)
+    }
+
+    async fn list_signing_keys(&self) -> Result<Vec<SigningKey>, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let keys = signing_keys::table
+            .order(signing_keys::created_at.asc())
+            .load::<SigningKey>(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(keys)
+    }
+
+    async fn promote_signing_key(&self, kid: &str, previous_not_after: NaiveDateTime) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let kid = kid.to_string();
+        conn.transaction::<_, AppError, _>(|conn| async move {
+            let key = signing_keys::table
+                .find(&kid)
+                .for_update()
+                .first::<SigningKey>(conn)
+                .await
+                .optional()?
+                .ok_or(AppError::NotFound)?;
+
+            if key.active {
+                return Ok(());
+            }
+
+            if key.not_after.is_some_and(|not_after| not_after <= Utc::now().naive_utc()) {
+                return Err(AppError::BadRequest(format!("Signing key {} has been retired", kid)));
+            }
+
+            // Demote first: the partial unique index allows one active key
+            diesel::update(signing_keys::table.filter(signing_keys::active.eq(true)))
+                .set((
+                    signing_keys::active.eq(false),
+                    signing_keys::not_after.eq(Some(previous_not_after)),
+                ))
+                .execute(conn)
+                .await?;
+
+            diesel::update(signing_keys::table.find(&kid))
+                .set((
+                    signing_keys::active.eq(true),
+                    signing_keys::not_after.eq(None::<NaiveDateTime>),
+                ))
+                .execute(conn)
+                .await?;
+
+            Ok(())
+        }.scope_boxed())
+        .await
+    }
+
+    async fn retire_signing_key(&self, kid: &str, not_after: NaiveDateTime) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let key = signing_keys::table
+            .find(kid)
+            .first::<SigningKey>(&mut conn)
+            .await
+            .optional()
+            .map_err(AppError::Database)?
+            .ok_or(AppError::NotFound)?;
+
+        if key.active {
+            return Err(AppError::BadRequest(
+                "Cannot retire the active signing key; promote another key first".to_string()
+            ));
+        }
+
+        diesel::update(signing_keys::table.find(kid))
+            .set(signing_keys::not_after.eq(Some(not_after)))
+            .execute(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RoleStore for DieselStore {
+    async fn find_user_grants(&self, user_id: Uuid) -> Result<UserGrants, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let roles = user_roles::table
+            .inner_join(roles::table)
+            .filter(user_roles::user_id.eq(user_id))
+            .select(roles::name)
+            .order(roles::name.asc())
+            .load::<String>(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        let permissions = role_permissions::table
+            .inner_join(permissions::table)
+            .filter(role_permissions::role_id.eq_any(
+                user_roles::table
+                    .filter(user_roles::user_id.eq(user_id))
+                    .select(user_roles::role_id),
+            ))
+            .select(permissions::name)
+            .distinct()
+            .order(permissions::name.asc())
+            .load::<String>(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(UserGrants { roles, permissions })
+    }
+
+    async fn assign_role(&self, user_id: Uuid, role: &str) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let role_id = roles::table
+            .filter(roles::name.eq(role))
+            .select(roles::id)
+            .first::<Uuid>(&mut conn)
+            .await
+            .optional()
+            .map_err(AppError::Database)?
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown role: {}", role)))?;
+
+        let new_user_role = NewUserRole {
+            user_id,
+            role_id,
+            created_at: Utc::now().naive_utc(),
+        };
+
+        diesel::insert_into(user_roles::table)
+            .values(&new_user_role)
+            .on_conflict_do_nothing()
+            .execute(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    async fn revoke_role(&self, user_id: Uuid, role: &str) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let deleted = diesel::delete(
+            user_roles::table
+                .filter(user_roles::user_id.eq(user_id))
+                .filter(user_roles::role_id.eq_any(
+                    roles::table.filter(roles::name.eq(role)).select(roles::id),
+                )),
+        )
+        .execute(&mut conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        if deleted == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MfaStore for DieselStore {
+    async fn find_totp_secret(&self, user_id: Uuid) -> Result<Option<TotpSecret>, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let secret = totp_secrets::table
+            .find(user_id)
+            .first::<TotpSecret>(&mut conn)
+            .await
+            .optional()
+            .map_err(AppError::Database)?;
+
+        Ok(secret)
+    }
+
+    async fn set_pending_totp_secret(&self, user_id: Uuid, secret: String) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        conn.transaction::<_, AppError, _>(|conn| async move {
+            let existing = totp_secrets::table
+                .find(user_id)
+                .for_update()
+                .first::<TotpSecret>(conn)
+                .await
+                .optional()?;
+
+            if existing.is_some_and(|existing| existing.is_enabled()) {
+                return Err(AppError::Conflict {
+                    code: "mfa_already_enabled",
+                    message: "Two-factor authentication is already enabled".to_string(),
+                });
+            }
+
+            let new_secret = NewTotpSecret {
+                user_id,
+                secret,
+                created_at: Utc::now().naive_utc(),
+            };
+
+            diesel::insert_into(totp_secrets::table)
+                .values(&new_secret)
+                .on_conflict(totp_secrets::user_id)
+                .do_update()
+                .set((
+                    totp_secrets::secret.eq(&new_secret.secret),
+                    totp_secrets::last_used_step.eq(None::<i64>),
+                    totp_secrets::created_at.eq(new_secret.created_at),
+                ))
+                .execute(conn)
+                .await?;
+
+            Ok(())
+        }.scope_boxed())
+        .await
+    }
+
+    async fn enable_totp(&self, user_id: Uuid, step: i64, recovery_code_hashes: Vec<String>) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        conn.transaction::<_, AppError, _>(|conn| async move {
+            let confirmed = diesel::update(
+                totp_secrets::table
+                    .filter(totp_secrets::user_id.eq(user_id))
+                    .filter(totp_secrets::confirmed_at.is_null()),
+            )
+            .set((
+                totp_secrets::confirmed_at.eq(Some(Utc::now().naive_utc())),
+                totp_secrets::last_used_step.eq(Some(step)),
+            ))
+            .execute(conn)
+            .await?;
+
+            if confirmed == 0 {
+                return Err(AppError::BadRequest("No pending two-factor enrollment".to_string()));
+            }
+
+            insert_recovery_codes(conn, user_id, recovery_code_hashes).await?;
+            Ok(())
+        }.scope_boxed())
+        .await
+    }
+
+    async fn use_totp_step(&self, user_id: Uuid, step: i64) -> Result<bool, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        // Conditional update so the same code can't be accepted twice
+        let updated = diesel::update(
+            totp_secrets::table
+                .filter(totp_secrets::user_id.eq(user_id))
+                .filter(totp_secrets::last_used_step.is_null().or(totp_secrets::last_used_step.lt(step))),
+        )
+        .set(totp_secrets::last_used_step.eq(Some(step)))
+        .execute(&mut conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(updated == 1)
+    }
+
+    async fn disable_totp(&self, user_id: Uuid) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        conn.transaction::<_, AppError, _>(|conn| async move {
+            diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(user_id)))
+                .execute(conn)
+                .await?;
+            diesel::delete(totp_secrets::table.find(user_id))
+                .execute(conn)
+                .await?;
+
+            Ok(())
+        }.scope_boxed())
+        .await
+    }
+
+    async fn replace_recovery_codes(&self, user_id: Uuid, code_hashes: Vec<String>) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        conn.transaction::<_, AppError, _>(|conn| async move {
+            insert_recovery_codes(conn, user_id, code_hashes).await?;
+            Ok(())
+        }.scope_boxed())
+        .await
+    }
+
+    async fn use_recovery_code(&self, user_id: Uuid, code_hash: &str) -> Result<bool, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let updated = diesel::update(
+            recovery_codes::table
+                .filter(recovery_codes::user_id.eq(user_id))
+                .filter(recovery_codes::code_hash.eq(code_hash))
+                .filter(recovery_codes::used_at.is_null()),
+        )
+        .set(recovery_codes::used_at.eq(Some(Utc::now().naive_utc())))
+        .execute(&mut conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(updated >= 1)
+    }
+
+    async fn count_unused_recovery_codes(&self, user_id: Uuid) -> Result<i64, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let count = recovery_codes::table
+            .filter(recovery_codes::user_id.eq(user_id))
+            .filter(recovery_codes::used_at.is_null())
+            .count()
+            .get_result::<i64>(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(count)
+    }
+}
+
+#[async_trait]
+impl WebAuthnStore for DieselStore {
+    async fn create_webauthn_credential(
+        &self,
+        user_id: Uuid,
+        credential_id: String,
+        public_key: Vec<u8>,
+        sign_count: i64,
+        name: String,
+    ) -> Result<WebAuthnCredential, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let new_credential = NewWebAuthnCredential {
+            id: Uuid::new_v4(),
+            user_id,
+            credential_id,
+            public_key,
+            sign_count,
+            name,
+            created_at: Utc::now().naive_utc(),
+        };
+
+        diesel::insert_into(webauthn_credentials::table)
+            .values(&new_credential)
+            .on_conflict_do_nothing()
+            .get_result::<WebAuthnCredential>(&mut conn)
+            .await
+            .optional()
+            .map_err(AppError::Database)?
+            .ok_or_else(|| AppError::Conflict {
+                code: "passkey_already_registered",
+                message: "This passkey is already registered".to_string(),
+            })
+    }
+
+    async fn find_webauthn_credential(&self, credential_id: &str) -> Result<Option<WebAuthnCredential>, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let credential = webauthn_credentials::table
+            .filter(webauthn_credentials::credential_id.eq(credential_id))
+            .first::<WebAuthnCredential>(&mut conn)
+            .await
+            .optional()
+            .map_err(AppError::Database)?;
+
+        Ok(credential)
+    }
+
+    async fn find_webauthn_credentials_by_user(&self, user_id: Uuid) -> Result<Vec<WebAuthnCredential>, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let credentials = webauthn_credentials::table
+            .filter(webauthn_credentials::user_id.eq(user_id))
+            .order(webauthn_credentials::created_at.asc())
+            .load::<WebAuthnCredential>(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(credentials)
+    }
+
+    async fn update_webauthn_sign_count(&self, id: Uuid, sign_count: i64) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        diesel::update(webauthn_credentials::table.find(id))
+            .set((
+                webauthn_credentials::sign_count.eq(sign_count),
+                webauthn_credentials::last_used_at.eq(Some(Utc::now().naive_utc())),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    async fn delete_webauthn_credential(&self, user_id: Uuid, id: Uuid) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let deleted = diesel::delete(
+            webauthn_credentials::table
+                .filter(webauthn_credentials::id.eq(id))
+                .filter(webauthn_credentials::user_id.eq(user_id)),
+        )
+        .execute(&mut conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        if deleted == 0 {
+            return Err(AppError::NotFound);
+        }
 
         Ok(())
     }
 }
 
+#[async_trait]
+impl PasswordResetStore for DieselStore {
+    async fn create_password_reset_token(
+        &self,
+        user_id: Uuid,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let new_token = NewPasswordResetToken {
+            id: Uuid::new_v4(),
+            user_id,
+            token_hash,
+            expires_at,
+            created_at: Utc::now().naive_utc(),
+        };
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| async move {
+            diesel::delete(password_reset_tokens::table.filter(password_reset_tokens::user_id.eq(user_id)))
+                .execute(conn)
+                .await?;
+
+            diesel::insert_into(password_reset_tokens::table)
+                .values(&new_token)
+                .execute(conn)
+                .await?;
+
+            Ok(())
+        }.scope_boxed())
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn consume_password_reset_token(&self, token_hash: &str) -> Result<Option<Uuid>, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let now = Utc::now().naive_utc();
+        // Conditional update so two concurrent resets can't both succeed
+        let user_id = diesel::update(
+            password_reset_tokens::table
+                .filter(password_reset_tokens::token_hash.eq(token_hash))
+                .filter(password_reset_tokens::used_at.is_null())
+                .filter(password_reset_tokens::expires_at.gt(now)),
+        )
+        .set(password_reset_tokens::used_at.eq(Some(now)))
+        .returning(password_reset_tokens::user_id)
+        .get_result::<Uuid>(&mut conn)
+        .await
+        .optional()
+        .map_err(AppError::Database)?;
+
+        Ok(user_id)
+    }
+}
+
+#[async_trait]
+impl EmailVerificationStore for DieselStore {
+    async fn create_email_verification_token(
+        &self,
+        user_id: Uuid,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let new_token = NewEmailVerificationToken {
+            id: Uuid::new_v4(),
+            user_id,
+            token_hash,
+            expires_at,
+            created_at: Utc::now().naive_utc(),
+        };
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| async move {
+            diesel::delete(email_verification_tokens::table.filter(email_verification_tokens::user_id.eq(user_id)))
+                .execute(conn)
+                .await?;
+
+            diesel::insert_into(email_verification_tokens::table)
+                .values(&new_token)
+                .execute(conn)
+                .await?;
+
+            Ok(())
+        }.scope_boxed())
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn consume_email_verification_token(&self, token_hash: &str) -> Result<Option<Uuid>, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let user_id = diesel::delete(
+            email_verification_tokens::table
+                .filter(email_verification_tokens::token_hash.eq(token_hash))
+                .filter(email_verification_tokens::expires_at.gt(Utc::now().naive_utc())),
+        )
+        .returning(email_verification_tokens::user_id)
+        .get_result::<Uuid>(&mut conn)
+        .await
+        .optional()
+        .map_err(AppError::Database)?;
+
+        Ok(user_id)
+    }
+}
+
+#[async_trait]
+impl MagicLinkStore for DieselStore {
+    async fn create_magic_link_token(
+        &self,
+        email: String,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let new_token = NewMagicLinkToken {
+            id: Uuid::new_v4(),
+            email,
+            token_hash,
+            expires_at,
+            created_at: Utc::now().naive_utc(),
+        };
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| async move {
+            diesel::delete(magic_link_tokens::table.filter(magic_link_tokens::email.eq(&new_token.email)))
+                .execute(conn)
+                .await?;
+
+            diesel::insert_into(magic_link_tokens::table)
+                .values(&new_token)
+                .execute(conn)
+                .await?;
+
+            Ok(())
+        }.scope_boxed())
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn consume_magic_link_token(&self, token_hash: &str) -> Result<Option<String>, AppError> {
+        let mut conn = self.pool.get().await
+            .map_err(|e| AppError::Pool(e.to_string()))?;
+
+        let email = diesel::delete(
+            magic_link_tokens::table
+                .filter(magic_link_tokens::token_hash.eq(token_hash))
+                .filter(magic_link_tokens::expires_at.gt(Utc::now().naive_utc())),
+        )
+        .returning(magic_link_tokens::email)
+        .get_result::<String>(&mut conn)
+        .await
+        .optional()
+        .map_err(AppError::Database)?;
+
+        Ok(email)
+    }
+}
+
+/// Replaces the user's recovery codes; run inside a transaction.
+async fn insert_recovery_codes(
+    conn: &mut AsyncPgConnection,
+    user_id: Uuid,
+    code_hashes: Vec<String>,
+) -> Result<(), diesel::result::Error> {
+    diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(user_id)))
+        .execute(conn)
+        .await?;
+
+    let now = Utc::now().naive_utc();
+    let new_codes: Vec<NewRecoveryCode> = code_hashes
+        .into_iter()
+        .map(|code_hash| NewRecoveryCode {
+            id: Uuid::new_v4(),
+            user_id,
+            code_hash,
+            created_at: now,
+        })
+        .collect();
+
+    diesel::insert_into(recovery_codes::table)
+        .values(&new_codes)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
 #[derive(Insertable)]
 #[diesel(table_name = users)]
 struct NewUser {
@@ -156,8 +1251,118 @@ struct NewUser {
     email: String,
     name: String,
     password_hash: Option<String>,
-    oauth_provider: Option<String>,
-    oauth_id: Option<String>,
     created_at: NaiveDateTime,
     updated_at: NaiveDateTime,
 }
+
+#[derive(Insertable)]
+#[diesel(table_name = user_identities)]
+struct NewUserIdentity {
+    id: Uuid,
+    user_id: Uuid,
+    provider: String,
+    provider_user_id: String,
+    email: Option<String>,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = refresh_tokens)]
+struct NewRefreshToken {
+    id: Uuid,
+    user_id: Uuid,
+    family_id: Uuid,
+    token_hash: String,
+    expires_at: NaiveDateTime,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = oauth_clients)]
+struct NewOAuthClient {
+    id: Uuid,
+    client_id: String,
+    name: String,
+    client_secret_hash: Option<String>,
+    redirect_uris: Vec<String>,
+    allowed_scopes: Vec<String>,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = signing_keys)]
+struct NewSigningKey {
+    kid: String,
+    algorithm: String,
+    private_key_path: Option<String>,
+    public_key_path: Option<String>,
+    active: bool,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = user_roles)]
+struct NewUserRole {
+    user_id: Uuid,
+    role_id: Uuid,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = totp_secrets)]
+struct NewTotpSecret {
+    user_id: Uuid,
+    secret: String,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = recovery_codes)]
+struct NewRecoveryCode {
+    id: Uuid,
+    user_id: Uuid,
+    code_hash: String,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = webauthn_credentials)]
+struct NewWebAuthnCredential {
+    id: Uuid,
+    user_id: Uuid,
+    credential_id: String,
+    public_key: Vec<u8>,
+    sign_count: i64,
+    name: String,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = password_reset_tokens)]
+struct NewPasswordResetToken {
+    id: Uuid,
+    user_id: Uuid,
+    token_hash: String,
+    expires_at: NaiveDateTime,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = email_verification_tokens)]
+struct NewEmailVerificationToken {
+    id: Uuid,
+    user_id: Uuid,
+    token_hash: String,
+    expires_at: NaiveDateTime,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = magic_link_tokens)]
+struct NewMagicLinkToken {
+    id: Uuid,
+    email: String,
+    token_hash: String,
+    expires_at: NaiveDateTime,
+    created_at: NaiveDateTime,
+}
@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+use crate::error::AppError;
+
+/// Persistence of hashed email verification tokens.
+#[async_trait]
+pub trait EmailVerificationStore: Send + Sync {
+    /// Stores a new token, replacing any the user was sent before so only
+    /// the latest link works.
+    async fn create_email_verification_token(
+        &self,
+        user_id: Uuid,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), AppError>;
+
+    /// Deletes the token and returns its user. Returns `None` if it is
+    /// unknown or expired.
+    async fn consume_email_verification_token(&self, token_hash: &str) -> Result<Option<Uuid>, AppError>;
+}
@@ -0,0 +1,23 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+use crate::models::user_identity::UserIdentity;
+use crate::error::AppError;
+
+/// Persistence of the OAuth logins linked to a user. A user can hold a
+/// password plus at most one identity per provider.
+#[async_trait]
+pub trait IdentityStore: Send + Sync {
+    async fn link_identity(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        provider_user_id: &str,
+        email: Option<String>,
+    ) -> Result<UserIdentity, AppError>;
+
+    async fn find_identities_by_user(&self, user_id: Uuid) -> Result<Vec<UserIdentity>, AppError>;
+
+    /// Removes the user's `provider` identity, refusing if it is their last
+    /// remaining way to log in.
+    async fn unlink_identity(&self, user_id: Uuid, provider: &str) -> Result<(), AppError>;
+}
@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use crate::error::AppError;
+
+/// Persistence of hashed, single-use login links. Links are keyed by email
+/// rather than user, since the address may not have an account yet.
+#[async_trait]
+pub trait MagicLinkStore: Send + Sync {
+    /// Stores a new token, replacing any link sent to the address before so
+    /// only the latest email works.
+    async fn create_magic_link_token(
+        &self,
+        email: String,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), AppError>;
+
+    /// Deletes the token and returns the address it was sent to. Returns
+    /// `None` if it is unknown or expired.
+    async fn consume_magic_link_token(&self, token_hash: &str) -> Result<Option<String>, AppError>;
+}
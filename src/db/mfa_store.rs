@@ -0,0 +1,33 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+use crate::models::mfa::TotpSecret;
+use crate::error::AppError;
+
+/// Persistence of TOTP secrets and hashed recovery codes.
+#[async_trait]
+pub trait MfaStore: Send + Sync {
+    async fn find_totp_secret(&self, user_id: Uuid) -> Result<Option<TotpSecret>, AppError>;
+
+    /// Starts enrollment with a new unconfirmed secret, replacing any earlier
+    /// pending one. Fails with a conflict once 2FA is enabled.
+    async fn set_pending_totp_secret(&self, user_id: Uuid, secret: String) -> Result<(), AppError>;
+
+    /// Enables 2FA: confirms the pending secret, records `step` as used and
+    /// replaces the user's recovery codes.
+    async fn enable_totp(&self, user_id: Uuid, step: i64, recovery_code_hashes: Vec<String>) -> Result<(), AppError>;
+
+    /// Records `step` as used. Returns `false` if it isn't later than the
+    /// last step used, which callers must treat as a replayed code.
+    async fn use_totp_step(&self, user_id: Uuid, step: i64) -> Result<bool, AppError>;
+
+    /// Removes the secret and every recovery code.
+    async fn disable_totp(&self, user_id: Uuid) -> Result<(), AppError>;
+
+    async fn replace_recovery_codes(&self, user_id: Uuid, code_hashes: Vec<String>) -> Result<(), AppError>;
+
+    /// Marks the code as used. Returns `false` if it doesn't exist or was
+    /// already used.
+    async fn use_recovery_code(&self, user_id: Uuid, code_hash: &str) -> Result<bool, AppError>;
+
+    async fn count_unused_recovery_codes(&self, user_id: Uuid) -> Result<i64, AppError>;
+}
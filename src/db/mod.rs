@@ -1,7 +1,31 @@
 pub mod pool;
+pub mod user_store;
+pub mod identity_store;
+pub mod oauth_client_store;
+pub mod refresh_token_store;
+pub mod revocation_store;
+pub mod role_store;
+pub mod mfa_store;
+pub mod webauthn_store;
+pub mod password_reset_store;
+pub mod email_verification_store;
+pub mod magic_link_store;
+pub mod signing_key_store;
 pub mod store;
 pub mod diesel_store;
 
 pub use pool::{DbPool, create_pool};
+pub use user_store::UserStore;
+pub use identity_store::IdentityStore;
+pub use oauth_client_store::OAuthClientStore;
+pub use refresh_token_store::RefreshTokenStore;
+pub use revocation_store::RevocationStore;
+pub use role_store::RoleStore;
+pub use mfa_store::MfaStore;
+pub use webauthn_store::WebAuthnStore;
+pub use password_reset_store::PasswordResetStore;
+pub use email_verification_store::EmailVerificationStore;
+pub use magic_link_store::MagicLinkStore;
+pub use signing_key_store::SigningKeyStore;
 pub use store::Store;
 pub use diesel_store::DieselStore;
@@ -0,0 +1,18 @@
+use async_trait::async_trait;
+use crate::models::oauth_client::OAuthClient;
+use crate::error::AppError;
+
+/// Registered clients of our own authorization server.
+#[async_trait]
+pub trait OAuthClientStore: Send + Sync {
+    async fn create_oauth_client(
+        &self,
+        client_id: String,
+        name: String,
+        client_secret_hash: Option<String>,
+        redirect_uris: Vec<String>,
+        allowed_scopes: Vec<String>,
+    ) -> Result<OAuthClient, AppError>;
+
+    async fn find_oauth_client(&self, client_id: &str) -> Result<Option<OAuthClient>, AppError>;
+}
@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+use crate::error::AppError;
+
+/// Persistence of hashed, single-use password reset tokens.
+#[async_trait]
+pub trait PasswordResetStore: Send + Sync {
+    /// Stores a new token, replacing any the user was sent before so only
+    /// the latest email works.
+    async fn create_password_reset_token(
+        &self,
+        user_id: Uuid,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), AppError>;
+
+    /// Marks the token as used and returns its user. Returns `None` if it is
+    /// unknown, expired or was already used.
+    async fn consume_password_reset_token(&self, token_hash: &str) -> Result<Option<Uuid>, AppError>;
+}
@@ -0,0 +1,28 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+use crate::models::refresh_token::RefreshToken;
+use crate::error::AppError;
+
+/// Persistence of hashed refresh tokens. Tokens issued from one login share a
+/// `family_id` so the whole chain can be revoked when reuse is detected.
+#[async_trait]
+pub trait RefreshTokenStore: Send + Sync {
+    async fn create_refresh_token(
+        &self,
+        user_id: Uuid,
+        family_id: Uuid,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<RefreshToken, AppError>;
+
+    async fn find_refresh_token_by_hash(&self, token_hash: &str) -> Result<Option<RefreshToken>, AppError>;
+
+    /// Marks the token as used. Returns `false` if it had already been used,
+    /// which callers must treat as reuse.
+    async fn consume_refresh_token(&self, id: Uuid) -> Result<bool, AppError>;
+
+    async fn revoke_refresh_token_family(&self, family_id: Uuid) -> Result<(), AppError>;
+
+    async fn revoke_user_refresh_tokens(&self, user_id: Uuid) -> Result<(), AppError>;
+}
@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+use crate::error::AppError;
+
+/// Persistence of revoked access tokens and per-user "not before" cutoffs.
+/// The in-process `RevocationList` mirrors this so checks stay off the DB.
+#[async_trait]
+pub trait RevocationStore: Send + Sync {
+    async fn revoke_token(&self, jti: Uuid, user_id: Uuid, expires_at: NaiveDateTime) -> Result<(), AppError>;
+
+    /// Returns `(jti, expires_at)` for every revocation that hasn't expired yet.
+    async fn list_revoked_tokens(&self) -> Result<Vec<(Uuid, NaiveDateTime)>, AppError>;
+
+    async fn set_tokens_valid_after(&self, user_id: Uuid, valid_after: NaiveDateTime) -> Result<(), AppError>;
+
+    /// Returns `(user_id, tokens_valid_after)` for every user with a cutoff.
+    async fn list_token_cutoffs(&self) -> Result<Vec<(Uuid, NaiveDateTime)>, AppError>;
+
+    async fn purge_expired_revocations(&self) -> Result<(), AppError>;
+}
@@ -0,0 +1,16 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+use crate::models::role::UserGrants;
+use crate::error::AppError;
+
+/// Persistence of role assignments. Roles and the permissions they grant
+/// are defined by migrations; only who holds which role changes at runtime.
+#[async_trait]
+pub trait RoleStore: Send + Sync {
+    async fn find_user_grants(&self, user_id: Uuid) -> Result<UserGrants, AppError>;
+
+    /// Gives the user `role`. Assigning a role the user already holds is a no-op.
+    async fn assign_role(&self, user_id: Uuid, role: &str) -> Result<(), AppError>;
+
+    async fn revoke_role(&self, user_id: Uuid, role: &str) -> Result<(), AppError>;
+}
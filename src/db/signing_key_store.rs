@@ -0,0 +1,27 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use crate::models::signing_key::SigningKey;
+use crate::error::AppError;
+
+/// Persistence of the JWT keyset. `JwtKeys` mirrors it in memory and picks up
+/// changes made by `auth_session keys ...` without a restart.
+#[async_trait]
+pub trait SigningKeyStore: Send + Sync {
+    async fn create_signing_key(
+        &self,
+        kid: String,
+        algorithm: String,
+        private_key_path: Option<String>,
+        public_key_path: Option<String>,
+        active: bool,
+    ) -> Result<SigningKey, AppError>;
+
+    async fn list_signing_keys(&self) -> Result<Vec<SigningKey>, AppError>;
+
+    /// Makes `kid` the active key. The previously active key keeps verifying
+    /// tokens until `previous_not_after`.
+    async fn promote_signing_key(&self, kid: &str, previous_not_after: NaiveDateTime) -> Result<(), AppError>;
+
+    /// Stops accepting tokens signed with `kid` after `not_after`.
+    async fn retire_signing_key(&self, kid: &str, not_after: NaiveDateTime) -> Result<(), AppError>;
+}
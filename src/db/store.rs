@@ -1,23 +1,62 @@
+use std::collections::HashMap;
 use std::sync::Arc;
+use async_trait::async_trait;
 use tokio::sync::RwLock;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
 use crate::models::user::User;
+use crate::models::user_identity::UserIdentity;
+use crate::models::refresh_token::RefreshToken;
+use crate::models::oauth_client::OAuthClient;
+use crate::models::signing_key::SigningKey;
+use crate::models::role::UserGrants;
+use crate::models::mfa::{RecoveryCode, TotpSecret};
+use crate::models::webauthn_credential::WebAuthnCredential;
+use crate::models::password_reset_token::PasswordResetToken;
 use crate::error::AppError;
+use crate::db::{
+    EmailVerificationStore, IdentityStore, MagicLinkStore, MfaStore, OAuthClientStore, PasswordResetStore, RefreshTokenStore, RevocationStore, RoleStore,
+    SigningKeyStore, UserStore, WebAuthnStore,
+};
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Store {
     users: Arc<RwLock<Vec<User>>>,
+    identities: Arc<RwLock<Vec<UserIdentity>>>,
+    refresh_tokens: Arc<RwLock<Vec<RefreshToken>>>,
+    oauth_clients: Arc<RwLock<Vec<OAuthClient>>>,
+    signing_keys: Arc<RwLock<Vec<SigningKey>>>,
+    // role -> permissions it grants
+    roles: Arc<RwLock<HashMap<String, Vec<String>>>>,
+    // (user_id, role)
+    user_roles: Arc<RwLock<Vec<(Uuid, String)>>>,
+    totp_secrets: Arc<RwLock<HashMap<Uuid, TotpSecret>>>,
+    recovery_codes: Arc<RwLock<Vec<RecoveryCode>>>,
+    webauthn_credentials: Arc<RwLock<Vec<WebAuthnCredential>>>,
+    password_reset_tokens: Arc<RwLock<Vec<PasswordResetToken>>>,
+    // (user_id, token_hash, expires_at)
+    email_verification_tokens: Arc<RwLock<Vec<(Uuid, String, NaiveDateTime)>>>,
+    // (email, token_hash, expires_at)
+    magic_link_tokens: Arc<RwLock<Vec<(String, String, NaiveDateTime)>>>,
+    // jti -> expires_at
+    revoked_tokens: Arc<RwLock<HashMap<Uuid, NaiveDateTime>>>,
 }
 
 impl Store {
+    /// Starts with the roles the migrations seed.
     pub fn new() -> Self {
-        Self {
-            users: Arc::new(RwLock::new(Vec::new())),
-        }
+        let store = Self::default();
+        store.roles.try_write().expect("new store is unshared").insert(
+            "admin".to_string(),
+            vec!["roles:write".to_string(), "users:read".to_string()],
+        );
+        store
     }
+}
 
-    pub async fn create_user(
+#[async_trait]
+impl UserStore for Store {
+    async fn create_user(
         &self,
         email: String,
         name: String,
@@ -25,8 +64,10 @@ impl Store {
         oauth_provider: Option<String>,
         oauth_id: Option<String>,
     ) -> Result<User, AppError> {
+        // Lock order is always users, then identities
         let mut users = self.users.write().await;
-        
+        let mut identities = self.identities.write().await;
+
         if users.iter().any(|u| u.email == email) {
             return Err(AppError::BadRequest("Email already exists".to_string()));
         }
@@ -37,38 +78,693 @@ impl Store {
             email,
             name,
             password_hash,
-            oauth_provider,
-            oauth_id,
             created_at: now,
             updated_at: now,
+            tokens_valid_after: None,
+            email_verified_at: None,
         };
 
+        if let (Some(provider), Some(provider_user_id)) = (oauth_provider, oauth_id) {
+            if identities.iter().any(|i| i.provider == provider && i.provider_user_id == provider_user_id) {
+                return Err(AppError::BadRequest("Identity already linked to another user".to_string()));
+            }
+
+            identities.push(UserIdentity {
+                id: Uuid::new_v4(),
+                user_id: user.id,
+                provider,
+                provider_user_id,
+                email: Some(user.email.clone()),
+                created_at: now,
+            });
+        }
+
         users.push(user.clone());
         Ok(user)
     }
 
-    pub async fn find_user_by_email(&self, email: &str) -> Result<Option<User>, AppError> {
+    async fn find_user_by_email(&self, email: &str) -> Result<Option<User>, AppError> {
         let users = self.users.read().await;
         Ok(users.iter().find(|u| u.email == email).cloned())
     }
 
-    pub async fn find_user_by_oauth(
+    async fn find_user_by_oauth(
         &self,
         oauth_provider: &str,
         oauth_id: &str,
     ) -> Result<Option<User>, AppError> {
         let users = self.users.read().await;
-        Ok(users
+        let identities = self.identities.read().await;
+
+        let user_id = identities
             .iter()
-            .find(|u| {
-                u.oauth_provider.as_ref().map(|p| p.as_str()) == Some(oauth_provider)
-                    && u.oauth_id.as_ref().map(|i| i.as_str()) == Some(oauth_id)
-            })
-            .cloned())
+            .find(|i| i.provider == oauth_provider && i.provider_user_id == oauth_id)
+            .map(|i| i.user_id);
+
+        Ok(user_id.and_then(|id| users.iter().find(|u| u.id == id).cloned()))
     }
 
-    pub async fn find_user_by_id(&self, id: Uuid) -> Result<Option<User>, AppError> {
+    async fn find_user_by_id(&self, id: Uuid) -> Result<Option<User>, AppError> {
         let users = self.users.read().await;
         Ok(users.iter().find(|u| u.id == id).cloned())
     }
+
+    async fn update_user_password(&self, id: Uuid, new_password_hash: String) -> Result<User, AppError> {
+        let mut users = self.users.write().await;
+
+        let user = users
+            .iter_mut()
+            .find(|u| u.id == id)
+            .ok_or(AppError::NotFound)?;
+
+        user.password_hash = Some(new_password_hash);
+        user.updated_at = Utc::now().naive_utc();
+
+        Ok(user.clone())
+    }
+
+    async fn clear_user_password(&self, id: Uuid) -> Result<(), AppError> {
+        let mut users = self.users.write().await;
+
+        if let Some(user) = users.iter_mut().find(|u| u.id == id) {
+            user.password_hash = None;
+            user.updated_at = Utc::now().naive_utc();
+        }
+
+        Ok(())
+    }
+
+    async fn mark_email_verified(&self, id: Uuid) -> Result<(), AppError> {
+        let mut users = self.users.write().await;
+
+        if let Some(user) = users.iter_mut().find(|u| u.id == id && u.email_verified_at.is_none()) {
+            let now = Utc::now().naive_utc();
+            user.email_verified_at = Some(now);
+            user.updated_at = now;
+        }
+
+        Ok(())
+    }
+
+    async fn delete_user(&self, id: Uuid) -> Result<(), AppError> {
+        let mut users = self.users.write().await;
+        let mut identities = self.identities.write().await;
+        users.retain(|u| u.id != id);
+        identities.retain(|i| i.user_id != id);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl IdentityStore for Store {
+    async fn link_identity(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        provider_user_id: &str,
+        email: Option<String>,
+    ) -> Result<UserIdentity, AppError> {
+        let mut identities = self.identities.write().await;
+
+        if let Some(existing) = identities
+            .iter()
+            .find(|i| i.provider == provider && i.provider_user_id == provider_user_id)
+        {
+            if existing.user_id == user_id {
+                return Ok(existing.clone());
+            }
+            return Err(AppError::BadRequest(format!(
+                "This {} account is already linked to another user",
+                provider
+            )));
+        }
+
+        if identities.iter().any(|i| i.user_id == user_id && i.provider == provider) {
+            return Err(AppError::BadRequest(format!("A {} account is already linked", provider)));
+        }
+
+        let identity = UserIdentity {
+            id: Uuid::new_v4(),
+            user_id,
+            provider: provider.to_string(),
+            provider_user_id: provider_user_id.to_string(),
+            email,
+            created_at: Utc::now().naive_utc(),
+        };
+
+        identities.push(identity.clone());
+        Ok(identity)
+    }
+
+    async fn find_identities_by_user(&self, user_id: Uuid) -> Result<Vec<UserIdentity>, AppError> {
+        let identities = self.identities.read().await;
+        Ok(identities.iter().filter(|i| i.user_id == user_id).cloned().collect())
+    }
+
+    async fn unlink_identity(&self, user_id: Uuid, provider: &str) -> Result<(), AppError> {
+        let users = self.users.read().await;
+        let mut identities = self.identities.write().await;
+
+        let user = users.iter().find(|u| u.id == user_id).ok_or(AppError::NotFound)?;
+        if !identities.iter().any(|i| i.user_id == user_id && i.provider == provider) {
+            return Err(AppError::NotFound);
+        }
+
+        let remaining = identities.iter().filter(|i| i.user_id == user_id).count() - 1;
+        if remaining == 0 && user.password_hash.is_none() {
+            return Err(AppError::BadRequest("Cannot remove the last login method".to_string()));
+        }
+
+        identities.retain(|i| !(i.user_id == user_id && i.provider == provider));
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RefreshTokenStore for Store {
+    async fn create_refresh_token(
+        &self,
+        user_id: Uuid,
+        family_id: Uuid,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<RefreshToken, AppError> {
+        let mut tokens = self.refresh_tokens.write().await;
+
+        let token = RefreshToken {
+            id: Uuid::new_v4(),
+            user_id,
+            family_id,
+            token_hash,
+            expires_at,
+            used_at: None,
+            revoked_at: None,
+            created_at: Utc::now().naive_utc(),
+        };
+
+        tokens.push(token.clone());
+        Ok(token)
+    }
+
+    async fn find_refresh_token_by_hash(&self, token_hash: &str) -> Result<Option<RefreshToken>, AppError> {
+        let tokens = self.refresh_tokens.read().await;
+        Ok(tokens.iter().find(|t| t.token_hash == token_hash).cloned())
+    }
+
+    async fn consume_refresh_token(&self, id: Uuid) -> Result<bool, AppError> {
+        let mut tokens = self.refresh_tokens.write().await;
+
+        match tokens.iter_mut().find(|t| t.id == id && t.used_at.is_none()) {
+            Some(token) => {
+                token.used_at = Some(Utc::now().naive_utc());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn revoke_refresh_token_family(&self, family_id: Uuid) -> Result<(), AppError> {
+        let mut tokens = self.refresh_tokens.write().await;
+        let now = Utc::now().naive_utc();
+
+        for token in tokens.iter_mut().filter(|t| t.family_id == family_id && t.revoked_at.is_none()) {
+            token.revoked_at = Some(now);
+        }
+
+        Ok(())
+    }
+
+    async fn revoke_user_refresh_tokens(&self, user_id: Uuid) -> Result<(), AppError> {
+        let mut tokens = self.refresh_tokens.write().await;
+        let now = Utc::now().naive_utc();
+
+        for token in tokens.iter_mut().filter(|t| t.user_id == user_id && t.revoked_at.is_none()) {
+            token.revoked_at = Some(now);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RevocationStore for Store {
+    async fn revoke_token(&self, jti: Uuid, _user_id: Uuid, expires_at: NaiveDateTime) -> Result<(), AppError> {
+        let mut revoked = self.revoked_tokens.write().await;
+        revoked.insert(jti, expires_at);
+        Ok(())
+    }
+
+    async fn list_revoked_tokens(&self) -> Result<Vec<(Uuid, NaiveDateTime)>, AppError> {
+        let revoked = self.revoked_tokens.read().await;
+        let now = Utc::now().naive_utc();
+        Ok(revoked
+            .iter()
+            .filter(|(_, expires_at)| **expires_at > now)
+            .map(|(jti, expires_at)| (*jti, *expires_at))
+            .collect())
+    }
+
+    async fn set_tokens_valid_after(&self, user_id: Uuid, valid_after: NaiveDateTime) -> Result<(), AppError> {
+        let mut users = self.users.write().await;
+
+        let user = users
+            .iter_mut()
+            .find(|u| u.id == user_id)
+            .ok_or(AppError::NotFound)?;
+
+        user.tokens_valid_after = Some(valid_after);
+        Ok(())
+    }
+
+    async fn list_token_cutoffs(&self) -> Result<Vec<(Uuid, NaiveDateTime)>, AppError> {
+        let users = self.users.read().await;
+        Ok(users
+            .iter()
+            .filter_map(|u| u.tokens_valid_after.map(|cutoff| (u.id, cutoff)))
+            .collect())
+    }
+
+    async fn purge_expired_revocations(&self) -> Result<(), AppError> {
+        let mut revoked = self.revoked_tokens.write().await;
+        let now = Utc::now().naive_utc();
+        revoked.retain(|_, expires_at| *expires_at > now);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OAuthClientStore for Store {
+    async fn create_oauth_client(
+        &self,
+        client_id: String,
+        name: String,
+        client_secret_hash: Option<String>,
+        redirect_uris: Vec<String>,
+        allowed_scopes: Vec<String>,
+    ) -> Result<OAuthClient, AppError> {
+        let mut clients = self.oauth_clients.write().await;
+
+        if clients.iter().any(|c| c.client_id == client_id) {
+            return Err(AppError::BadRequest("Client ID already exists".to_string()));
+        }
+
+        let client = OAuthClient {
+            id: Uuid::new_v4(),
+            client_id,
+            name,
+            client_secret_hash,
+            redirect_uris,
+            allowed_scopes,
+            created_at: Utc::now().naive_utc(),
+        };
+
+        clients.push(client.clone());
+        Ok(client)
+    }
+
+    async fn find_oauth_client(&self, client_id: &str) -> Result<Option<OAuthClient>, AppError> {
+        let clients = self.oauth_clients.read().await;
+        Ok(clients.iter().find(|c| c.client_id == client_id).cloned())
+    }
+}
+
+#[async_trait]
+impl SigningKeyStore for Store {
+    async fn create_signing_key(
+        &self,
+        kid: String,
+        algorithm: String,
+        private_key_path: Option<String>,
+        public_key_path: Option<String>,
+        active: bool,
+    ) -> Result<SigningKey, AppError> {
+        let mut keys = self.signing_keys.write().await;
+
+        if keys.iter().any(|k| k.kid == kid) {
+            return Err(AppError::BadRequest(format!("Signing key {} already exists", kid)));
+        }
+
+        if active && keys.iter().any(|k| k.active) {
+            return Err(AppError::BadRequest("Another signing key is already active".to_string()));
+        }
+
+        let key = SigningKey {
+            kid,
+            algorithm,
+            private_key_path,
+            public_key_path,
+            active,
+            not_after: None,
+            created_at: Utc::now().naive_utc(),
+        };
+
+        keys.push(key.clone());
+        Ok(key)
+    }
+
+    async fn list_signing_keys(&self) -> Result<Vec<SigningKey>, AppError> {
+        let keys = self.signing_keys.read().await;
+        Ok(keys.clone())
+    }
+
+    async fn promote_signing_key(&self, kid: &str, previous_not_after: NaiveDateTime) -> Result<(), AppError> {
+        let mut keys = self.signing_keys.write().await;
+
+        let key = keys.iter().find(|k| k.kid == kid).ok_or(AppError::NotFound)?;
+        if key.active {
+            return Ok(());
+        }
+
+        if key.not_after.is_some_and(|not_after| not_after <= Utc::now().naive_utc()) {
+            return Err(AppError::BadRequest(format!("Signing key {} has been retired", kid)));
+        }
+
+        for key in keys.iter_mut() {
+            if key.active {
+                key.active = false;
+                key.not_after = Some(previous_not_after);
+            } else if key.kid == kid {
+                key.active = true;
+                key.not_after = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn retire_signing_key(&self, kid: &str, not_after: NaiveDateTime) -> Result<(), AppError> {
+        let mut keys = self.signing_keys.write().await;
+
+        let key = keys.iter_mut().find(|k| k.kid == kid).ok_or(AppError::NotFound)?;
+        if key.active {
+            return Err(AppError::BadRequest(
+                "Cannot retire the active signing key; promote another key first".to_string()
+            ));
+        }
+
+        key.not_after = Some(not_after);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RoleStore for Store {
+    async fn find_user_grants(&self, user_id: Uuid) -> Result<UserGrants, AppError> {
+        let roles = self.roles.read().await;
+        let user_roles = self.user_roles.read().await;
+
+        let mut grants = UserGrants::default();
+        for (_, role) in user_roles.iter().filter(|(id, _)| *id == user_id) {
+            grants.roles.push(role.clone());
+            grants.permissions.extend(roles.get(role).into_iter().flatten().cloned());
+        }
+
+        grants.roles.sort();
+        grants.permissions.sort();
+        grants.permissions.dedup();
+        Ok(grants)
+    }
+
+    async fn assign_role(&self, user_id: Uuid, role: &str) -> Result<(), AppError> {
+        let roles = self.roles.read().await;
+        let mut user_roles = self.user_roles.write().await;
+
+        if !roles.contains_key(role) {
+            return Err(AppError::BadRequest(format!("Unknown role: {}", role)));
+        }
+
+        if !user_roles.iter().any(|(id, r)| *id == user_id && r == role) {
+            user_roles.push((user_id, role.to_string()));
+        }
+
+        Ok(())
+    }
+
+    async fn revoke_role(&self, user_id: Uuid, role: &str) -> Result<(), AppError> {
+        let mut user_roles = self.user_roles.write().await;
+
+        let before = user_roles.len();
+        user_roles.retain(|(id, r)| !(*id == user_id && r == role));
+        if user_roles.len() == before {
+            return Err(AppError::NotFound);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MfaStore for Store {
+    async fn find_totp_secret(&self, user_id: Uuid) -> Result<Option<TotpSecret>, AppError> {
+        let secrets = self.totp_secrets.read().await;
+        Ok(secrets.get(&user_id).cloned())
+    }
+
+    async fn set_pending_totp_secret(&self, user_id: Uuid, secret: String) -> Result<(), AppError> {
+        let mut secrets = self.totp_secrets.write().await;
+
+        if secrets.get(&user_id).is_some_and(TotpSecret::is_enabled) {
+            return Err(AppError::Conflict {
+                code: "mfa_already_enabled",
+                message: "Two-factor authentication is already enabled".to_string(),
+            });
+        }
+
+        secrets.insert(user_id, TotpSecret {
+            user_id,
+            secret,
+            confirmed_at: None,
+            last_used_step: None,
+            created_at: Utc::now().naive_utc(),
+        });
+        Ok(())
+    }
+
+    async fn enable_totp(&self, user_id: Uuid, step: i64, recovery_code_hashes: Vec<String>) -> Result<(), AppError> {
+        let mut secrets = self.totp_secrets.write().await;
+
+        match secrets.get_mut(&user_id).filter(|s| !s.is_enabled()) {
+            Some(secret) => {
+                secret.confirmed_at = Some(Utc::now().naive_utc());
+                secret.last_used_step = Some(step);
+            }
+            None => return Err(AppError::BadRequest("No pending two-factor enrollment".to_string())),
+        }
+
+        self.replace_recovery_codes(user_id, recovery_code_hashes).await
+    }
+
+    async fn use_totp_step(&self, user_id: Uuid, step: i64) -> Result<bool, AppError> {
+        let mut secrets = self.totp_secrets.write().await;
+
+        match secrets.get_mut(&user_id).filter(|s| s.last_used_step.is_none_or(|last| last < step)) {
+            Some(secret) => {
+                secret.last_used_step = Some(step);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn disable_totp(&self, user_id: Uuid) -> Result<(), AppError> {
+        self.totp_secrets.write().await.remove(&user_id);
+        self.recovery_codes.write().await.retain(|c| c.user_id != user_id);
+        Ok(())
+    }
+
+    async fn replace_recovery_codes(&self, user_id: Uuid, code_hashes: Vec<String>) -> Result<(), AppError> {
+        let mut codes = self.recovery_codes.write().await;
+        let now = Utc::now().naive_utc();
+
+        codes.retain(|c| c.user_id != user_id);
+        codes.extend(code_hashes.into_iter().map(|code_hash| RecoveryCode {
+            id: Uuid::new_v4(),
+            user_id,
+            code_hash,
+            used_at: None,
+            created_at: now,
+        }));
+        Ok(())
+    }
+
+    async fn use_recovery_code(&self, user_id: Uuid, code_hash: &str) -> Result<bool, AppError> {
+        let mut codes = self.recovery_codes.write().await;
+
+        match codes.iter_mut().find(|c| c.user_id == user_id && c.code_hash == code_hash && c.used_at.is_none()) {
+            Some(code) => {
+                code.used_at = Some(Utc::now().naive_utc());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn count_unused_recovery_codes(&self, user_id: Uuid) -> Result<i64, AppError> {
+        let codes = self.recovery_codes.read().await;
+        Ok(codes.iter().filter(|c| c.user_id == user_id && c.used_at.is_none()).count() as i64)
+    }
+}
+
+#[async_trait]
+impl WebAuthnStore for Store {
+    async fn create_webauthn_credential(
+        &self,
+        user_id: Uuid,
+        credential_id: String,
+        public_key: Vec<u8>,
+        sign_count: i64,
+        name: String,
+    ) -> Result<WebAuthnCredential, AppError> {
+        let mut credentials = self.webauthn_credentials.write().await;
+
+        if credentials.iter().any(|c| c.credential_id == credential_id) {
+            return Err(AppError::Conflict {
+                code: "passkey_already_registered",
+                message: "This passkey is already registered".to_string(),
+            });
+        }
+
+        let credential = WebAuthnCredential {
+            id: Uuid::new_v4(),
+            user_id,
+            credential_id,
+            public_key,
+            sign_count,
+            name,
+            created_at: Utc::now().naive_utc(),
+            last_used_at: None,
+        };
+
+        credentials.push(credential.clone());
+        Ok(credential)
+    }
+
+    async fn find_webauthn_credential(&self, credential_id: &str) -> Result<Option<WebAuthnCredential>, AppError> {
+        let credentials = self.webauthn_credentials.read().await;
+        Ok(credentials.iter().find(|c| c.credential_id == credential_id).cloned())
+    }
+
+    async fn find_webauthn_credentials_by_user(&self, user_id: Uuid) -> Result<Vec<WebAuthnCredential>, AppError> {
+        let credentials = self.webauthn_credentials.read().await;
+        Ok(credentials.iter().filter(|c| c.user_id == user_id).cloned().collect())
+    }
+
+    async fn update_webauthn_sign_count(&self, id: Uuid, sign_count: i64) -> Result<(), AppError> {
+        let mut credentials = self.webauthn_credentials.write().await;
+
+        if let Some(credential) = credentials.iter_mut().find(|c| c.id == id) {
+            credential.sign_count = sign_count;
+            credential.last_used_at = Some(Utc::now().naive_utc());
+        }
+
+        Ok(())
+    }
+
+    async fn delete_webauthn_credential(&self, user_id: Uuid, id: Uuid) -> Result<(), AppError> {
+        let mut credentials = self.webauthn_credentials.write().await;
+
+        let before = credentials.len();
+        credentials.retain(|c| !(c.id == id && c.user_id == user_id));
+        if credentials.len() == before {
+            return Err(AppError::NotFound);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PasswordResetStore for Store {
+    async fn create_password_reset_token(
+        &self,
+        user_id: Uuid,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), AppError> {
+        let mut tokens = self.password_reset_tokens.write().await;
+
+        tokens.retain(|t| t.user_id != user_id);
+        tokens.push(PasswordResetToken {
+            id: Uuid::new_v4(),
+            user_id,
+            token_hash,
+            expires_at,
+            used_at: None,
+            created_at: Utc::now().naive_utc(),
+        });
+
+        Ok(())
+    }
+
+    async fn consume_password_reset_token(&self, token_hash: &str) -> Result<Option<Uuid>, AppError> {
+        let mut tokens = self.password_reset_tokens.write().await;
+        let now = Utc::now().naive_utc();
+
+        match tokens
+            .iter_mut()
+            .find(|t| t.token_hash == token_hash && t.used_at.is_none() && t.expires_at > now)
+        {
+            Some(token) => {
+                token.used_at = Some(now);
+                Ok(Some(token.user_id))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl EmailVerificationStore for Store {
+    async fn create_email_verification_token(
+        &self,
+        user_id: Uuid,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), AppError> {
+        let mut tokens = self.email_verification_tokens.write().await;
+
+        tokens.retain(|(id, _, _)| *id != user_id);
+        tokens.push((user_id, token_hash, expires_at));
+
+        Ok(())
+    }
+
+    async fn consume_email_verification_token(&self, token_hash: &str) -> Result<Option<Uuid>, AppError> {
+        let mut tokens = self.email_verification_tokens.write().await;
+        let now = Utc::now().naive_utc();
+
+        let Some(index) = tokens.iter().position(|(_, hash, expires_at)| hash == token_hash && *expires_at > now) else {
+            return Ok(None);
+        };
+
+        Ok(Some(tokens.remove(index).0))
+    }
+}
+
+#[async_trait]
+impl MagicLinkStore for Store {
+    async fn create_magic_link_token(
+        &self,
+        email: String,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), AppError> {
+        let mut tokens = self.magic_link_tokens.write().await;
+
+        tokens.retain(|(sent_to, _, _)| *sent_to != email);
+        tokens.push((email, token_hash, expires_at));
+
+        Ok(())
+    }
+
+    async fn consume_magic_link_token(&self, token_hash: &str) -> Result<Option<String>, AppError> {
+        let mut tokens = self.magic_link_tokens.write().await;
+        let now = Utc::now().naive_utc();
+
+        let Some(index) = tokens.iter().position(|(_, hash, expires_at)| hash == token_hash && *expires_at > now) else {
+            return Ok(None);
+        };
+
+        Ok(Some(tokens.remove(index).0))
+    }
 }
@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+use crate::models::user::User;
+use crate::error::AppError;
+
+/// Persistence operations on users, implemented by both the Postgres-backed
+/// `DieselStore` and the in-memory `Store`.
+#[async_trait]
+pub trait UserStore: Send + Sync {
+    async fn create_user(
+        &self,
+        email: String,
+        name: String,
+        password_hash: Option<String>,
+        oauth_provider: Option<String>,
+        oauth_id: Option<String>,
+    ) -> Result<User, AppError>;
+
+    async fn find_user_by_email(&self, email: &str) -> Result<Option<User>, AppError>;
+
+    async fn find_user_by_oauth(
+        &self,
+        oauth_provider: &str,
+        oauth_id: &str,
+    ) -> Result<Option<User>, AppError>;
+
+    async fn find_user_by_id(&self, id: Uuid) -> Result<Option<User>, AppError>;
+
+    async fn update_user_password(&self, id: Uuid, new_password_hash: String) -> Result<User, AppError>;
+
+    /// Removes the password, leaving only the account's linked logins.
+    async fn clear_user_password(&self, id: Uuid) -> Result<(), AppError>;
+
+    /// Records that the user proved they own their email address. Keeps the
+    /// first timestamp if it was already verified.
+    async fn mark_email_verified(&self, id: Uuid) -> Result<(), AppError>;
+
+    async fn delete_user(&self, id: Uuid) -> Result<(), AppError>;
+}
@@ -0,0 +1,27 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+use crate::models::webauthn_credential::WebAuthnCredential;
+use crate::error::AppError;
+
+/// Persistence of passkey public keys and their signature counters.
+#[async_trait]
+pub trait WebAuthnStore: Send + Sync {
+    /// Fails with a conflict if the credential is already registered.
+    async fn create_webauthn_credential(
+        &self,
+        user_id: Uuid,
+        credential_id: String,
+        public_key: Vec<u8>,
+        sign_count: i64,
+        name: String,
+    ) -> Result<WebAuthnCredential, AppError>;
+
+    async fn find_webauthn_credential(&self, credential_id: &str) -> Result<Option<WebAuthnCredential>, AppError>;
+
+    async fn find_webauthn_credentials_by_user(&self, user_id: Uuid) -> Result<Vec<WebAuthnCredential>, AppError>;
+
+    /// Records a successful assertion.
+    async fn update_webauthn_sign_count(&self, id: Uuid, sign_count: i64) -> Result<(), AppError>;
+
+    async fn delete_webauthn_credential(&self, user_id: Uuid, id: Uuid) -> Result<(), AppError>;
+}
@@ -23,12 +23,20 @@ pub enum AppError {
     #[error("Unauthorized")]
     Unauthorized,
 
+    /// Authenticated, but lacking the role or permission required
+    #[error("Forbidden")]
+    Forbidden,
+
     #[error("Not found")]
     NotFound,
 
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    /// A conflict the client can resolve; `code` is a stable identifier to branch on
+    #[error("Conflict ({code}): {message}")]
+    Conflict { code: &'static str, message: String },
+
     #[error("Too many requests: {0}")]
     TooManyRequests(String),
 
@@ -38,21 +46,34 @@ pub enum AppError {
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let code = match &self {
+            AppError::Conflict { code, .. } => Some(*code),
+            _ => None,
+        };
+
         let (status, message) = match self {
             AppError::Database(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
             AppError::Pool(e) => (StatusCode::INTERNAL_SERVER_ERROR, e),
             AppError::Jwt(_) => (StatusCode::UNAUTHORIZED, "Invalid token".to_string()),
             AppError::Io(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
             AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
+            AppError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden".to_string()),
             AppError::NotFound => (StatusCode::NOT_FOUND, "Resource not found".to_string()),
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            AppError::Conflict { message, .. } => (StatusCode::CONFLICT, message),
             AppError::TooManyRequests(msg) => (StatusCode::TOO_MANY_REQUESTS, msg),
             AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
         };
 
-        let body = Json(json!({
-            "error": message,
-        }));
+        let body = match code {
+            Some(code) => Json(json!({
+                "error": message,
+                "code": code,
+            })),
+            None => Json(json!({
+                "error": message,
+            })),
+        };
 
         (status, body).into_response()
     }
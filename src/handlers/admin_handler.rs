@@ -0,0 +1,46 @@
+use axum::{Json, extract::{Path, State}};
+use uuid::Uuid;
+use crate::error::AppError;
+use crate::handlers::auth_handler::AppState;
+use crate::middleware::auth_middleware::AuthUser;
+use crate::models::role::UserGrants;
+
+pub async fn get_user_roles(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<UserGrants>, AppError> {
+    state.store.find_user_by_id(user_id).await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Json(state.roles.find_user_grants(user_id).await?))
+}
+
+/// Grants `role` to the user. It is added to their tokens on the next
+/// login or refresh.
+pub async fn assign_role(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((user_id, role)): Path<(Uuid, String)>,
+) -> Result<Json<UserGrants>, AppError> {
+    state.store.find_user_by_id(user_id).await?
+        .ok_or(AppError::NotFound)?;
+
+    state.roles.assign_role(user_id, &role).await?;
+    tracing::info!("User {} granted role {} to user: {}", auth.user_id, role, user_id);
+
+    Ok(Json(state.roles.find_user_grants(user_id).await?))
+}
+
+/// Takes `role` away. The user's access tokens still carry it, so they are
+/// revoked; refreshing issues new ones without it.
+pub async fn revoke_role(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((user_id, role)): Path<(Uuid, String)>,
+) -> Result<Json<UserGrants>, AppError> {
+    state.roles.revoke_role(user_id, &role).await?;
+    state.revocations.revoke_all_issued(user_id).await?;
+    tracing::info!("User {} revoked role {} from user: {}", auth.user_id, role, user_id);
+
+    Ok(Json(state.roles.find_user_grants(user_id).await?))
+}
@@ -1,25 +1,49 @@
 use axum::{
     Json,
-    extract::{State, Query, ConnectInfo},
-    response::{IntoResponse, Redirect},
+    extract::{State, ConnectInfo},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
 };
+use axum_extra::extract::cookie::CookieJar;
 use serde::{Deserialize, Serialize};
-use oauth2::{
-    AuthorizationCode, AuthUrl, ClientId, ClientSecret, CsrfToken, RedirectUrl,
-    TokenUrl, TokenResponse, basic::BasicClient, reqwest::async_http_client,
-};
 use std::net::SocketAddr;
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
 use crate::error::AppError;
 use crate::config::AppConfig;
-use crate::db::DieselStore;
-use crate::utils::{hashing, jwt};
-use crate::middleware::rate_limit::RateLimiter;
+use crate::db::{EmailVerificationStore, IdentityStore, MagicLinkStore, MfaStore, OAuthClientStore, PasswordResetStore, RefreshTokenStore, RoleStore, UserStore, WebAuthnStore};
+use crate::handlers::{mfa_handler, verification_handler};
+use crate::mail::{MailTemplates, Mailer};
+use crate::models::user::User;
+use crate::oauth::ProviderRegistry;
+use crate::utils::{hashing, jwt::{self, JwtKeys}, session, token};
+use crate::middleware::{account_link::AccountLinkStore, auth_middleware::AuthUser, authorization_code::AuthorizationCodeStore, csrf, mfa_challenge::MfaChallengeStore, webauthn_challenge::WebAuthnChallengeStore, oauth_state::OAuthStateStore, rate_limit::RateLimiter, revocation::RevocationList};
 
 #[derive(Clone)]
 pub struct AppState {
     pub config: AppConfig,
-    pub store: DieselStore,
+    pub jwt_keys: JwtKeys,
+    pub store: Arc<dyn UserStore>,
+    pub identities: Arc<dyn IdentityStore>,
+    pub refresh_tokens: Arc<dyn RefreshTokenStore>,
+    pub roles: Arc<dyn RoleStore>,
+    pub mfa: Arc<dyn MfaStore>,
+    pub webauthn: Arc<dyn WebAuthnStore>,
+    pub password_resets: Arc<dyn PasswordResetStore>,
+    pub email_verifications: Arc<dyn EmailVerificationStore>,
+    pub magic_links: Arc<dyn MagicLinkStore>,
+    pub mailer: Arc<dyn Mailer>,
+    pub mail_templates: MailTemplates,
     pub rate_limiter: RateLimiter,
+    pub revocations: RevocationList,
+    pub oauth_states: OAuthStateStore,
+    pub account_links: AccountLinkStore,
+    pub mfa_challenges: MfaChallengeStore,
+    pub webauthn_challenges: WebAuthnChallengeStore,
+    pub providers: ProviderRegistry,
+    pub oauth_clients: Arc<dyn OAuthClientStore>,
+    pub authorization_codes: AuthorizationCodeStore,
 }
 
 #[derive(Debug, Deserialize)]
@@ -38,6 +62,7 @@ pub struct LoginRequest {
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub token: String,
+    pub refresh_token: String,
     pub user: UserInfo,
 }
 
@@ -48,23 +73,34 @@ pub struct UserInfo {
     pub name: String,
 }
 
+/// Browsers using session cookies may send an empty body instead.
 #[derive(Debug, Deserialize)]
-pub struct GoogleUserInfo {
-    pub id: String,
-    pub email: String,
-    pub name: String,
+pub struct RefreshRequest {
+    pub refresh_token: String,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct OAuthCallbackQuery {
-    pub code: String,
-    pub state: String,
+pub struct LogoutRequest {
+    pub refresh_token: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct LogoutAllRequest {
+    /// Unix timestamp; tokens issued before it are revoked. Defaults to now.
+    pub before: Option<i64>,
+}
+
+/// Creates a password account and emails a link to verify the address.
+/// When verification is required, no session starts until it's done.
 pub async fn register(
     State(state): State<AppState>,
+    jar: CookieJar,
     Json(payload): Json<RegisterRequest>,
-) -> Result<Json<AuthResponse>, AppError> {
+) -> Result<Response, AppError> {
+    if !state.config.registration.open {
+        return Err(AppError::Forbidden);
+    }
+
     let start = std::time::Instant::now();
     let password_hash = hashing::hash_password(&payload.password)?;
     tracing::debug!("Register: hashing took {}ms", start.elapsed().as_millis());
@@ -79,29 +115,26 @@ pub async fn register(
     ).await?;
     tracing::debug!("Register: DB create_user took {}ms", db_start.elapsed().as_millis());
 
-    let token = jwt::generate_token(
-        user.id,
-        &user.email,
-        &user.name,
-        &state.config.jwt.secret,
-        state.config.jwt.expiration,
-    )?;
+    verification_handler::send_verification(&state, &user).await?;
 
-    Ok(Json(AuthResponse {
-        token,
-        user: UserInfo {
-            id: user.id.to_string(),
-            email: user.email,
-            name: user.name,
-        },
-    }))
+    if state.config.email_verification.required {
+        return Ok((StatusCode::CREATED, Json(serde_json::json!({
+            "code": "email_verification_required",
+            "message": "Account created. Follow the link we emailed you to verify your address, then log in"
+        }))).into_response());
+    }
+
+    Ok(start_session(&state, jar, user, None).await?.into_response())
 }
 
+/// Logs in with email and password. Accounts with 2FA enabled get an
+/// `mfa_required` challenge instead of tokens.
 pub async fn login(
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     State(state): State<AppState>,
+    jar: CookieJar,
     Json(payload): Json<LoginRequest>,
-) -> Result<Json<AuthResponse>, AppError> {
+) -> Result<Response, AppError> {
     let client_ip = addr.ip().to_string();
     
     // Check IP-based rate limit (prevent brute force from single IP)
@@ -142,124 +175,183 @@ pub async fn login(
         ));
     }
 
+    if state.config.email_verification.required && user.email_verified_at.is_none() {
+        tracing::warn!("Login with unverified email: {} from IP: {}", payload.email, client_ip);
+        return Err(AppError::Conflict {
+            code: "email_not_verified",
+            message: "Verify your email address before logging in".to_string(),
+        });
+    }
+
+    // The email limit keeps counting until the second factor is verified
+    if let Some(challenge) = mfa_handler::challenge(&state, &user).await? {
+        tracing::info!("Password accepted for email: {} from IP: {}, awaiting second factor", payload.email, client_ip);
+        return Ok(Json(challenge).into_response());
+    }
+
     // Successful login - reset email rate limit
     state.rate_limiter.reset_email_limit(&payload.email);
     tracing::info!("Successful login for email: {} from IP: {}", payload.email, client_ip);
 
-    let token = jwt::generate_token(
-        user.id,
-        &user.email,
-        &user.name,
-        &state.config.jwt.secret,
-        state.config.jwt.expiration,
-    )?;
-
-    Ok(Json(AuthResponse {
-        token,
-        user: UserInfo {
-            id: user.id.to_string(),
-            email: user.email,
-            name: user.name,
-        },
-    }))
+    Ok(start_session(&state, jar, user, None).await?.into_response())
 }
 
-pub async fn google_oauth(
+pub async fn refresh(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, AppError> {
-    let client = BasicClient::new(
-        ClientId::new(state.config.google_oauth.client_id.clone()),
-        Some(ClientSecret::new(state.config.google_oauth.client_secret.clone())),
-        AuthUrl::new(state.config.google_oauth.auth_url.clone())
-            .map_err(|e| AppError::Internal(format!("Invalid auth URL: {}", e)))?,
-        Some(TokenUrl::new(state.config.google_oauth.token_url.clone())
-            .map_err(|e| AppError::Internal(format!("Invalid token URL: {}", e)))?),
-    )
-    .set_redirect_uri(
-        RedirectUrl::new(state.config.google_oauth.redirect_url.clone())
-            .map_err(|e| AppError::Internal(format!("Invalid redirect URL: {}", e)))?,
-    );
-
-    let (auth_url, _csrf_token) = client
-        .authorize_url(CsrfToken::new_random)
-        .add_scope(oauth2::Scope::new("https://www.googleapis.com/auth/userinfo.email".to_string()))
-        .add_scope(oauth2::Scope::new("https://www.googleapis.com/auth/userinfo.profile".to_string()))
-        .url();
-
-    Ok(Redirect::to(auth_url.as_str()))
-}
+    headers: HeaderMap,
+    jar: CookieJar,
+    payload: Option<Json<RefreshRequest>>,
+) -> Result<(CookieJar, Json<AuthResponse>), AppError> {
+    let refresh_token = match payload {
+        Some(Json(p)) => p.refresh_token,
+        None => {
+            let refresh_token = session::refresh_token(&state.config.session, &jar)
+                .ok_or(AppError::Unauthorized)?;
+            csrf::verify(&state.config.session, &headers)?;
+            refresh_token
+        }
+    };
 
-pub async fn google_oauth_callback(
-    State(state): State<AppState>,
-    Query(query): Query<OAuthCallbackQuery>,
-) -> Result<Json<AuthResponse>, AppError> {
-    let client = BasicClient::new(
-        ClientId::new(state.config.google_oauth.client_id.clone()),
-        Some(ClientSecret::new(state.config.google_oauth.client_secret.clone())),
-        AuthUrl::new(state.config.google_oauth.auth_url.clone())
-            .map_err(|e| AppError::Internal(format!("Invalid auth URL: {}", e)))?,
-        Some(TokenUrl::new(state.config.google_oauth.token_url.clone())
-            .map_err(|e| AppError::Internal(format!("Invalid token URL: {}", e)))?),
-    )
-    .set_redirect_uri(
-        RedirectUrl::new(state.config.google_oauth.redirect_url.clone())
-            .map_err(|e| AppError::Internal(format!("Invalid redirect URL: {}", e)))?,
-    );
-
-    let token = client
-        .exchange_code(AuthorizationCode::new(query.code))
-        .request_async(async_http_client)
-        .await
-        .map_err(|_| AppError::Unauthorized)?;
-
-    let client = reqwest::Client::new();
-    let google_user: GoogleUserInfo = client
-        .get("https://www.googleapis.com/oauth2/v2/userinfo")
-        .bearer_auth(token.access_token().secret())
-        .send()
-        .await
-        .map_err(|e| AppError::Internal(format!("Failed to fetch Google user info: {}", e)))?
-        .json()
-        .await
-        .map_err(|e| AppError::Internal(format!("Failed to parse Google user info: {}", e)))?;
-
-    let user = match state
+    let token_hash = token::hash_token(&refresh_token);
+    let stored = state
+        .refresh_tokens
+        .find_refresh_token_by_hash(&token_hash)
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+
+    if stored.revoked_at.is_some() {
+        return Err(AppError::Unauthorized);
+    }
+
+    // A refresh token that was already rotated is being replayed, so the
+    // family has leaked. Revoke every token descended from the same login.
+    if stored.used_at.is_some() || !state.refresh_tokens.consume_refresh_token(stored.id).await? {
+        tracing::warn!(
+            "Refresh token reuse detected for user: {}, revoking family: {}",
+            stored.user_id,
+            stored.family_id
+        );
+        state.refresh_tokens.revoke_refresh_token_family(stored.family_id).await?;
+        return Err(AppError::Unauthorized);
+    }
+
+    if stored.expires_at < Utc::now().naive_utc() {
+        return Err(AppError::Unauthorized);
+    }
+
+    let user = state
         .store
-        .find_user_by_oauth("google", &google_user.id)
+        .find_user_by_id(stored.user_id)
         .await?
-    {
-        Some(user) => user,
-        None => {
-            state.store.create_user(
-                google_user.email.clone(),
-                google_user.name.clone(),
-                None,
-                Some("google".to_string()),
-                Some(google_user.id.clone()),
-            ).await?
+        .ok_or(AppError::Unauthorized)?;
+
+    start_session(&state, jar, user, Some(stored.family_id)).await
+}
+
+pub async fn logout(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    jar: CookieJar,
+    payload: Option<Json<LogoutRequest>>,
+) -> Result<(CookieJar, Json<serde_json::Value>), AppError> {
+    state.revocations.revoke(&auth.claims).await?;
+
+    // Also end the refresh token chain of this session if the client sent it
+    let refresh_token = payload
+        .and_then(|Json(p)| p.refresh_token)
+        .or_else(|| session::refresh_token(&state.config.session, &jar));
+    if let Some(refresh_token) = refresh_token {
+        let stored = state
+            .refresh_tokens
+            .find_refresh_token_by_hash(&token::hash_token(&refresh_token))
+            .await?;
+
+        if let Some(stored) = stored.filter(|t| t.user_id == auth.user_id) {
+            state.refresh_tokens.revoke_refresh_token_family(stored.family_id).await?;
         }
+    }
+
+    let jar = session::remove(&state.config.session, jar);
+
+    Ok((jar, Json(serde_json::json!({
+        "message": "Logged out successfully"
+    }))))
+}
+
+pub async fn logout_all(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    jar: CookieJar,
+    payload: Option<Json<LogoutAllRequest>>,
+) -> Result<(CookieJar, Json<serde_json::Value>), AppError> {
+    let user_id = auth.user_id;
+
+    let now = Utc::now();
+    let valid_after = match payload.and_then(|Json(p)| p.before) {
+        Some(before) => DateTime::from_timestamp(before, 0)
+            .ok_or_else(|| AppError::BadRequest("Invalid timestamp".to_string()))?
+            .min(now),
+        None => now,
     };
 
-    let jwt_token = jwt::generate_token(
-        user.id,
-        &user.email,
-        &user.name,
-        &state.config.jwt.secret,
+    state.revocations.revoke_all_before(user_id, valid_after).await?;
+    // The cutoff has second precision, so revoke the presenting token explicitly
+    state.revocations.revoke(&auth.claims).await?;
+    state.refresh_tokens.revoke_user_refresh_tokens(user_id).await?;
+    tracing::info!("Revoked all sessions for user: {} issued before {}", user_id, valid_after);
+
+    let jar = session::remove(&state.config.session, jar);
+
+    Ok((jar, Json(serde_json::json!({
+        "message": "Logged out of all sessions"
+    }))))
+}
+
+/// Issues tokens and, in session mode, also sets them as cookies.
+pub(crate) async fn start_session(
+    state: &AppState,
+    jar: CookieJar,
+    user: User,
+    family_id: Option<Uuid>,
+) -> Result<(CookieJar, Json<AuthResponse>), AppError> {
+    let tokens = issue_tokens(state, user, family_id).await?;
+    let jar = session::add(&state.config.session, jar, &tokens.token, &tokens.refresh_token);
+
+    Ok((jar, Json(tokens)))
+}
+
+/// Mints an access token together with a fresh refresh token. Passing a
+/// `family_id` continues an existing rotation chain; `None` starts a new one.
+async fn issue_tokens(
+    state: &AppState,
+    user: User,
+    family_id: Option<Uuid>,
+) -> Result<AuthResponse, AppError> {
+    let grants = state.roles.find_user_grants(user.id).await?;
+    let token = jwt::generate_token(
+        &user,
+        &grants,
+        &state.jwt_keys,
         state.config.jwt.expiration,
+        None,
     )?;
 
-    Ok(Json(AuthResponse {
-        token: jwt_token,
+    let refresh_token = token::generate_opaque_token();
+    let expires_at = (Utc::now() + Duration::seconds(state.config.jwt.refresh_expiration)).naive_utc();
+    state.refresh_tokens.create_refresh_token(
+        user.id,
+        family_id.unwrap_or_else(Uuid::new_v4),
+        token::hash_token(&refresh_token),
+        expires_at,
+    ).await?;
+
+    Ok(AuthResponse {
+        token,
+        refresh_token,
         user: UserInfo {
             id: user.id.to_string(),
             email: user.email,
             name: user.name,
         },
-    }))
-}
-
-pub async fn logout() -> Result<Json<serde_json::Value>, AppError> {
-    Ok(Json(serde_json::json!({
-        "message": "Logged out successfully"
-    })))
+    })
 }
@@ -0,0 +1,367 @@
+use axum::{
+    Form, Json,
+    extract::{ConnectInfo, Query, RawQuery, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::Instant;
+use crate::error::AppError;
+use crate::handlers::auth_handler::AppState;
+use crate::middleware::auth_middleware::AuthUser;
+use crate::middleware::authorization_code::PendingCode;
+use crate::models::oauth_client::OAuthClient;
+use crate::models::role::UserGrants;
+use crate::utils::{hashing, jwt, token};
+
+// Consent page that collects the user's approval for an authorization request
+const CONSENT_PAGE: &str = "/static/authorize.html";
+
+/// Query parameters of an authorization request (RFC 6749 §4.1.1, RFC 7636 §4.3).
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeParams {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    /// OpenID Connect: echoed in the ID token
+    pub nonce: Option<String>,
+}
+
+/// The consent page's answer, sent with the user's bearer token.
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeDecision {
+    #[serde(flatten)]
+    pub params: AuthorizeParams,
+    pub approve: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuthorizeDecisionResponse {
+    /// Where the consent page must send the browser next
+    pub redirect_to: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub code_verifier: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Present when the `openid` scope was granted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+/// Token endpoint errors in the RFC 6749 §5.2 format OAuth clients expect,
+/// rather than our usual `{"error": message}` body.
+#[derive(Debug)]
+pub struct OAuthError {
+    status: StatusCode,
+    error: &'static str,
+    description: String,
+}
+
+impl OAuthError {
+    fn new(error: &'static str, description: impl Into<String>) -> Self {
+        let status = match error {
+            "invalid_client" => StatusCode::UNAUTHORIZED,
+            "server_error" => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        };
+
+        Self {
+            status,
+            error,
+            description: description.into(),
+        }
+    }
+}
+
+impl From<AppError> for OAuthError {
+    fn from(e: AppError) -> Self {
+        tracing::error!("OAuth token request failed: {}", e);
+        Self::new("server_error", "Internal server error")
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({
+            "error": self.error,
+            "error_description": self.description,
+        }));
+
+        (self.status, [(header::CACHE_CONTROL, "no-store")], body).into_response()
+    }
+}
+
+/// Validates an authorization request and hands it to the consent page.
+/// Until the client and redirect URI are known to be genuine, errors are
+/// shown here instead of being redirected.
+pub async fn authorize(
+    State(state): State<AppState>,
+    Query(params): Query<AuthorizeParams>,
+    RawQuery(query): RawQuery,
+) -> Result<Redirect, AppError> {
+    let client = find_client(&state, &params).await?;
+
+    if let Err((error, description)) = check_request(&client, &params) {
+        return Ok(Redirect::to(&error_redirect(&params, error, description)?));
+    }
+
+    Ok(Redirect::to(&format!("{}?{}", CONSENT_PAGE, query.unwrap_or_default())))
+}
+
+/// Records the signed-in user's decision and returns the client redirect,
+/// carrying either an authorization code or `access_denied`.
+pub async fn decide(
+    State(state): State<AppState>,
+    AuthUser { user_id, .. }: AuthUser,
+    Json(decision): Json<AuthorizeDecision>,
+) -> Result<Json<AuthorizeDecisionResponse>, AppError> {
+    let params = decision.params;
+    let client = find_client(&state, &params).await?;
+
+    if let Err((error, description)) = check_request(&client, &params) {
+        return Ok(Json(AuthorizeDecisionResponse {
+            redirect_to: error_redirect(&params, error, description)?,
+        }));
+    }
+
+    if !decision.approve {
+        tracing::info!("User {} denied authorization for client: {}", user_id, client.client_id);
+        return Ok(Json(AuthorizeDecisionResponse {
+            redirect_to: error_redirect(&params, "access_denied", "The user denied the request")?,
+        }));
+    }
+
+    let code = token::generate_opaque_token();
+    state.authorization_codes.insert(token::hash_token(&code), PendingCode {
+        client_id: client.client_id.clone(),
+        user_id,
+        redirect_uri: params.redirect_uri.clone(),
+        code_challenge: params.code_challenge.clone().unwrap_or_default(),
+        scope: params.scope.clone(),
+        nonce: params.nonce.clone(),
+        created_at: Instant::now(),
+    });
+    tracing::info!("Issued authorization code to client: {} for user: {}", client.client_id, user_id);
+
+    let mut redirect_to = parse_redirect_uri(&params.redirect_uri)?;
+    redirect_to.query_pairs_mut().append_pair("code", &code);
+    if let Some(oauth_state) = &params.state {
+        redirect_to.query_pairs_mut().append_pair("state", oauth_state);
+    }
+
+    Ok(Json(AuthorizeDecisionResponse {
+        redirect_to: redirect_to.to_string(),
+    }))
+}
+
+/// Exchanges an authorization code for an access token for `/userinfo`,
+/// addressed to the client, plus an ID token for `openid` requests. Only the
+/// authorization-code grant is supported.
+pub async fn token(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(request): Form<TokenRequest>,
+) -> Result<Response, OAuthError> {
+    // Checking a client secret costs an Argon2 hash, so guesses are limited
+    let client_ip = addr.ip().to_string();
+    if let Err(msg) = state.rate_limiter.check_ip_limit(&client_ip) {
+        tracing::warn!("Rate limit exceeded for IP: {} - {}", client_ip, msg);
+        return Ok(AppError::TooManyRequests(msg).into_response());
+    }
+
+    if request.grant_type != "authorization_code" {
+        return Err(OAuthError::new("unsupported_grant_type", "Only authorization_code is supported"));
+    }
+
+    let client = authenticate_client(&state, &headers, &request).await?;
+
+    let code = request
+        .code
+        .as_deref()
+        .ok_or_else(|| OAuthError::new("invalid_request", "Missing code"))?;
+    let pending = state
+        .authorization_codes
+        .take(&token::hash_token(code))
+        .ok_or_else(|| OAuthError::new("invalid_grant", "Unknown, expired or already used code"))?;
+
+    if pending.client_id != client.client_id {
+        tracing::warn!("Client {} presented a code issued to {}", client.client_id, pending.client_id);
+        return Err(OAuthError::new("invalid_grant", "Code was issued to another client"));
+    }
+
+    if request.redirect_uri.as_deref() != Some(pending.redirect_uri.as_str()) {
+        return Err(OAuthError::new("invalid_grant", "redirect_uri does not match the authorization request"));
+    }
+
+    let code_verifier = request
+        .code_verifier
+        .as_deref()
+        .ok_or_else(|| OAuthError::new("invalid_request", "Missing code_verifier"))?;
+    // S256 is BASE64URL(SHA256(verifier)), exactly what hash_token computes
+    if token::hash_token(code_verifier) != pending.code_challenge {
+        return Err(OAuthError::new("invalid_grant", "code_verifier does not match the code challenge"));
+    }
+
+    let user = state
+        .store
+        .find_user_by_id(pending.user_id)
+        .await?
+        .ok_or_else(|| OAuthError::new("invalid_grant", "The user no longer exists"))?;
+
+    // Clients act on the user's behalf but never with their roles
+    let access_token = jwt::generate_token(
+        &user,
+        &UserGrants::default(),
+        &state.jwt_keys,
+        state.config.jwt.expiration,
+        Some(&client.client_id),
+    )?;
+    let id_token = match &pending.scope {
+        Some(scope) if scope.split(' ').any(|s| s == "openid") => Some(jwt::generate_id_token(
+            &user,
+            &client.client_id,
+            pending.nonce,
+            &state.jwt_keys,
+            state.config.jwt.expiration,
+        )?),
+        _ => None,
+    };
+    tracing::info!("Issued access token to client: {} for user: {}", client.client_id, user.id);
+
+    let body = Json(TokenResponse {
+        access_token,
+        token_type: "Bearer",
+        expires_in: state.config.jwt.expiration,
+        scope: pending.scope,
+        id_token,
+    });
+
+    Ok(([(header::CACHE_CONTROL, "no-store"), (header::PRAGMA, "no-cache")], body).into_response())
+}
+
+async fn find_client(state: &AppState, params: &AuthorizeParams) -> Result<OAuthClient, AppError> {
+    let client = state
+        .oauth_clients
+        .find_oauth_client(&params.client_id)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Unknown client_id".to_string()))?;
+
+    if !client.allows_redirect_uri(&params.redirect_uri) {
+        tracing::warn!("Rejected unregistered redirect_uri for client: {}", client.client_id);
+        return Err(AppError::BadRequest("redirect_uri is not registered for this client".to_string()));
+    }
+
+    Ok(client)
+}
+
+/// Checks the parts of the request that can be reported back to the client.
+fn check_request(client: &OAuthClient, params: &AuthorizeParams) -> Result<(), (&'static str, &'static str)> {
+    if params.response_type != "code" {
+        return Err(("unsupported_response_type", "Only response_type=code is supported"));
+    }
+
+    // The granted scope is echoed to the client and decides whether it gets
+    // an ID token, so only registered scopes are accepted
+    if params.scope.as_deref().is_some_and(|scope| !client.allows_scope(scope)) {
+        tracing::warn!("Rejected unregistered scope for client: {}", client.client_id);
+        return Err(("invalid_scope", "The requested scope is not allowed for this client"));
+    }
+
+    // Every client must use PKCE, and only the S256 method
+    if params.code_challenge.as_deref().is_none_or(str::is_empty)
+        || params.code_challenge_method.as_deref() != Some("S256")
+    {
+        return Err(("invalid_request", "PKCE with code_challenge_method=S256 is required"));
+    }
+
+    Ok(())
+}
+
+fn error_redirect(params: &AuthorizeParams, error: &str, description: &str) -> Result<String, AppError> {
+    let mut url = parse_redirect_uri(&params.redirect_uri)?;
+    url.query_pairs_mut()
+        .append_pair("error", error)
+        .append_pair("error_description", description);
+    if let Some(oauth_state) = &params.state {
+        url.query_pairs_mut().append_pair("state", oauth_state);
+    }
+
+    Ok(url.to_string())
+}
+
+fn parse_redirect_uri(redirect_uri: &str) -> Result<Url, AppError> {
+    Url::parse(redirect_uri)
+        .map_err(|e| AppError::Internal(format!("Invalid registered redirect_uri: {}", e)))
+}
+
+/// Confidential clients authenticate with HTTP Basic or form credentials;
+/// public clients (no stored secret) are identified by `client_id` alone.
+async fn authenticate_client(
+    state: &AppState,
+    headers: &HeaderMap,
+    request: &TokenRequest,
+) -> Result<OAuthClient, OAuthError> {
+    let (client_id, client_secret) = match basic_credentials(headers) {
+        Some((id, secret)) => (id, Some(secret)),
+        None => (
+            request
+                .client_id
+                .clone()
+                .ok_or_else(|| OAuthError::new("invalid_client", "Missing client credentials"))?,
+            request.client_secret.clone(),
+        ),
+    };
+
+    let client = state
+        .oauth_clients
+        .find_oauth_client(&client_id)
+        .await?
+        .ok_or_else(|| OAuthError::new("invalid_client", "Unknown client"))?;
+
+    if let Some(secret_hash) = &client.client_secret_hash {
+        let secret = client_secret
+            .ok_or_else(|| OAuthError::new("invalid_client", "Client authentication required"))?;
+
+        if !hashing::verify_password(&secret, secret_hash)? {
+            tracing::warn!("Failed client authentication for client: {}", client.client_id);
+            return Err(OAuthError::new("invalid_client", "Invalid client credentials"));
+        }
+    }
+
+    Ok(client)
+}
+
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let encoded = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Basic "))?;
+
+    let decoded = String::from_utf8(STANDARD.decode(encoded).ok()?).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+
+    Some((client_id.to_string(), client_secret.to_string()))
+}
@@ -0,0 +1,148 @@
+use axum::{
+    Json,
+    extract::{ConnectInfo, State},
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+use chrono::{Duration, Utc};
+use std::net::SocketAddr;
+use crate::error::AppError;
+use crate::handlers::{auth_handler::{self, AppState}, mfa_handler};
+use crate::models::user::User;
+use crate::utils::token;
+
+#[derive(Debug, Deserialize)]
+pub struct MagicLinkRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MagicLinkConsumeRequest {
+    pub token: String,
+}
+
+/// Emails a single-use login link. Addresses without an account only get
+/// one when open registration is enabled; the response is the same either way.
+pub async fn request_magic_link(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    Json(payload): Json<MagicLinkRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let client_ip = addr.ip().to_string();
+    if let Err(msg) = state.rate_limiter.check_ip_limit(&client_ip) {
+        tracing::warn!("Rate limit exceeded for IP: {} - {}", client_ip, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    if let Err(msg) = state.rate_limiter.check_mail_request_limit(&payload.email) {
+        tracing::warn!("Rate limit exceeded for magic link email: {} - {}", payload.email, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    let existing = state.store.find_user_by_email(&payload.email).await?;
+    if existing.is_some() || state.config.registration.open {
+        let magic_token = token::generate_opaque_token();
+        let expires_at = (Utc::now() + Duration::seconds(state.config.magic_link.expiration)).naive_utc();
+        state
+            .magic_links
+            .create_magic_link_token(payload.email.clone(), token::hash_token(&magic_token), expires_at)
+            .await?;
+
+        let email = state.mail_templates.render(
+            "magic_link",
+            &payload.email,
+            "Your login link",
+            serde_json::json!({
+                "token": magic_token,
+                "expires_in_minutes": state.config.magic_link.expiration / 60,
+                "new_account": existing.is_none(),
+            }),
+        )?;
+
+        // Sent in the background so the delay doesn't reveal whether the
+        // account exists
+        let mailer = state.mailer.clone();
+        tokio::spawn(async move {
+            if let Err(e) = mailer.send(email).await {
+                tracing::error!("Failed to send magic link email: {}", e);
+            }
+        });
+        tracing::info!("Magic link requested for email: {} from IP: {}", payload.email, client_ip);
+    } else {
+        tracing::info!("Magic link requested for unknown email: {} with registration closed", payload.email);
+    }
+
+    Ok(Json(serde_json::json!({
+        "message": "If this email can log in, a login link has been sent"
+    })))
+}
+
+/// Exchanges a magic link token for a session, creating the account on
+/// first use. Following the link proves the address, so it is marked
+/// verified, which is all `REQUIRE_EMAIL_VERIFICATION` asks for. Accounts
+/// with 2FA still get an `mfa_required` challenge.
+pub async fn consume_magic_link(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(payload): Json<MagicLinkConsumeRequest>,
+) -> Result<Response, AppError> {
+    let client_ip = addr.ip().to_string();
+    if let Err(msg) = state.rate_limiter.check_ip_limit(&client_ip) {
+        tracing::warn!("Rate limit exceeded for IP: {} - {}", client_ip, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    let email = state
+        .magic_links
+        .consume_magic_link_token(&token::hash_token(&payload.token))
+        .await?
+        .ok_or_else(|| {
+            tracing::warn!("Invalid or expired magic link from IP: {}", client_ip);
+            AppError::Unauthorized
+        })?;
+
+    let user = match state.store.find_user_by_email(&email).await? {
+        Some(user) => user,
+        None => create_magic_link_user(&state, email).await?,
+    };
+
+    // As when linking a login, a password on an unverified account may
+    // belong to whoever registered the address ahead of its owner
+    if user.email_verified_at.is_none() && user.password_hash.is_some() {
+        state.store.clear_user_password(user.id).await?;
+        state.refresh_tokens.revoke_user_refresh_tokens(user.id).await?;
+        state.revocations.revoke_all_issued(user.id).await?;
+        tracing::warn!("Cleared the password of unverified user: {} on magic link login", user.id);
+    }
+    state.store.mark_email_verified(user.id).await?;
+
+    // As with passwords, the email limit keeps counting until the second
+    // factor is verified
+    if let Some(challenge) = mfa_handler::challenge(&state, &user).await? {
+        tracing::info!("Magic link accepted for email: {} from IP: {}, awaiting second factor", user.email, client_ip);
+        return Ok(Json(challenge).into_response());
+    }
+
+    state.rate_limiter.reset_email_limit(&user.email);
+    tracing::info!("Successful magic link login for email: {} from IP: {}", user.email, client_ip);
+
+    Ok(auth_handler::start_session(&state, jar, user, None).await?.into_response())
+}
+
+/// Registration may have closed since the link was sent, so it is checked
+/// again here.
+async fn create_magic_link_user(state: &AppState, email: String) -> Result<User, AppError> {
+    if !state.config.registration.open {
+        tracing::warn!("Rejected magic link sign-up for email: {}, registration is closed", email);
+        return Err(AppError::Unauthorized);
+    }
+
+    // Users can change it later; the local part is a friendlier default than nothing
+    let name = email.split('@').next().unwrap_or_default().to_string();
+    let user = state.store.create_user(email, name, None, None, None).await?;
+    tracing::info!("Created passwordless user: {} from a magic link", user.id);
+
+    Ok(user)
+}
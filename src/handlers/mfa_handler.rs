@@ -0,0 +1,332 @@
+use axum::{
+    Json,
+    extract::{ConnectInfo, State},
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::Utc;
+use data_encoding::BASE32_NOPAD;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::Instant;
+use uuid::Uuid;
+use crate::error::AppError;
+use crate::handlers::{auth_handler::{self, AppState}, webauthn_handler};
+use crate::middleware::{auth_middleware::AuthUser, mfa_challenge::PendingMfa};
+use crate::models::mfa::TotpSecret;
+use crate::models::user::User;
+use crate::utils::{token, totp, webauthn::AuthenticationCredential};
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Returned by login instead of tokens when the account has 2FA enabled.
+/// The client exchanges `mfa_token` at `/api/auth/mfa/verify`.
+#[derive(Debug, Serialize)]
+pub struct MfaRequired {
+    pub code: &'static str,
+    pub mfa_token: String,
+    pub methods: Vec<&'static str>,
+    pub expires_in: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MfaVerifyRequest {
+    pub mfa_token: String,
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+    /// Passkey assertion answering `/api/auth/mfa/webauthn`
+    pub webauthn: Option<AuthenticationCredential>,
+}
+
+/// Proof of the second factor for changing 2FA settings: a current TOTP
+/// code or an unused recovery code.
+#[derive(Debug, Deserialize)]
+pub struct SecondFactorRequest {
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotpConfirmRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaStatusResponse {
+    pub enabled: bool,
+    pub recovery_codes_remaining: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TotpEnrollmentResponse {
+    /// Base32 secret, for typing into an app that can't scan the URI
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Shown once; only hashes are stored.
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+pub async fn status(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<MfaStatusResponse>, AppError> {
+    let enabled = state
+        .mfa
+        .find_totp_secret(auth.user_id)
+        .await?
+        .is_some_and(|secret| secret.is_enabled());
+
+    Ok(Json(MfaStatusResponse {
+        enabled,
+        recovery_codes_remaining: state.mfa.count_unused_recovery_codes(auth.user_id).await?,
+    }))
+}
+
+/// Starts TOTP enrollment with a new secret. 2FA stays off until a code
+/// from the authenticator app is confirmed.
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<TotpEnrollmentResponse>, AppError> {
+    let user = auth.load(&state).await?;
+
+    let secret = totp::generate_secret();
+    state.mfa.set_pending_totp_secret(user.id, secret.clone()).await?;
+
+    Ok(Json(TotpEnrollmentResponse {
+        otpauth_uri: totp::provisioning_uri(&state.config.mfa.issuer, &user.email, &secret),
+        secret,
+    }))
+}
+
+/// Enables 2FA once the user proves their app generates valid codes, and
+/// returns their recovery codes.
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<TotpConfirmRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    let secret = state
+        .mfa
+        .find_totp_secret(auth.user_id)
+        .await?
+        .filter(|secret| !secret.is_enabled())
+        .ok_or_else(|| AppError::BadRequest("No pending two-factor enrollment".to_string()))?;
+
+    let step = totp::verify(&secret.secret, &payload.code, Utc::now().timestamp())
+        .ok_or_else(|| AppError::BadRequest("Invalid code".to_string()))?;
+
+    let (recovery_codes, hashes) = generate_recovery_codes();
+    state.mfa.enable_totp(auth.user_id, step, hashes).await?;
+    tracing::info!("Enabled two-factor authentication for user: {}", auth.user_id);
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+pub async fn disable_totp(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<SecondFactorRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let user = auth.load(&state).await?;
+    require_second_factor(&state, &user, &payload).await?;
+
+    state.mfa.disable_totp(user.id).await?;
+    tracing::info!("Disabled two-factor authentication for user: {}", user.id);
+
+    Ok(Json(serde_json::json!({
+        "message": "Two-factor authentication disabled"
+    })))
+}
+
+/// Replaces every recovery code, used or not, with a new set.
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<SecondFactorRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    let user = auth.load(&state).await?;
+    require_second_factor(&state, &user, &payload).await?;
+
+    let (recovery_codes, hashes) = generate_recovery_codes();
+    state.mfa.replace_recovery_codes(user.id, hashes).await?;
+    tracing::info!("Regenerated recovery codes for user: {}", user.id);
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+/// Exchanges an `mfa_pending` token and a TOTP code, recovery code or
+/// passkey assertion for the session the login would have started.
+pub async fn verify(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(payload): Json<MfaVerifyRequest>,
+) -> Result<(CookieJar, Json<auth_handler::AuthResponse>), AppError> {
+    let client_ip = addr.ip().to_string();
+    if let Err(msg) = state.rate_limiter.check_ip_limit(&client_ip) {
+        tracing::warn!("Rate limit exceeded for IP: {} - {}", client_ip, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    let token_hash = token::hash_token(&payload.mfa_token);
+    let pending = state.mfa_challenges.get(&token_hash).ok_or(AppError::Unauthorized)?;
+
+    // The password step left the email limit running, so repeated logins
+    // can't be used to guess codes without bound
+    if let Err(msg) = state.rate_limiter.check_email_limit(&pending.email) {
+        tracing::warn!("Rate limit exceeded for email: {} - {}", pending.email, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    let verified = match &payload.webauthn {
+        Some(credential) => {
+            webauthn_handler::verify_second_factor(&state, &token_hash, pending.user_id, credential).await?
+        }
+        None => check_second_factor(
+            &state,
+            pending.user_id,
+            payload.code.as_deref(),
+            payload.recovery_code.as_deref(),
+        ).await?,
+    };
+
+    if !verified {
+        state.mfa_challenges.record_failure(&token_hash);
+        tracing::warn!("Failed second factor for email: {} from IP: {}", pending.email, client_ip);
+        return Err(AppError::Unauthorized);
+    }
+
+    let pending = state.mfa_challenges.take(&token_hash).ok_or(AppError::Unauthorized)?;
+    state.rate_limiter.reset_email_limit(&pending.email);
+
+    let user = state.store.find_user_by_id(pending.user_id).await?
+        .ok_or(AppError::Unauthorized)?;
+    tracing::info!("Successful login with second factor for email: {} from IP: {}", user.email, client_ip);
+
+    auth_handler::start_session(&state, jar, user, None).await
+}
+
+/// Starts an `mfa_pending` challenge if `user` has 2FA enabled, through a
+/// confirmed authenticator app or a registered passkey. Callers must return
+/// it instead of issuing tokens.
+pub(crate) async fn challenge(state: &AppState, user: &User) -> Result<Option<MfaRequired>, AppError> {
+    let totp_enabled = state
+        .mfa
+        .find_totp_secret(user.id)
+        .await?
+        .is_some_and(|secret| secret.is_enabled());
+    let has_passkey = !state.webauthn.find_webauthn_credentials_by_user(user.id).await?.is_empty();
+
+    if !totp_enabled && !has_passkey {
+        return Ok(None);
+    }
+
+    let mut methods = Vec::new();
+    if totp_enabled {
+        methods.extend(["totp", "recovery_code"]);
+    }
+    if has_passkey {
+        methods.push("webauthn");
+    }
+
+    let mfa_token = token::generate_opaque_token();
+    state.mfa_challenges.insert(token::hash_token(&mfa_token), PendingMfa {
+        user_id: user.id,
+        email: user.email.clone(),
+        failed_attempts: 0,
+        created_at: Instant::now(),
+    });
+
+    Ok(Some(MfaRequired {
+        code: "mfa_required",
+        mfa_token,
+        methods,
+        expires_in: state.mfa_challenges.ttl().as_secs(),
+    }))
+}
+
+async fn require_second_factor(
+    state: &AppState,
+    user: &User,
+    payload: &SecondFactorRequest,
+) -> Result<(), AppError> {
+    let enabled = state
+        .mfa
+        .find_totp_secret(user.id)
+        .await?
+        .is_some_and(|secret| secret.is_enabled());
+    if !enabled {
+        return Err(AppError::BadRequest("Two-factor authentication is not enabled".to_string()));
+    }
+
+    if let Err(msg) = state.rate_limiter.check_email_limit(&user.email) {
+        tracing::warn!("Rate limit exceeded for email: {} - {}", user.email, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    if !check_second_factor(state, user.id, payload.code.as_deref(), payload.recovery_code.as_deref()).await? {
+        tracing::warn!("Failed second factor for user: {}", user.id);
+        return Err(AppError::Unauthorized);
+    }
+
+    state.rate_limiter.reset_email_limit(&user.email);
+    Ok(())
+}
+
+/// Accepts a TOTP code not used before, or consumes a recovery code.
+async fn check_second_factor(
+    state: &AppState,
+    user_id: Uuid,
+    code: Option<&str>,
+    recovery_code: Option<&str>,
+) -> Result<bool, AppError> {
+    match (code, recovery_code) {
+        (Some(code), _) => {
+            let Some(secret) = state.mfa.find_totp_secret(user_id).await?.filter(TotpSecret::is_enabled) else {
+                return Ok(false);
+            };
+
+            match totp::verify(&secret.secret, code, Utc::now().timestamp()) {
+                Some(step) => state.mfa.use_totp_step(user_id, step).await,
+                None => Ok(false),
+            }
+        }
+        (None, Some(recovery_code)) => {
+            let code_hash = token::hash_token(&normalize_recovery_code(recovery_code));
+            let used = state.mfa.use_recovery_code(user_id, &code_hash).await?;
+            if used {
+                tracing::info!("Recovery code used by user: {}", user_id);
+            }
+            Ok(used)
+        }
+        (None, None) => Err(AppError::BadRequest("Either code or recovery_code is required".to_string())),
+    }
+}
+
+/// Returns the codes to show the user alongside the hashes to store. Each
+/// code carries 80 bits, grouped as `xxxx-xxxx-xxxx-xxxx`.
+fn generate_recovery_codes() -> (Vec<String>, Vec<String>) {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 10];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            let raw = BASE32_NOPAD.encode(&bytes).to_lowercase();
+
+            let code = format!("{}-{}-{}-{}", &raw[0..4], &raw[4..8], &raw[8..12], &raw[12..16]);
+            (code, token::hash_token(&raw))
+        })
+        .unzip()
+}
+
+// Users may retype codes without dashes or in upper case
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
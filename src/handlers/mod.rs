@@ -1,2 +1,11 @@
+pub mod admin_handler;
 pub mod auth_handler;
+pub mod magic_link_handler;
+pub mod authorization_handler;
+pub mod mfa_handler;
+pub mod oauth_handler;
+pub mod oidc_handler;
+pub mod password_handler;
 pub mod user_handler;
+pub mod verification_handler;
+pub mod webauthn_handler;
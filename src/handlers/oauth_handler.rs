@@ -0,0 +1,401 @@
+use axum::{
+    Json,
+    extract::{ConnectInfo, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::Instant;
+use uuid::Uuid;
+use crate::error::AppError;
+use crate::handlers::{auth_handler::{self, AppState}, mfa_handler};
+use crate::handlers::user_handler::IdentityResponse;
+use crate::middleware::{account_link::PendingLink, auth_middleware::AuthUser, oauth_state::PendingAuthorization};
+use crate::models::user::User;
+use crate::oauth::{AuthorizationRequest, ProviderIdentity};
+use crate::utils::{hashing, token};
+
+// Binds a pending OAuth `state` to the browser that started the flow
+const OAUTH_BINDING_COOKIE: &str = "oauth_binding";
+
+#[derive(Debug, Deserialize)]
+pub struct OAuthCallbackQuery {
+    pub code: String,
+    pub state: Option<String>,
+}
+
+/// Returned with `409 Conflict` when an OAuth login matches an existing
+/// account; the owner must confirm through `/api/auth/link/confirm`.
+#[derive(Debug, Serialize)]
+pub struct AccountLinkRequired {
+    pub error: String,
+    pub code: &'static str,
+    pub link_token: String,
+    // Ways the owner can prove the account is theirs: "password" and/or "email"
+    pub methods: Vec<&'static str>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinkEmailRequest {
+    pub link_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinkConfirmRequest {
+    pub link_token: String,
+    pub password: Option<String>,
+    pub code: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinkResponse {
+    /// Where the browser must be sent to approve the link at the provider
+    pub authorization_url: String,
+}
+
+pub async fn authorize(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    Path(provider_name): Path<String>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AppError> {
+    // Every call holds a slot in the shared pending-authorization store
+    let client_ip = addr.ip().to_string();
+    if let Err(msg) = state.rate_limiter.check_ip_limit(&client_ip) {
+        tracing::warn!("Rate limit exceeded for IP: {} - {}", client_ip, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    let provider = state.providers.get(&provider_name).ok_or(AppError::NotFound)?;
+    let request = provider.authorization_request().await?;
+    let url = request.url.clone();
+
+    let jar = bind_oauth_state(&state, jar, &provider_name, request, &provider.config().redirect_url, None)?;
+
+    Ok((jar, Redirect::to(&url)))
+}
+
+/// Starts linking `provider` to the current account. The client must send
+/// the browser to `authorization_url`; the usual callback completes the link.
+pub async fn start_link(
+    State(state): State<AppState>,
+    Path(provider_name): Path<String>,
+    auth: AuthUser,
+    jar: CookieJar,
+) -> Result<(CookieJar, Json<LinkResponse>), AppError> {
+    let provider = state.providers.get(&provider_name).ok_or(AppError::NotFound)?;
+    let request = provider.authorization_request().await?;
+    let authorization_url = request.url.clone();
+
+    let jar = bind_oauth_state(
+        &state,
+        jar,
+        &provider_name,
+        request,
+        &provider.config().redirect_url,
+        Some(auth.user_id),
+    )?;
+
+    Ok((jar, Json(LinkResponse { authorization_url })))
+}
+
+pub async fn callback(
+    State(state): State<AppState>,
+    Path(provider_name): Path<String>,
+    jar: CookieJar,
+    Query(query): Query<OAuthCallbackQuery>,
+) -> Result<Response, AppError> {
+    let provider = state.providers.get(&provider_name).ok_or(AppError::NotFound)?;
+
+    let pending = verify_oauth_state(&state, &jar, query.state.as_deref(), &provider_name)?;
+    let jar = jar.remove(Cookie::build(OAUTH_BINDING_COOKIE).path("/api/auth"));
+
+    let identity = provider
+        .authenticate(query.code, pending.pkce_verifier, pending.nonce.as_deref())
+        .await?;
+
+    if let Some(user_id) = pending.link_user_id {
+        let linked = state
+            .identities
+            .link_identity(user_id, &provider_name, &identity.subject, Some(identity.email))
+            .await?;
+        tracing::info!("Linked {} identity to user: {}", provider_name, user_id);
+
+        return Ok((jar, Json(IdentityResponse::from(linked))).into_response());
+    }
+
+    tracing::info!("OAuth login via {} for subject: {}", provider_name, identity.subject);
+
+    if let Some(user) = state.store.find_user_by_oauth(&provider_name, &identity.subject).await? {
+        if identity.email_verified && user.email_verified_at.is_none() && identity.email == user.email {
+            state.store.mark_email_verified(user.id).await?;
+        }
+        if let Some(challenge) = mfa_handler::challenge(&state, &user).await? {
+            return Ok((jar, Json(challenge)).into_response());
+        }
+        return Ok(auth_handler::start_session(&state, jar, user, None).await?.into_response());
+    }
+
+    if let Some(existing) = state.store.find_user_by_email(&identity.email).await? {
+        let link = start_account_link(&state, &provider_name, existing, identity)?;
+        return Ok((jar, (StatusCode::CONFLICT, Json(link))).into_response());
+    }
+
+    if !state.config.registration.open {
+        tracing::warn!("Rejected {} sign-up for subject: {}, registration is closed", provider_name, identity.subject);
+        return Err(AppError::Forbidden);
+    }
+
+    let user = create_oauth_user(&state, &provider_name, identity).await?;
+
+    Ok(auth_handler::start_session(&state, jar, user, None).await?.into_response())
+}
+
+/// Sends the account owner a code that confirms linking the pending login.
+pub async fn request_link_email(
+    State(state): State<AppState>,
+    Json(payload): Json<LinkEmailRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let token_hash = token::hash_token(&payload.link_token);
+    let pending = state.account_links.get(&token_hash).ok_or(AppError::Unauthorized)?;
+
+    if let Err(msg) = state.rate_limiter.check_mail_request_limit(&pending.email) {
+        tracing::warn!("Rate limit exceeded for link email: {} - {}", pending.email, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    let code = token::generate_opaque_token();
+    if !state.account_links.set_email_code(&token_hash, token::hash_token(&code)) {
+        return Err(AppError::Unauthorized);
+    }
+
+    let email = state.mail_templates.render(
+        "account_link",
+        &pending.email,
+        format!("Confirm your {} login", pending.provider),
+        serde_json::json!({ "provider": pending.provider, "code": code }),
+    )?;
+    state.mailer.send(email).await?;
+    tracing::info!("Sent account link confirmation code to {} ({} login)", pending.email, pending.provider);
+
+    Ok(Json(serde_json::json!({
+        "message": "A confirmation code has been sent to the account's email address"
+    })))
+}
+
+/// Links the pending OAuth identity once the owner proves the account is
+/// theirs, by password or by the emailed code, and logs them in (through
+/// the 2FA challenge if they have it enabled).
+pub async fn confirm_link(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(payload): Json<LinkConfirmRequest>,
+) -> Result<Response, AppError> {
+    let client_ip = addr.ip().to_string();
+    if let Err(msg) = state.rate_limiter.check_ip_limit(&client_ip) {
+        tracing::warn!("Rate limit exceeded for IP: {} - {}", client_ip, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    let token_hash = token::hash_token(&payload.link_token);
+    let pending = state.account_links.get(&token_hash).ok_or(AppError::Unauthorized)?;
+
+    if let Err(msg) = state.rate_limiter.check_email_limit(&pending.email) {
+        tracing::warn!("Rate limit exceeded for email: {} - {}", pending.email, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    let user = state.store.find_user_by_id(pending.user_id).await?
+        .ok_or(AppError::Unauthorized)?;
+
+    let by_email = payload.password.is_none();
+    let confirmed = match (&payload.password, &payload.code) {
+        // Whoever registered an unverified address may not own it, so its
+        // password proves nothing
+        (Some(_), _) if user.email_verified_at.is_none() => {
+            return Err(AppError::BadRequest(
+                "This account's email address is unverified. Confirm with the emailed code".to_string(),
+            ));
+        }
+        (Some(password), _) => match &user.password_hash {
+            Some(password_hash) => hashing::verify_password(password, password_hash)?,
+            None => false,
+        },
+        (None, Some(code)) => pending.email_code_hash.as_deref() == Some(token::hash_token(code).as_str()),
+        (None, None) => {
+            return Err(AppError::BadRequest("Either password or code is required".to_string()));
+        }
+    };
+
+    if !confirmed {
+        tracing::warn!("Failed account link confirmation for email: {} from IP: {}", pending.email, client_ip);
+        return Err(AppError::Unauthorized);
+    }
+
+    let pending = state.account_links.take(&token_hash).ok_or(AppError::Unauthorized)?;
+    state.rate_limiter.reset_email_limit(&pending.email);
+
+    state
+        .identities
+        .link_identity(user.id, &pending.provider, &pending.provider_user_id, Some(pending.email))
+        .await?;
+    tracing::info!("Linked {} identity to existing user: {}", pending.provider, user.id);
+
+    // A password on an account whose address was never verified may have
+    // been set by someone else ahead of its real owner. Now that the owner
+    // has proved the address, lock that person out.
+    if user.email_verified_at.is_none() && user.password_hash.is_some() {
+        state.store.clear_user_password(user.id).await?;
+        state.refresh_tokens.revoke_user_refresh_tokens(user.id).await?;
+        state.revocations.revoke_all_issued(user.id).await?;
+        tracing::warn!("Cleared the password of unverified user: {} on linking {}", user.id, pending.provider);
+    }
+
+    // The emailed code proved the owner can read mail sent to the account
+    if by_email {
+        state.store.mark_email_verified(user.id).await?;
+    }
+
+    if let Some(challenge) = mfa_handler::challenge(&state, &user).await? {
+        return Ok(Json(challenge).into_response());
+    }
+
+    Ok(auth_handler::start_session(&state, jar, user, None).await?.into_response())
+}
+
+/// Signs up a new user from their provider identity. The address counts
+/// as verified when the provider says it is.
+async fn create_oauth_user(
+    state: &AppState,
+    provider: &str,
+    identity: ProviderIdentity,
+) -> Result<User, AppError> {
+    let email_verified = identity.email_verified;
+    let mut user = state.store.create_user(
+        identity.email,
+        identity.name,
+        None,
+        Some(provider.to_string()),
+        Some(identity.subject),
+    ).await?;
+
+    if email_verified {
+        state.store.mark_email_verified(user.id).await?;
+        user.email_verified_at = Some(Utc::now().naive_utc());
+    }
+
+    Ok(user)
+}
+
+/// An OAuth login matched an existing account by email. Linking it is only
+/// offered when the provider vouches for the email, and never happens until
+/// the account owner confirms.
+fn start_account_link(
+    state: &AppState,
+    provider: &str,
+    existing: User,
+    identity: ProviderIdentity,
+) -> Result<AccountLinkRequired, AppError> {
+    if !identity.email_verified {
+        tracing::warn!("Rejected {} login with unverified email matching user: {}", provider, existing.id);
+        return Err(AppError::Conflict {
+            code: "email_unverified",
+            message: format!(
+                "An account with this email already exists and {} has not verified the address",
+                provider
+            ),
+        });
+    }
+
+    let link_token = token::generate_opaque_token();
+    state.account_links.insert(token::hash_token(&link_token), PendingLink {
+        user_id: existing.id,
+        email: existing.email,
+        provider: provider.to_string(),
+        provider_user_id: identity.subject,
+        email_code_hash: None,
+        created_at: Instant::now(),
+    });
+
+    let mut methods = vec!["email"];
+    if existing.password_hash.is_some() && existing.email_verified_at.is_some() {
+        methods.insert(0, "password");
+    }
+
+    Ok(AccountLinkRequired {
+        error: "An account with this email already exists. Confirm it is yours to link this login".to_string(),
+        code: "account_link_required",
+        link_token,
+        methods,
+    })
+}
+
+/// Remembers a pending authorization under its `state` and sets the cookie
+/// that ties it to this browser.
+fn bind_oauth_state(
+    state: &AppState,
+    jar: CookieJar,
+    provider: &str,
+    request: AuthorizationRequest,
+    redirect_url: &str,
+    link_user_id: Option<Uuid>,
+) -> Result<CookieJar, AppError> {
+    let binding = token::generate_opaque_token();
+    let stored = state.oauth_states.insert(request.csrf_token.secret().clone(), PendingAuthorization {
+        provider: provider.to_string(),
+        binding_hash: token::hash_token(&binding),
+        pkce_verifier: request.pkce_verifier,
+        nonce: request.nonce,
+        link_user_id,
+        created_at: Instant::now(),
+    });
+    if !stored {
+        tracing::warn!("Refused {} authorization, too many pending OAuth logins", provider);
+        return Err(AppError::TooManyRequests("Too many pending logins. Try again later".to_string()));
+    }
+
+    let cookie = Cookie::build((OAUTH_BINDING_COOKIE, binding))
+        .path("/api/auth")
+        .http_only(true)
+        .secure(redirect_url.starts_with("https://"))
+        .same_site(SameSite::Lax)
+        .max_age(state.oauth_states.ttl().try_into().unwrap_or_default());
+
+    Ok(jar.add(cookie))
+}
+
+/// Rejects a callback unless its `state` was issued by us for `provider`,
+/// hasn't expired or been used before, and belongs to the browser presenting it.
+fn verify_oauth_state(
+    state: &AppState,
+    jar: &CookieJar,
+    oauth_state: Option<&str>,
+    provider: &str,
+) -> Result<PendingAuthorization, AppError> {
+    let oauth_state = oauth_state
+        .ok_or_else(|| AppError::BadRequest("Missing OAuth state".to_string()))?;
+
+    let pending = state
+        .oauth_states
+        .take(oauth_state)
+        .ok_or_else(|| AppError::BadRequest("Unknown or expired OAuth state".to_string()))?;
+
+    let binding = jar
+        .get(OAUTH_BINDING_COOKIE)
+        .ok_or_else(|| AppError::BadRequest("OAuth state does not match this browser".to_string()))?;
+
+    if token::hash_token(binding.value()) != pending.binding_hash {
+        tracing::warn!("OAuth callback with state bound to a different browser");
+        return Err(AppError::BadRequest("OAuth state does not match this browser".to_string()));
+    }
+
+    if pending.provider != provider {
+        return Err(AppError::BadRequest("OAuth state was issued for a different provider".to_string()));
+    }
+
+    Ok(pending)
+}
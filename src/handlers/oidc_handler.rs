@@ -0,0 +1,70 @@
+use axum::{Json, extract::State};
+use jsonwebtoken::{Algorithm, jwk::JwkSet};
+use serde::Serialize;
+use crate::error::AppError;
+use crate::handlers::auth_handler::AppState;
+use crate::middleware::auth_middleware::ClientAuthUser;
+
+/// `/.well-known/openid-configuration` (OpenID Connect Discovery 1.0 §3).
+#[derive(Debug, Serialize)]
+pub struct DiscoveryDocument {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
+    pub response_types_supported: Vec<&'static str>,
+    pub grant_types_supported: Vec<&'static str>,
+    pub subject_types_supported: Vec<&'static str>,
+    pub id_token_signing_alg_values_supported: Vec<Algorithm>,
+    pub token_endpoint_auth_methods_supported: Vec<&'static str>,
+    pub code_challenge_methods_supported: Vec<&'static str>,
+    pub scopes_supported: Vec<&'static str>,
+    pub claims_supported: Vec<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserInfoResponse {
+    pub sub: String,
+    pub email: String,
+    pub name: String,
+}
+
+pub async fn discovery(State(state): State<AppState>) -> Json<DiscoveryDocument> {
+    let issuer = &state.config.jwt.issuer;
+
+    Json(DiscoveryDocument {
+        issuer: issuer.clone(),
+        authorization_endpoint: format!("{}/oauth/authorize", issuer),
+        token_endpoint: format!("{}/oauth/token", issuer),
+        userinfo_endpoint: format!("{}/userinfo", issuer),
+        jwks_uri: format!("{}/.well-known/jwks.json", issuer),
+        response_types_supported: vec!["code"],
+        grant_types_supported: vec!["authorization_code"],
+        subject_types_supported: vec!["public"],
+        id_token_signing_alg_values_supported: vec![state.jwt_keys.algorithm()],
+        token_endpoint_auth_methods_supported: vec!["client_secret_basic", "client_secret_post", "none"],
+        code_challenge_methods_supported: vec!["S256"],
+        scopes_supported: vec!["openid", "email", "profile"],
+        claims_supported: vec!["iss", "sub", "aud", "exp", "iat", "nonce", "email", "name"],
+    })
+}
+
+pub async fn jwks(State(state): State<AppState>) -> Json<JwkSet> {
+    Json(state.jwt_keys.jwks())
+}
+
+/// OIDC userinfo (Core §5.3). Accepts our own access tokens as well as
+/// those issued to OAuth clients.
+pub async fn userinfo(
+    State(state): State<AppState>,
+    ClientAuthUser(auth): ClientAuthUser,
+) -> Result<Json<UserInfoResponse>, AppError> {
+    let user = auth.load(&state).await?;
+
+    Ok(Json(UserInfoResponse {
+        sub: user.id.to_string(),
+        email: user.email,
+        name: user.name,
+    }))
+}
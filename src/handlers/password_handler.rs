@@ -0,0 +1,125 @@
+use axum::{
+    Json,
+    extract::{ConnectInfo, State},
+};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use crate::error::AppError;
+use crate::handlers::auth_handler::AppState;
+use crate::utils::{hashing, token};
+
+#[derive(Debug, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub password: String,
+}
+
+/// Sends a password reset token to the address if it belongs to an account
+/// with a password. The response is the same either way, so it can't be used
+/// to find out which emails are registered.
+pub async fn forgot_password(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let client_ip = addr.ip().to_string();
+    if let Err(msg) = state.rate_limiter.check_ip_limit(&client_ip) {
+        tracing::warn!("Rate limit exceeded for IP: {} - {}", client_ip, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    // Counted for unknown addresses too, so hitting the limit reveals nothing
+    if let Err(msg) = state.rate_limiter.check_mail_request_limit(&payload.email) {
+        tracing::warn!("Rate limit exceeded for password reset email: {} - {}", payload.email, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    let user = state
+        .store
+        .find_user_by_email(&payload.email)
+        .await?
+        .filter(|user| user.password_hash.is_some());
+
+    match user {
+        Some(user) => {
+            let reset_token = token::generate_opaque_token();
+            let expires_at = (Utc::now() + Duration::seconds(state.config.password_reset.expiration)).naive_utc();
+            state
+                .password_resets
+                .create_password_reset_token(user.id, token::hash_token(&reset_token), expires_at)
+                .await?;
+
+            let email = state.mail_templates.render(
+                "password_reset",
+                &user.email,
+                "Reset your password",
+                serde_json::json!({
+                    "token": reset_token,
+                    "expires_in_minutes": state.config.password_reset.expiration / 60,
+                }),
+            )?;
+
+            // Sent in the background so neither the delay nor a delivery
+            // failure tells the caller that the account exists
+            let mailer = state.mailer.clone();
+            tokio::spawn(async move {
+                if let Err(e) = mailer.send(email).await {
+                    tracing::error!("Failed to send password reset email: {}", e);
+                }
+            });
+            tracing::info!("Password reset requested for user: {}", user.id);
+        }
+        None => {
+            tracing::info!("Password reset requested for unknown or passwordless email: {}", payload.email);
+        }
+    }
+
+    Ok(Json(serde_json::json!({
+        "message": "If an account with a password exists for this email, a reset link has been sent"
+    })))
+}
+
+/// Sets a new password with an emailed token and signs the account out
+/// everywhere, in case the old password was what leaked.
+pub async fn reset_password(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let client_ip = addr.ip().to_string();
+    if let Err(msg) = state.rate_limiter.check_ip_limit(&client_ip) {
+        tracing::warn!("Rate limit exceeded for IP: {} - {}", client_ip, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    let user_id = state
+        .password_resets
+        .consume_password_reset_token(&token::hash_token(&payload.token))
+        .await?
+        .ok_or_else(|| {
+            tracing::warn!("Invalid or expired password reset token from IP: {}", client_ip);
+            AppError::BadRequest("Invalid or expired reset token".to_string())
+        })?;
+
+    let password_hash = hashing::hash_password(&payload.password)?;
+    let user = state.store.update_user_password(user_id, password_hash).await?;
+    // Following the emailed link proved the address is theirs
+    state.store.mark_email_verified(user.id).await?;
+
+    // Cutoffs have second precision and only reject tokens issued strictly
+    // before them, so round up to cover tokens from this very second
+    state.revocations.revoke_all_before(user.id, Utc::now() + Duration::seconds(1)).await?;
+    state.refresh_tokens.revoke_user_refresh_tokens(user.id).await?;
+    state.rate_limiter.reset_email_limit(&user.email);
+    tracing::info!("Password reset for user: {} from IP: {}, all sessions revoked", user.id, client_ip);
+
+    Ok(Json(serde_json::json!({
+        "message": "Password has been reset. Log in with your new password"
+    })))
+}
@@ -1,45 +1,74 @@
-use axum::{Json, extract::State, http::header};
+use axum::{Json, extract::{Path, State}};
 use serde::Serialize;
-use uuid::Uuid;
 use crate::error::AppError;
 use crate::handlers::auth_handler::AppState;
-use crate::utils::jwt;
+use crate::middleware::auth_middleware::AuthUser;
+use crate::models::user_identity::UserIdentity;
 
 #[derive(Debug, Serialize)]
 pub struct ProfileResponse {
     pub id: String,
     pub email: String,
+    pub email_verified: bool,
     pub name: String,
-    pub oauth_provider: Option<String>,
+    pub has_password: bool,
+    pub oauth_providers: Vec<String>,
     pub created_at: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct IdentityResponse {
+    pub provider: String,
+    pub email: Option<String>,
+    pub created_at: String,
+}
+
+impl From<UserIdentity> for IdentityResponse {
+    fn from(identity: UserIdentity) -> Self {
+        Self {
+            provider: identity.provider,
+            email: identity.email,
+            created_at: identity.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
+
 pub async fn get_profile(
     State(state): State<AppState>,
-    headers: axum::http::HeaderMap,
+    auth: AuthUser,
 ) -> Result<Json<ProfileResponse>, AppError> {
-    let auth_header = headers
-        .get(header::AUTHORIZATION)
-        .and_then(|h| h.to_str().ok())
-        .ok_or(AppError::Unauthorized)?;
-
-    let token = auth_header
-        .strip_prefix("Bearer ")
-        .ok_or(AppError::Unauthorized)?;
-
-    let claims = jwt::verify_token(token, &state.config.jwt.secret)?;
-    
-    let user_id = Uuid::parse_str(&claims.sub)
-        .map_err(|_| AppError::Unauthorized)?;
-
-    let user = state.store.find_user_by_id(user_id).await?
-        .ok_or(AppError::NotFound)?;
+    let user = auth.load(&state).await?;
+    let identities = state.identities.find_identities_by_user(user.id).await?;
 
     Ok(Json(ProfileResponse {
         id: user.id.to_string(),
         email: user.email,
+        email_verified: user.email_verified_at.is_some(),
         name: user.name,
-        oauth_provider: user.oauth_provider,
+        has_password: user.password_hash.is_some(),
+        oauth_providers: identities.into_iter().map(|i| i.provider).collect(),
         created_at: user.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
     }))
 }
+
+pub async fn list_identities(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<Vec<IdentityResponse>>, AppError> {
+    let identities = state.identities.find_identities_by_user(auth.user_id).await?;
+
+    Ok(Json(identities.into_iter().map(IdentityResponse::from).collect()))
+}
+
+pub async fn unlink_identity(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(provider): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    state.identities.unlink_identity(auth.user_id, &provider).await?;
+    tracing::info!("Unlinked {} identity from user: {}", provider, auth.user_id);
+
+    Ok(Json(serde_json::json!({
+        "message": format!("{} login removed", provider)
+    })))
+}
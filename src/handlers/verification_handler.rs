@@ -0,0 +1,116 @@
+use axum::{
+    Json,
+    extract::{ConnectInfo, State},
+};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use crate::error::AppError;
+use crate::handlers::auth_handler::AppState;
+use crate::models::user::User;
+use crate::utils::token;
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: String,
+}
+
+/// Marks the address verified with the token from the emailed link.
+pub async fn verify_email(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let client_ip = addr.ip().to_string();
+    if let Err(msg) = state.rate_limiter.check_ip_limit(&client_ip) {
+        tracing::warn!("Rate limit exceeded for IP: {} - {}", client_ip, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    let user_id = state
+        .email_verifications
+        .consume_email_verification_token(&token::hash_token(&payload.token))
+        .await?
+        .ok_or_else(|| {
+            tracing::warn!("Invalid or expired email verification token from IP: {}", client_ip);
+            AppError::BadRequest("Invalid or expired verification link".to_string())
+        })?;
+
+    state.store.mark_email_verified(user_id).await?;
+    tracing::info!("Verified email address of user: {}", user_id);
+
+    Ok(Json(serde_json::json!({
+        "message": "Email address verified"
+    })))
+}
+
+/// Sends a new verification link if the address belongs to an unverified
+/// account. Like `forgot_password`, the response never reveals which.
+pub async fn resend_verification(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    Json(payload): Json<ResendVerificationRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let client_ip = addr.ip().to_string();
+    if let Err(msg) = state.rate_limiter.check_ip_limit(&client_ip) {
+        tracing::warn!("Rate limit exceeded for IP: {} - {}", client_ip, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    if let Err(msg) = state.rate_limiter.check_mail_request_limit(&payload.email) {
+        tracing::warn!("Rate limit exceeded for verification email: {} - {}", payload.email, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    let user = state
+        .store
+        .find_user_by_email(&payload.email)
+        .await?
+        .filter(|user| user.email_verified_at.is_none());
+
+    if let Some(user) = user {
+        send_verification(&state, &user).await?;
+    }
+
+    Ok(Json(serde_json::json!({
+        "message": "If this email belongs to an unverified account, a new verification link has been sent"
+    })))
+}
+
+/// Issues a verification token for `user` and emails the link in the
+/// background, so a slow or failing mail server neither delays the caller
+/// nor reveals the account.
+pub(crate) async fn send_verification(state: &AppState, user: &User) -> Result<(), AppError> {
+    let verification_token = token::generate_opaque_token();
+    let expires_at = (Utc::now() + Duration::seconds(state.config.email_verification.expiration)).naive_utc();
+    state
+        .email_verifications
+        .create_email_verification_token(user.id, token::hash_token(&verification_token), expires_at)
+        .await?;
+
+    let email = state.mail_templates.render(
+        "verify_email",
+        &user.email,
+        "Verify your email address",
+        serde_json::json!({
+            "name": user.name,
+            "token": verification_token,
+            "expires_in_hours": state.config.email_verification.expiration / 3600,
+        }),
+    )?;
+
+    let mailer = state.mailer.clone();
+    tokio::spawn(async move {
+        if let Err(e) = mailer.send(email).await {
+            tracing::error!("Failed to send verification email: {}", e);
+        }
+    });
+    tracing::info!("Sent email verification link to user: {}", user.id);
+
+    Ok(())
+}
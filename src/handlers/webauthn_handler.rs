@@ -0,0 +1,296 @@
+use axum::{
+    Json,
+    extract::{ConnectInfo, Path, State},
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use uuid::Uuid;
+use crate::error::AppError;
+use crate::handlers::auth_handler::{self, AppState};
+use crate::middleware::{auth_middleware::AuthUser, webauthn_challenge::Ceremony};
+use crate::models::webauthn_credential::WebAuthnCredential;
+use crate::utils::{token, webauthn::{self, AuthenticationCredential, CreationOptions, RegistrationCredential, RequestOptions}};
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterPasskeyRequest {
+    pub name: Option<String>,
+    pub credential: RegistrationCredential,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasskeyLoginRequest {
+    pub credential: AuthenticationCredential,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SecondFactorOptionsRequest {
+    pub mfa_token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PasskeyResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+}
+
+impl From<WebAuthnCredential> for PasskeyResponse {
+    fn from(credential: WebAuthnCredential) -> Self {
+        Self {
+            id: credential.id,
+            name: credential.name,
+            created_at: credential.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            last_used_at: credential.last_used_at.map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string()),
+        }
+    }
+}
+
+/// Starts adding a passkey to the signed-in account. The browser passes
+/// the options to `navigator.credentials.create()`.
+pub async fn registration_options(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<CreationOptions>, AppError> {
+    let user = auth.load(&state).await?;
+
+    // Stop the authenticator from registering the same key twice
+    let existing = state.webauthn.find_webauthn_credentials_by_user(user.id).await?;
+
+    let challenge = start_ceremony(&state, addr, Ceremony::Registration { user_id: user.id })?;
+
+    Ok(Json(webauthn::creation_options(
+        &state.config.webauthn,
+        challenge,
+        user.id.as_bytes(),
+        &user.email,
+        &user.name,
+        existing.into_iter().map(|c| c.credential_id).collect(),
+        timeout_ms(&state),
+    )))
+}
+
+pub async fn register(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<RegisterPasskeyRequest>,
+) -> Result<Json<PasskeyResponse>, AppError> {
+    let challenge = payload.credential.challenge()
+        .ok_or_else(|| AppError::BadRequest("Missing WebAuthn client data".to_string()))?;
+
+    match state.webauthn_challenges.take(&challenge) {
+        Some(Ceremony::Registration { user_id }) if user_id == auth.user_id => {}
+        _ => return Err(AppError::BadRequest("Unknown or expired WebAuthn challenge".to_string())),
+    }
+
+    let verified = webauthn::verify_registration(&state.config.webauthn, &payload.credential, &challenge)?;
+
+    let name = payload
+        .name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "Passkey".to_string());
+
+    let credential = state.webauthn.create_webauthn_credential(
+        auth.user_id,
+        verified.credential_id,
+        verified.public_key,
+        verified.sign_count.into(),
+        name,
+    ).await?;
+    tracing::info!("Registered passkey {} for user: {}", credential.id, auth.user_id);
+
+    Ok(Json(PasskeyResponse::from(credential)))
+}
+
+pub async fn list_passkeys(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<Vec<PasskeyResponse>>, AppError> {
+    let credentials = state.webauthn.find_webauthn_credentials_by_user(auth.user_id).await?;
+
+    Ok(Json(credentials.into_iter().map(PasskeyResponse::from).collect()))
+}
+
+pub async fn delete_passkey(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    state.webauthn.delete_webauthn_credential(auth.user_id, id).await?;
+    tracing::info!("Deleted passkey {} of user: {}", id, auth.user_id);
+
+    Ok(Json(serde_json::json!({
+        "message": "Passkey deleted"
+    })))
+}
+
+/// Starts a passkey login. No account is named: the authenticator offers
+/// its discoverable credentials for this site.
+pub async fn login_options(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+) -> Result<Json<RequestOptions>, AppError> {
+    let challenge = start_ceremony(&state, addr, Ceremony::Login)?;
+
+    Ok(Json(webauthn::request_options(
+        &state.config.webauthn,
+        challenge,
+        Vec::new(),
+        "required",
+        timeout_ms(&state),
+    )))
+}
+
+/// Logs in with a passkey. User verification on the authenticator already
+/// makes this two factors, so no `mfa_required` challenge follows.
+pub async fn login(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(payload): Json<PasskeyLoginRequest>,
+) -> Result<(CookieJar, Json<auth_handler::AuthResponse>), AppError> {
+    let client_ip = addr.ip().to_string();
+    if let Err(msg) = state.rate_limiter.check_ip_limit(&client_ip) {
+        tracing::warn!("Rate limit exceeded for IP: {} - {}", client_ip, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    let challenge = payload.credential.challenge().ok_or(AppError::Unauthorized)?;
+    if state.webauthn_challenges.take(&challenge) != Some(Ceremony::Login) {
+        return Err(AppError::Unauthorized);
+    }
+
+    let stored = state
+        .webauthn
+        .find_webauthn_credential(&payload.credential.id)
+        .await?
+        .ok_or_else(|| {
+            tracing::warn!("Passkey login with unknown credential from IP: {}", client_ip);
+            AppError::Unauthorized
+        })?;
+    check_assertion(&state, &stored, &payload.credential, &challenge, true).await?;
+
+    let user = state.store.find_user_by_id(stored.user_id).await?
+        .ok_or(AppError::Unauthorized)?;
+
+    if state.config.email_verification.required && user.email_verified_at.is_none() {
+        tracing::warn!("Passkey login with unverified email: {} from IP: {}", user.email, client_ip);
+        return Err(AppError::Conflict {
+            code: "email_not_verified",
+            message: "Verify your email address before logging in".to_string(),
+        });
+    }
+    tracing::info!("Successful passkey login for email: {} from IP: {}", user.email, client_ip);
+
+    auth_handler::start_session(&state, jar, user, None).await
+}
+
+/// Starts a passkey check as the second factor of a password login. The
+/// assertion is then sent to `/api/auth/mfa/verify` as `webauthn`.
+pub async fn second_factor_options(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    Json(payload): Json<SecondFactorOptionsRequest>,
+) -> Result<Json<RequestOptions>, AppError> {
+    let mfa_token_hash = token::hash_token(&payload.mfa_token);
+    let pending = state.mfa_challenges.get(&mfa_token_hash).ok_or(AppError::Unauthorized)?;
+
+    let credentials = state.webauthn.find_webauthn_credentials_by_user(pending.user_id).await?;
+    if credentials.is_empty() {
+        return Err(AppError::BadRequest("No passkeys are registered for this account".to_string()));
+    }
+
+    let challenge = start_ceremony(&state, addr, Ceremony::SecondFactor {
+        user_id: pending.user_id,
+        mfa_token_hash,
+    })?;
+
+    Ok(Json(webauthn::request_options(
+        &state.config.webauthn,
+        challenge,
+        credentials.into_iter().map(|c| c.credential_id).collect(),
+        "discouraged",
+        timeout_ms(&state),
+    )))
+}
+
+/// Checks a passkey assertion answering a challenge from
+/// `second_factor_options` for the same `mfa_pending` token.
+pub(crate) async fn verify_second_factor(
+    state: &AppState,
+    mfa_token_hash: &str,
+    user_id: Uuid,
+    credential: &AuthenticationCredential,
+) -> Result<bool, AppError> {
+    let Some(challenge) = credential.challenge() else {
+        return Ok(false);
+    };
+
+    let expected = Ceremony::SecondFactor {
+        user_id,
+        mfa_token_hash: mfa_token_hash.to_string(),
+    };
+    if state.webauthn_challenges.take(&challenge) != Some(expected) {
+        return Ok(false);
+    }
+
+    let Some(stored) = state
+        .webauthn
+        .find_webauthn_credential(&credential.id)
+        .await?
+        .filter(|c| c.user_id == user_id)
+    else {
+        return Ok(false);
+    };
+
+    match check_assertion(state, &stored, credential, &challenge, false).await {
+        Ok(()) => Ok(true),
+        Err(AppError::Unauthorized) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+async fn check_assertion(
+    state: &AppState,
+    stored: &WebAuthnCredential,
+    credential: &AuthenticationCredential,
+    challenge: &str,
+    require_user_verification: bool,
+) -> Result<(), AppError> {
+    let verified = webauthn::verify_assertion(
+        &state.config.webauthn,
+        credential,
+        challenge,
+        &stored.public_key,
+        u32::try_from(stored.sign_count).unwrap_or(u32::MAX),
+        stored.user_id.as_bytes(),
+        require_user_verification,
+    )?;
+
+    state.webauthn.update_webauthn_sign_count(stored.id, verified.sign_count.into()).await
+}
+
+/// Issues a challenge for `ceremony`, unless the client is over its rate
+/// limit or too many are already pending.
+fn start_ceremony(state: &AppState, addr: SocketAddr, ceremony: Ceremony) -> Result<String, AppError> {
+    let client_ip = addr.ip().to_string();
+    if let Err(msg) = state.rate_limiter.check_ip_limit(&client_ip) {
+        tracing::warn!("Rate limit exceeded for IP: {} - {}", client_ip, msg);
+        return Err(AppError::TooManyRequests(msg));
+    }
+
+    let challenge = token::generate_opaque_token();
+    if !state.webauthn_challenges.insert(challenge.clone(), ceremony) {
+        tracing::warn!("Refused passkey ceremony, too many pending");
+        return Err(AppError::TooManyRequests("Too many pending passkey requests. Try again later".to_string()));
+    }
+
+    Ok(challenge)
+}
+
+fn timeout_ms(state: &AppState) -> u64 {
+    state.webauthn_challenges.ttl().as_millis() as u64
+}
@@ -1,3 +1,4 @@
+pub mod cli;
 pub mod config;
 pub mod error;
 pub mod server;
@@ -6,5 +7,7 @@ pub mod handlers;
 pub mod models;
 pub mod db;
 pub mod middleware;
+pub mod mail;
+pub mod oauth;
 pub mod utils;
 pub mod schema;
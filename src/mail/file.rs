@@ -0,0 +1,37 @@
+use async_trait::async_trait;
+use lettre::{AsyncFileTransport, AsyncTransport, Tokio1Executor};
+use lettre::message::Mailbox;
+use crate::error::AppError;
+use super::{Email, Mailer};
+
+/// Drops each message into a directory as an `.eml` file, for development
+/// without a mail server.
+pub struct FileMailer {
+    transport: AsyncFileTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl FileMailer {
+    pub fn new(dir: &str, from: Mailbox) -> Result<Self, AppError> {
+        std::fs::create_dir_all(dir)?;
+
+        Ok(Self {
+            transport: AsyncFileTransport::new(dir),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let id = self
+            .transport
+            .send(email.to_message(&self.from)?)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to write email: {}", e)))?;
+        tracing::debug!("Wrote email to {} as {}.eml", email.to, id);
+
+        Ok(())
+    }
+}
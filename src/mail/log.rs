@@ -0,0 +1,15 @@
+use async_trait::async_trait;
+use crate::error::AppError;
+use super::{Email, Mailer};
+
+/// Logs the plain-text body instead of sending anything. Anyone who can
+/// read the logs can use the links in it, so never use it in production.
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        tracing::info!("Email to {} ({}):\n{}", email.to, email.subject, email.text);
+        Ok(())
+    }
+}
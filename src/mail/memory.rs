@@ -0,0 +1,28 @@
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use crate::error::AppError;
+use super::{Email, Mailer};
+
+/// Keeps every message in memory so tests can inspect what was sent.
+#[derive(Clone, Default)]
+pub struct MemoryMailer {
+    sent: Arc<Mutex<Vec<Email>>>,
+}
+
+impl MemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().expect("mailer lock poisoned").clone()
+    }
+}
+
+#[async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        self.sent.lock().expect("mailer lock poisoned").push(email);
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+pub mod file;
+pub mod log;
+pub mod memory;
+pub mod smtp;
+
+use std::path::Path;
+use std::sync::Arc;
+use async_trait::async_trait;
+use lettre::message::{Mailbox, Message, MultiPart};
+use minijinja::{Environment, path_loader};
+use serde::Serialize;
+use crate::config::{MailConfig, MailTransport};
+use crate::error::AppError;
+
+pub use file::FileMailer;
+pub use log::LogMailer;
+pub use memory::MemoryMailer;
+pub use smtp::SmtpMailer;
+
+/// A rendered message, ready for any transport.
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+impl Email {
+    /// Builds the MIME message, with the text and HTML bodies as alternatives.
+    pub(crate) fn to_message(&self, from: &Mailbox) -> Result<Message, AppError> {
+        let to = self
+            .to
+            .parse::<Mailbox>()
+            .map_err(|e| AppError::BadRequest(format!("Invalid email address {}: {}", self.to, e)))?;
+
+        Message::builder()
+            .from(from.clone())
+            .to(to)
+            .subject(&self.subject)
+            .multipart(MultiPart::alternative_plain_html(self.text.clone(), self.html.clone()))
+            .map_err(|e| AppError::Internal(format!("Failed to build email: {}", e)))
+    }
+}
+
+/// Delivers rendered messages. Which transport is used comes from
+/// `AppConfig::mail`.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), AppError>;
+}
+
+pub fn from_config(config: &MailConfig) -> Result<Arc<dyn Mailer>, AppError> {
+    let from = config
+        .from
+        .parse::<Mailbox>()
+        .map_err(|e| AppError::Internal(format!("Invalid MAIL_FROM: {}", e)))?;
+
+    let mailer: Arc<dyn Mailer> = match &config.transport {
+        MailTransport::Smtp(smtp) => {
+            tracing::info!("Sending mail through SMTP relay {}:{}", smtp.host, smtp.port);
+            Arc::new(SmtpMailer::new(smtp, from)?)
+        }
+        MailTransport::File { dir } => {
+            tracing::info!("Writing mail to directory: {}", dir);
+            Arc::new(FileMailer::new(dir, from)?)
+        }
+        MailTransport::Log => {
+            tracing::warn!("Mail is logged instead of sent; set MAIL_TRANSPORT for production");
+            Arc::new(LogMailer)
+        }
+    };
+
+    Ok(mailer)
+}
+
+/// Message templates: `<name>.txt` for the plain-text body and `<name>.html`
+/// for the HTML one, loaded from `MailConfig::templates_dir`. HTML templates
+/// are auto-escaped and every template can use `app_url`.
+#[derive(Clone)]
+pub struct MailTemplates {
+    env: Arc<Environment<'static>>,
+}
+
+impl MailTemplates {
+    pub fn new(config: &MailConfig) -> Result<Self, AppError> {
+        if !Path::new(&config.templates_dir).is_dir() {
+            return Err(AppError::Internal(format!(
+                "Email templates directory not found: {}",
+                config.templates_dir
+            )));
+        }
+
+        let mut env = Environment::new();
+        env.set_loader(path_loader(&config.templates_dir));
+        env.add_global("app_url", config.app_url.clone());
+
+        Ok(Self { env: Arc::new(env) })
+    }
+
+    pub fn render<S: Serialize>(
+        &self,
+        name: &str,
+        to: &str,
+        subject: impl Into<String>,
+        context: S,
+    ) -> Result<Email, AppError> {
+        let render = |file: String| {
+            self.env
+                .get_template(&file)
+                .and_then(|template| template.render(&context))
+                .map_err(|e| AppError::Internal(format!("Failed to render email template {}: {}", file, e)))
+        };
+
+        Ok(Email {
+            to: to.to_string(),
+            subject: subject.into(),
+            text: render(format!("{}.txt", name))?,
+            html: render(format!("{}.html", name))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn templates() -> MailTemplates {
+        MailTemplates::new(&MailConfig {
+            transport: MailTransport::Log,
+            from: "AuthSession <no-reply@example.com>".into(),
+            templates_dir: "src/templates/email".into(),
+            app_url: "https://app.example.com".into(),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn renders_every_template_and_sends_it() {
+        let templates = templates();
+        let mailer = MemoryMailer::new();
+
+        // The contexts the handlers render them with
+        let messages = [
+            ("account_link", json!({ "provider": "github", "code": "c0de" }), "c0de"),
+            ("password_reset", json!({ "token": "reset-token", "expires_in_minutes": 60 }), "?token=reset-token"),
+            (
+                "magic_link",
+                json!({ "token": "magic-token", "expires_in_minutes": 15, "new_account": true }),
+                "?magic_token=magic-token",
+            ),
+            (
+                "verify_email",
+                json!({ "name": "Alice", "token": "verify-token", "expires_in_hours": 24 }),
+                "?verify_token=verify-token",
+            ),
+        ];
+        for (name, context, expected) in &messages {
+            let email = templates.render(name, "alice@example.com", *name, context).unwrap();
+            assert!(email.text.contains(expected), "{} text: {}", name, email.text);
+            assert!(email.html.contains(expected), "{} html: {}", name, email.html);
+            assert!(email.html.contains("</html>"), "{} does not extend the layout", name);
+            mailer.send(email).await.unwrap();
+        }
+
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), messages.len());
+        assert!(sent.iter().all(|email| email.to == "alice@example.com"));
+        assert!(sent[1].text.contains("https://app.example.com/static/reset-password.html?token=reset-token"));
+    }
+
+    #[test]
+    fn escapes_html_bodies_only() {
+        let name = "<script>alert(\"hi\")</script>";
+        let email = templates()
+            .render(
+                "verify_email",
+                "alice@example.com",
+                "Verify your email address",
+                json!({ "name": name, "token": "t", "expires_in_hours": 24 }),
+            )
+            .unwrap();
+
+        assert!(!email.html.contains("<script>"), "{}", email.html);
+        assert!(email.html.contains("&lt;script&gt;"), "{}", email.html);
+        assert!(email.text.contains(name), "{}", email.text);
+    }
+
+    #[test]
+    fn builds_a_multipart_message() {
+        let email = Email {
+            to: "alice@example.com".into(),
+            subject: "Hello".into(),
+            text: "plain".into(),
+            html: "<p>rich</p>".into(),
+        };
+        let from = "AuthSession <no-reply@example.com>".parse().unwrap();
+        let formatted = String::from_utf8(email.to_message(&from).unwrap().formatted()).unwrap();
+
+        assert!(formatted.contains("multipart/alternative"));
+        assert!(formatted.contains("To: alice@example.com"));
+
+        let invalid = Email { to: "not an address".into(), ..email };
+        assert!(matches!(invalid.to_message(&from), Err(AppError::BadRequest(_))));
+    }
+}
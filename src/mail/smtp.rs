@@ -0,0 +1,49 @@
+use async_trait::async_trait;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use crate::config::{SmtpConfig, SmtpSecurity};
+use crate::error::AppError;
+use super::{Email, Mailer};
+
+/// Sends through an SMTP relay, reusing pooled connections.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &SmtpConfig, from: Mailbox) -> Result<Self, AppError> {
+        let builder = match config.security {
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host),
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host),
+            SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)),
+        }
+        .map_err(|e| AppError::Internal(format!("Invalid SMTP relay {}: {}", config.host, e)))?
+        .port(config.port);
+
+        let builder = match (&config.username, &config.password) {
+            (Some(username), Some(password)) => {
+                builder.credentials(Credentials::new(username.clone(), password.clone()))
+            }
+            _ => builder,
+        };
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        self.transport
+            .send(email.to_message(&self.from)?)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to send email: {}", e)))?;
+
+        Ok(())
+    }
+}
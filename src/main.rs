@@ -1,4 +1,4 @@
-use auth_session::{config::AppConfig, server};
+use auth_session::{cli, config::AppConfig, server};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
@@ -14,6 +14,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .init();
 
     let config = AppConfig::new()?;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("keys") => return Ok(cli::keys(config, &args[1..]).await?),
+        Some("roles") => return Ok(cli::roles(config, &args[1..]).await?),
+        Some("clients") => return Ok(cli::clients(config, &args[1..]).await?),
+        _ => {}
+    }
     
     tracing::debug!("Starting auth_session server...");
     
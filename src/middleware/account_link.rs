@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// An OAuth login whose verified email matches an existing account. The
+/// identity is only linked once the account owner confirms it.
+#[derive(Debug, Clone)]
+pub struct PendingLink {
+    // Existing account the identity would be linked to
+    pub user_id: Uuid,
+    pub email: String,
+    pub provider: String,
+    pub provider_user_id: String,
+    // Hash of the confirmation code emailed to the account owner, once requested
+    pub email_code_hash: Option<String>,
+    pub created_at: Instant,
+}
+
+#[derive(Clone)]
+pub struct AccountLinkStore {
+    // Track pending links by the hash of their link token
+    pending: Arc<DashMap<String, PendingLink>>,
+    ttl: Duration,
+}
+
+impl AccountLinkStore {
+    pub fn new(ttl_seconds: u64) -> Self {
+        Self {
+            pending: Arc::new(DashMap::new()),
+            ttl: Duration::from_secs(ttl_seconds),
+        }
+    }
+
+    pub fn insert(&self, token_hash: String, pending: PendingLink) {
+        self.pending.insert(token_hash, pending);
+    }
+
+    pub fn get(&self, token_hash: &str) -> Option<PendingLink> {
+        let pending = self.pending.get(token_hash)?;
+
+        if pending.created_at.elapsed() >= self.ttl {
+            return None;
+        }
+
+        Some(pending.clone())
+    }
+
+    /// Records the code sent by email, replacing any earlier one.
+    pub fn set_email_code(&self, token_hash: &str, code_hash: String) -> bool {
+        match self.pending.get_mut(token_hash) {
+            Some(mut pending) => {
+                pending.email_code_hash = Some(code_hash);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the pending link so a confirmation can't be replayed.
+    pub fn take(&self, token_hash: &str) -> Option<PendingLink> {
+        let (_, pending) = self.pending.remove(token_hash)?;
+
+        if pending.created_at.elapsed() >= self.ttl {
+            return None;
+        }
+
+        Some(pending)
+    }
+
+    // Cleanup abandoned links periodically
+    pub fn cleanup(&self) {
+        let now = Instant::now();
+        self.pending.retain(|_, pending| now.duration_since(pending.created_at) < self.ttl);
+    }
+}
@@ -1,35 +1,128 @@
 use axum::{
-    extract::{Request, State},
-    http::{header, StatusCode},
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap, Method},
     middleware::Next,
     response::Response,
 };
+use axum_extra::extract::cookie::CookieJar;
+use uuid::Uuid;
+use crate::error::AppError;
 use crate::handlers::auth_handler::AppState;
-use crate::utils::jwt;
+use crate::middleware::csrf;
+use crate::models::user::User;
+use crate::utils::{jwt::{self, Claims}, session};
 
+/// The caller of a protected handler, authenticated by a bearer access
+/// token. Taking it as an argument is all a handler needs to require login;
+/// missing, invalid or revoked tokens are rejected with 401.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub user_id: Uuid,
+    pub claims: Claims,
+}
+
+impl AuthUser {
+    /// Loads the account behind the token. A user deleted since the token
+    /// was issued is treated as unauthenticated.
+    pub async fn load(&self, state: &AppState) -> Result<User, AppError> {
+        state.store.find_user_by_id(self.user_id).await?
+            .ok_or(AppError::Unauthorized)
+    }
+}
+
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        // `identify` has normally verified the token already
+        let claims = match parts.extensions.get::<Claims>() {
+            Some(claims) => claims.clone(),
+            None => verify_request(&parts.method, &parts.headers, state)?,
+        };
+
+        let user_id = Uuid::parse_str(&claims.sub)
+            .map_err(|_| AppError::Unauthorized)?;
+
+        Ok(Self { user_id, claims })
+    }
+}
+
+/// Like `AuthUser`, but also accepts access tokens issued to OAuth clients.
+/// Only endpoints meant for clients, such as `/userinfo`, should take it.
+#[derive(Debug, Clone)]
+pub struct ClientAuthUser(pub AuthUser);
+
+impl FromRequestParts<AppState> for ClientAuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let token = bearer_token(&parts.headers).ok_or(AppError::Unauthorized)?;
+
+        let claims = jwt::verify_client_token(token, &state.jwt_keys)?;
+        if state.revocations.is_revoked(&claims) {
+            return Err(AppError::Unauthorized);
+        }
+
+        let user_id = Uuid::parse_str(&claims.sub)
+            .map_err(|_| AppError::Unauthorized)?;
+
+        Ok(Self(AuthUser { user_id, claims }))
+    }
+}
+
+/// Rejects every request without a valid access token, for mounting on a
+/// whole group of routes.
 pub async fn auth(
+    user: AuthUser,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    req.extensions_mut().insert(user.claims);
+
+    next.run(req).await
+}
+
+/// Attaches the claims of a valid bearer token, if one is sent, without
+/// rejecting anything. `RequireRole`/`RequirePermission` rely on it.
+pub async fn identify(
     State(state): State<AppState>,
     mut req: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    let auth_header = req
-        .headers()
-        .get(header::AUTHORIZATION)
-        .and_then(|header| header.to_str().ok());
+) -> Response {
+    if let Ok(claims) = verify_request(req.method(), req.headers(), &state) {
+        req.extensions_mut().insert(claims);
+    }
 
-    let auth_header = match auth_header {
-        Some(header) => header,
-        None => return Err(StatusCode::UNAUTHORIZED),
-    };
+    next.run(req).await
+}
 
-    let token = auth_header
-        .strip_prefix("Bearer ")
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+}
 
-    let claims = jwt::verify_token(token, &state.config.jwt.secret)
-        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+/// Verifies the bearer token, or in session mode the session cookie. The
+/// browser sends cookies on its own, so writes relying on them must also
+/// pass the CSRF check.
+fn verify_request(method: &Method, headers: &HeaderMap, state: &AppState) -> Result<Claims, AppError> {
+    let token = match bearer_token(headers) {
+        Some(token) => token.to_string(),
+        None => {
+            let token = session::access_token(&state.config.session, &CookieJar::from_headers(headers))
+                .ok_or(AppError::Unauthorized)?;
+            if !method.is_safe() {
+                csrf::verify(&state.config.session, headers)?;
+            }
+            token
+        }
+    };
 
-    req.extensions_mut().insert(claims);
+    let claims = jwt::verify_token(&token, &state.jwt_keys)?;
+    if state.revocations.is_revoked(&claims) {
+        return Err(AppError::Unauthorized);
+    }
 
-    Ok(next.run(req).await)
+    Ok(claims)
 }
@@ -0,0 +1,90 @@
+use axum::{
+    extract::Request,
+    response::{IntoResponse, Response},
+};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use crate::error::AppError;
+use crate::utils::jwt::Claims;
+
+/// Route layer admitting only users holding the role, e.g.
+/// `get(handler).route_layer(RequireRole("admin"))`.
+#[derive(Debug, Clone, Copy)]
+pub struct RequireRole(pub &'static str);
+
+/// Route layer admitting only users granted the permission, e.g.
+/// `get(handler).route_layer(RequirePermission("users:read"))`.
+#[derive(Debug, Clone, Copy)]
+pub struct RequirePermission(pub &'static str);
+
+#[derive(Debug, Clone, Copy)]
+enum Requirement {
+    Role(&'static str),
+    Permission(&'static str),
+}
+
+impl Requirement {
+    /// 401 without valid credentials, 403 when they don't carry the right.
+    fn check(&self, claims: Option<&Claims>) -> Result<(), AppError> {
+        let claims = claims.ok_or(AppError::Unauthorized)?;
+
+        let allowed = match self {
+            Requirement::Role(role) => claims.has_role(role),
+            Requirement::Permission(permission) => claims.has_permission(permission),
+        };
+
+        if !allowed {
+            tracing::warn!("User {} denied: {:?} required", claims.sub, self);
+            return Err(AppError::Forbidden);
+        }
+
+        Ok(())
+    }
+}
+
+impl<S> Layer<S> for RequireRole {
+    type Service = Authorize<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Authorize { inner, requirement: Requirement::Role(self.0) }
+    }
+}
+
+impl<S> Layer<S> for RequirePermission {
+    type Service = Authorize<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Authorize { inner, requirement: Requirement::Permission(self.0) }
+    }
+}
+
+/// Checks the claims attached by `auth_middleware::identify` before
+/// passing the request on.
+#[derive(Debug, Clone)]
+pub struct Authorize<S> {
+    inner: S,
+    requirement: Requirement,
+}
+
+impl<S> Service<Request> for Authorize<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        match self.requirement.check(req.extensions().get::<Claims>()) {
+            Ok(()) => Box::pin(self.inner.call(req)),
+            Err(e) => Box::pin(async move { Ok(e.into_response()) }),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// An authorization code issued by `/oauth/authorize`, waiting to be
+/// exchanged at `/oauth/token`.
+#[derive(Debug, Clone)]
+pub struct PendingCode {
+    pub client_id: String,
+    pub user_id: Uuid,
+    // Must be repeated verbatim in the token request
+    pub redirect_uri: String,
+    // PKCE S256 challenge the code verifier must hash to
+    pub code_challenge: String,
+    pub scope: Option<String>,
+    // OpenID Connect nonce to put in the ID token
+    pub nonce: Option<String>,
+    pub created_at: Instant,
+}
+
+#[derive(Clone)]
+pub struct AuthorizationCodeStore {
+    // Track issued codes by their hash
+    codes: Arc<DashMap<String, PendingCode>>,
+    ttl: Duration,
+}
+
+impl AuthorizationCodeStore {
+    pub fn new(ttl_seconds: u64) -> Self {
+        Self {
+            codes: Arc::new(DashMap::new()),
+            ttl: Duration::from_secs(ttl_seconds),
+        }
+    }
+
+    pub fn insert(&self, code_hash: String, pending: PendingCode) {
+        self.codes.insert(code_hash, pending);
+    }
+
+    /// Removes and returns the code so it can only be exchanged once.
+    pub fn take(&self, code_hash: &str) -> Option<PendingCode> {
+        let (_, pending) = self.codes.remove(code_hash)?;
+
+        if pending.created_at.elapsed() >= self.ttl {
+            return None;
+        }
+
+        Some(pending)
+    }
+
+    // Cleanup codes that were never exchanged
+    pub fn cleanup(&self) {
+        let now = Instant::now();
+        self.codes.retain(|_, pending| now.duration_since(pending.created_at) < self.ttl);
+    }
+}
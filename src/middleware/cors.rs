@@ -0,0 +1,115 @@
+use axum::http::{HeaderValue, request::Parts};
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use crate::config::CorsConfig;
+
+/// Builds the CORS layer from `CorsConfig`. Wildcard subdomain patterns such
+/// as `https://*.example.com` are matched per request, and the matching
+/// origin is echoed back rather than `*`, so they work with credentials.
+pub fn cors_layer(config: &CorsConfig) -> CorsLayer {
+    let allow_origin = if config.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        let patterns = config.allowed_origins.clone();
+        AllowOrigin::predicate(move |origin: &HeaderValue, _: &Parts| {
+            origin
+                .to_str()
+                .is_ok_and(|origin| patterns.iter().any(|pattern| origin_matches(pattern, origin)))
+        })
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(config.allowed_methods.clone())
+        .allow_headers(config.allowed_headers.clone())
+        .allow_credentials(config.allow_credentials)
+        .max_age(Duration::from_secs(config.max_age))
+}
+
+fn origin_matches(pattern: &str, origin: &str) -> bool {
+    let Some((scheme, domain)) = pattern.split_once("*.") else {
+        return pattern.eq_ignore_ascii_case(origin);
+    };
+
+    // `https://*.example.com` covers `https://a.b.example.com` but neither
+    // `https://example.com` nor `https://evil.com/.example.com`
+    let origin = origin.to_ascii_lowercase();
+    let Some(host) = origin
+        .strip_prefix(&scheme.to_ascii_lowercase())
+        .and_then(|rest| rest.strip_suffix(&format!(".{}", domain.to_ascii_lowercase())))
+    else {
+        return false;
+    };
+
+    !host.is_empty()
+        && host
+            .split('.')
+            .all(|label| !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Body, http::{Request, header}, routing::get};
+    use tower::ServiceExt;
+
+    fn list(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    fn config(origins: &[&str], allow_credentials: bool) -> anyhow::Result<CorsConfig> {
+        CorsConfig::from_lists(list(origins), list(&["GET", "POST"]), list(&["content-type"]), allow_credentials, 600)
+    }
+
+    #[test]
+    fn wildcard_patterns_match_subdomains_only() {
+        let pattern = "https://*.example.com";
+
+        assert!(origin_matches(pattern, "https://app.example.com"));
+        assert!(origin_matches(pattern, "https://a.b.example.com"));
+        assert!(origin_matches(pattern, "HTTPS://App.Example.com"));
+
+        assert!(!origin_matches(pattern, "https://example.com"));
+        assert!(!origin_matches(pattern, "http://app.example.com"));
+        assert!(!origin_matches(pattern, "https://app.example.com.evil.com"));
+        assert!(!origin_matches(pattern, "https://evil.com/.example.com"));
+        assert!(!origin_matches(pattern, "https://evil.com:443.example.com"));
+        assert!(!origin_matches(pattern, "https://..example.com"));
+    }
+
+    #[test]
+    fn exact_origins_ignore_case_only() {
+        assert!(origin_matches("https://app.example.com", "https://APP.example.com"));
+        assert!(!origin_matches("https://app.example.com", "https://app.example.com:8443"));
+    }
+
+    #[test]
+    fn credentials_cannot_be_combined_with_wildcards() {
+        assert!(config(&["*"], false).is_ok());
+        assert!(config(&["*"], true).is_err());
+        assert!(config(&["https://*.example.com"], true).is_ok());
+
+        let methods = CorsConfig::from_lists(list(&["https://app.example.com"]), list(&["*"]), vec![], true, 600);
+        assert!(methods.is_err());
+        let headers = CorsConfig::from_lists(list(&["https://app.example.com"]), vec![], list(&["*"]), true, 600);
+        assert!(headers.is_err());
+    }
+
+    #[tokio::test]
+    async fn echoes_the_matching_origin() {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(cors_layer(&config(&["https://*.example.com"], true).unwrap()));
+        let allowed_origin = |origin: &'static str| {
+            let app = app.clone();
+            async move {
+                let request = Request::builder().uri("/").header(header::ORIGIN, origin).body(Body::empty()).unwrap();
+                let response = app.oneshot(request).await.unwrap();
+                response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).cloned()
+            }
+        };
+
+        assert_eq!(allowed_origin("https://app.example.com").await.unwrap(), "https://app.example.com");
+        assert_eq!(allowed_origin("https://example.com").await, None);
+    }
+}
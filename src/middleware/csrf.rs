@@ -0,0 +1,30 @@
+use axum::http::HeaderMap;
+use axum_extra::extract::cookie::CookieJar;
+use crate::config::SessionConfig;
+use crate::error::AppError;
+use crate::utils::session;
+
+/// Double-submit CSRF check for requests authenticated by session cookies:
+/// a state-changing request must repeat the `<name>_csrf` cookie in the
+/// `X-CSRF-Token` header, which another site can neither read nor set.
+/// Only code that actually authenticates with the cookies calls it, so
+/// logins and other forms work whatever cookies the browser still holds.
+pub fn verify(config: &SessionConfig, headers: &HeaderMap) -> Result<(), AppError> {
+    let expected = session::csrf_token(config, &CookieJar::from_headers(headers));
+    let submitted = headers
+        .get(session::CSRF_HEADER)
+        .and_then(|h| h.to_str().ok());
+
+    match (expected.as_deref(), submitted) {
+        (Some(expected), Some(submitted)) if !expected.is_empty() && constant_time_eq(expected, submitted) => Ok(()),
+        _ => {
+            tracing::warn!("Rejected a cookie-authenticated request without a valid CSRF token");
+            Err(AppError::Forbidden)
+        }
+    }
+}
+
+// Takes the same time wherever the inputs first differ
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
@@ -0,0 +1,120 @@
+use std::sync::Arc;
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+// Wrong codes allowed per challenge before the user must log in again
+const MAX_ATTEMPTS: u32 = 5;
+
+/// A login whose password was correct but which still needs the second
+/// factor before any token is issued.
+#[derive(Debug, Clone)]
+pub struct PendingMfa {
+    pub user_id: Uuid,
+    pub email: String,
+    pub failed_attempts: u32,
+    pub created_at: Instant,
+}
+
+#[derive(Clone)]
+pub struct MfaChallengeStore {
+    // Track pending logins by the hash of their mfa_pending token
+    pending: Arc<DashMap<String, PendingMfa>>,
+    ttl: Duration,
+}
+
+impl MfaChallengeStore {
+    pub fn new(ttl_seconds: u64) -> Self {
+        Self {
+            pending: Arc::new(DashMap::new()),
+            ttl: Duration::from_secs(ttl_seconds),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn insert(&self, token_hash: String, pending: PendingMfa) {
+        self.pending.insert(token_hash, pending);
+    }
+
+    pub fn get(&self, token_hash: &str) -> Option<PendingMfa> {
+        let pending = self.pending.get(token_hash)?;
+
+        if pending.created_at.elapsed() >= self.ttl {
+            return None;
+        }
+
+        Some(pending.clone())
+    }
+
+    /// Counts a wrong code, dropping the challenge once too many were tried.
+    pub fn record_failure(&self, token_hash: &str) {
+        let exhausted = match self.pending.get_mut(token_hash) {
+            Some(mut pending) => {
+                pending.failed_attempts += 1;
+                pending.failed_attempts >= MAX_ATTEMPTS
+            }
+            None => false,
+        };
+
+        if exhausted {
+            self.pending.remove(token_hash);
+        }
+    }
+
+    /// Removes the challenge so it can't be exchanged twice.
+    pub fn take(&self, token_hash: &str) -> Option<PendingMfa> {
+        let (_, pending) = self.pending.remove(token_hash)?;
+
+        if pending.created_at.elapsed() >= self.ttl {
+            return None;
+        }
+
+        Some(pending)
+    }
+
+    // Cleanup abandoned challenges periodically
+    pub fn cleanup(&self) {
+        let now = Instant::now();
+        self.pending.retain(|_, pending| now.duration_since(pending.created_at) < self.ttl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending() -> PendingMfa {
+        PendingMfa {
+            user_id: Uuid::new_v4(),
+            email: "alice@example.com".to_string(),
+            failed_attempts: 0,
+            created_at: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let store = MfaChallengeStore::new(300);
+        store.insert("hash".to_string(), pending());
+
+        for _ in 1..MAX_ATTEMPTS {
+            store.record_failure("hash");
+            assert!(store.get("hash").is_some());
+        }
+        store.record_failure("hash");
+        assert!(store.get("hash").is_none());
+        assert!(store.take("hash").is_none());
+    }
+
+    #[test]
+    fn a_challenge_is_taken_once() {
+        let store = MfaChallengeStore::new(300);
+        store.insert("hash".to_string(), pending());
+
+        assert!(store.take("hash").is_some());
+        assert!(store.take("hash").is_none());
+    }
+}
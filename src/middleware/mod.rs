@@ -1,3 +1,12 @@
 pub mod auth_middleware;
+pub mod authorization;
+pub mod cors;
+pub mod csrf;
 pub mod timing;
 pub mod rate_limit;
+pub mod revocation;
+pub mod oauth_state;
+pub mod account_link;
+pub mod mfa_challenge;
+pub mod webauthn_challenge;
+pub mod authorization_code;
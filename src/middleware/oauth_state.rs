@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Server-side record of an authorization request that has been sent to a
+/// provider but not yet completed.
+#[derive(Debug, Clone)]
+pub struct PendingAuthorization {
+    // Provider the authorization was started for
+    pub provider: String,
+    // Hash of the random value stored in the browser's binding cookie
+    pub binding_hash: String,
+    // PKCE code verifier, sent back to the provider when exchanging the code
+    pub pkce_verifier: Option<String>,
+    // OpenID Connect nonce expected back in the id_token
+    pub nonce: Option<String>,
+    // Set when an authenticated user is linking this provider to their account
+    pub link_user_id: Option<Uuid>,
+    pub created_at: Instant,
+}
+
+#[derive(Clone)]
+pub struct OAuthStateStore {
+    // Track pending authorizations by their `state` parameter
+    pending: Arc<DashMap<String, PendingAuthorization>>,
+    ttl: Duration,
+    // Anyone can start an authorization, so the map must not grow unbounded
+    max_pending: usize,
+}
+
+impl OAuthStateStore {
+    pub fn new(ttl_seconds: u64, max_pending: usize) -> Self {
+        Self {
+            pending: Arc::new(DashMap::new()),
+            ttl: Duration::from_secs(ttl_seconds),
+            max_pending,
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns false, storing nothing, when `max_pending` unexpired
+    /// authorizations are already waiting.
+    pub fn insert(&self, state: String, pending: PendingAuthorization) -> bool {
+        if self.pending.len() >= self.max_pending {
+            self.cleanup();
+            if self.pending.len() >= self.max_pending {
+                return false;
+            }
+        }
+
+        self.pending.insert(state, pending);
+        true
+    }
+
+    /// Removes and returns the pending authorization for `state`. Each state
+    /// can be taken once, so a replayed callback finds nothing.
+    pub fn take(&self, state: &str) -> Option<PendingAuthorization> {
+        let (_, pending) = self.pending.remove(state)?;
+
+        if pending.created_at.elapsed() >= self.ttl {
+            return None;
+        }
+
+        Some(pending)
+    }
+
+    // Cleanup abandoned authorizations periodically
+    pub fn cleanup(&self) {
+        let now = Instant::now();
+        self.pending.retain(|_, pending| now.duration_since(pending.created_at) < self.ttl);
+    }
+}
@@ -8,6 +8,10 @@ pub struct RateLimiter {
     ip_attempts: Arc<DashMap<String, Vec<Instant>>>,
     // Track login attempts per email
     email_attempts: Arc<DashMap<String, Vec<Instant>>>,
+    // Track emails sent on request per address (login links, password
+    // resets, verification), apart from login attempts so requesting them
+    // can't lock the owner out of their password
+    mail_requests: Arc<DashMap<String, Vec<Instant>>>,
     
     // Configuration
     max_attempts_per_ip: usize,
@@ -24,6 +28,7 @@ impl RateLimiter {
         Self {
             ip_attempts: Arc::new(DashMap::new()),
             email_attempts: Arc::new(DashMap::new()),
+            mail_requests: Arc::new(DashMap::new()),
             max_attempts_per_ip,
             max_attempts_per_email,
             window_duration: Duration::from_secs(window_seconds),
@@ -33,7 +38,7 @@ impl RateLimiter {
     pub fn check_ip_limit(&self, ip: &str) -> Result<(), String> {
         let now = Instant::now();
         
-        let mut entry = self.ip_attempts.entry(ip.to_string()).or_insert_with(Vec::new);
+        let mut entry = self.ip_attempts.entry(ip.to_string()).or_default();
         
         // Remove old attempts outside the window
         entry.retain(|&time| now.duration_since(time) < self.window_duration);
@@ -54,7 +59,7 @@ impl RateLimiter {
     pub fn check_email_limit(&self, email: &str) -> Result<(), String> {
         let now = Instant::now();
         
-        let mut entry = self.email_attempts.entry(email.to_string()).or_insert_with(Vec::new);
+        let mut entry = self.email_attempts.entry(email.to_string()).or_default();
         
         // Remove old attempts outside the window
         entry.retain(|&time| now.duration_since(time) < self.window_duration);
@@ -72,6 +77,28 @@ impl RateLimiter {
         Ok(())
     }
 
+    /// Limits emails sent to one address on request to the per-email allowance.
+    pub fn check_mail_request_limit(&self, email: &str) -> Result<(), String> {
+        let now = Instant::now();
+
+        let mut entry = self.mail_requests.entry(email.to_string()).or_default();
+
+        // Remove old requests outside the window
+        entry.retain(|&time| now.duration_since(time) < self.window_duration);
+
+        if entry.len() >= self.max_attempts_per_email {
+            let oldest = entry.first().unwrap();
+            let wait_time = self.window_duration.saturating_sub(now.duration_since(*oldest));
+            return Err(format!(
+                "Too many emails requested for this address. Try again in {} seconds",
+                wait_time.as_secs()
+            ));
+        }
+
+        entry.push(now);
+        Ok(())
+    }
+
     pub fn reset_email_limit(&self, email: &str) {
         self.email_attempts.remove(email);
     }
@@ -94,6 +121,11 @@ impl RateLimiter {
             attempts.retain(|&time| now.duration_since(time) < self.window_duration);
             !attempts.is_empty()
         });
+
+        self.mail_requests.retain(|_, requests| {
+            requests.retain(|&time| now.duration_since(time) < self.window_duration);
+            !requests.is_empty()
+        });
     }
 }
 
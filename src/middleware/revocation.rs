@@ -0,0 +1,102 @@
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use uuid::Uuid;
+use crate::db::RevocationStore;
+use crate::error::AppError;
+use crate::utils::jwt::Claims;
+
+/// In-process mirror of the revocation tables so every authenticated request
+/// can be checked without a database round trip. Writes go to the store first
+/// and then to the cache; `sync` pulls in revocations made by other instances.
+#[derive(Clone)]
+pub struct RevocationList {
+    store: Arc<dyn RevocationStore>,
+    // Revoked jti -> token expiry (unix seconds)
+    revoked: Arc<DashMap<Uuid, i64>>,
+    // User id -> tokens issued before this instant (unix seconds) are rejected
+    cutoffs: Arc<DashMap<Uuid, i64>>,
+}
+
+impl RevocationList {
+    pub fn new(store: Arc<dyn RevocationStore>) -> Self {
+        Self {
+            store,
+            revoked: Arc::new(DashMap::new()),
+            cutoffs: Arc::new(DashMap::new()),
+        }
+    }
+
+    pub async fn sync(&self) -> Result<(), AppError> {
+        for (jti, expires_at) in self.store.list_revoked_tokens().await? {
+            self.revoked.insert(jti, expires_at.and_utc().timestamp());
+        }
+
+        for (user_id, valid_after) in self.store.list_token_cutoffs().await? {
+            self.cutoffs.insert(user_id, valid_after.and_utc().timestamp());
+        }
+
+        Ok(())
+    }
+
+    pub fn is_revoked(&self, claims: &Claims) -> bool {
+        if Uuid::parse_str(&claims.jti).is_ok_and(|jti| self.revoked.contains_key(&jti)) {
+            return true;
+        }
+
+        match Uuid::parse_str(&claims.sub) {
+            Ok(user_id) => self
+                .cutoffs
+                .get(&user_id)
+                .is_some_and(|cutoff| claims.iat < *cutoff),
+            Err(_) => false,
+        }
+    }
+
+    /// Revokes a single access token until it would have expired anyway.
+    pub async fn revoke(&self, claims: &Claims) -> Result<(), AppError> {
+        let jti = Uuid::parse_str(&claims.jti).map_err(|_| AppError::Unauthorized)?;
+        let user_id = Uuid::parse_str(&claims.sub).map_err(|_| AppError::Unauthorized)?;
+        let expires_at = DateTime::from_timestamp(claims.exp, 0)
+            .ok_or(AppError::Unauthorized)?
+            .naive_utc();
+
+        self.store.revoke_token(jti, user_id, expires_at).await?;
+        self.revoked.insert(jti, claims.exp);
+        Ok(())
+    }
+
+    /// Rejects every token issued to `user_id` before `valid_after`. A cutoff
+    /// never moves backwards, so older timestamps are ignored.
+    pub async fn revoke_all_before(&self, user_id: Uuid, valid_after: DateTime<Utc>) -> Result<(), AppError> {
+        let cutoff = valid_after.timestamp();
+        if self.cutoffs.get(&user_id).is_some_and(|existing| *existing >= cutoff) {
+            return Ok(());
+        }
+
+        self.store.set_tokens_valid_after(user_id, valid_after.naive_utc()).await?;
+        self.cutoffs.insert(user_id, cutoff);
+        Ok(())
+    }
+
+    /// Rejects every token issued to `user_id` so far. Cutoffs have second
+    /// precision, so this one covers the current second too, and then waits
+    /// for it to pass so a session started right after is not rejected.
+    pub async fn revoke_all_issued(&self, user_id: Uuid) -> Result<(), AppError> {
+        let valid_after = Utc::now() + Duration::seconds(1);
+        self.revoke_all_before(user_id, valid_after).await?;
+
+        let cutoff = DateTime::from_timestamp(valid_after.timestamp(), 0).unwrap_or(valid_after);
+        if let Ok(wait) = (cutoff - Utc::now()).to_std() {
+            tokio::time::sleep(wait).await;
+        }
+        Ok(())
+    }
+
+    // Cleanup entries for tokens that have expired on their own
+    pub async fn cleanup(&self) -> Result<(), AppError> {
+        let now = Utc::now().timestamp();
+        self.revoked.retain(|_, expires_at| *expires_at > now);
+        self.store.purge_expired_revocations().await
+    }
+}
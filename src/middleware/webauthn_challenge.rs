@@ -0,0 +1,79 @@
+use std::sync::Arc;
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// What a WebAuthn challenge was issued for. A response is only accepted
+/// by the endpoint finishing the same ceremony.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ceremony {
+    /// Adding a passkey to a signed-in account
+    Registration { user_id: Uuid },
+    /// Passkey login; the credential identifies the user
+    Login,
+    /// Second factor for the login behind an `mfa_pending` token
+    SecondFactor { user_id: Uuid, mfa_token_hash: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct PendingCeremony {
+    pub ceremony: Ceremony,
+    pub created_at: Instant,
+}
+
+#[derive(Clone)]
+pub struct WebAuthnChallengeStore {
+    // Challenges are random and single use, so they key the map directly
+    pending: Arc<DashMap<String, PendingCeremony>>,
+    ttl: Duration,
+    // Anyone can start a passkey login, so the map must not grow unbounded
+    max_pending: usize,
+}
+
+impl WebAuthnChallengeStore {
+    pub fn new(ttl_seconds: u64, max_pending: usize) -> Self {
+        Self {
+            pending: Arc::new(DashMap::new()),
+            ttl: Duration::from_secs(ttl_seconds),
+            max_pending,
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns false, storing nothing, when `max_pending` unexpired
+    /// ceremonies are already waiting.
+    pub fn insert(&self, challenge: String, ceremony: Ceremony) -> bool {
+        if self.pending.len() >= self.max_pending {
+            self.cleanup();
+            if self.pending.len() >= self.max_pending {
+                return false;
+            }
+        }
+
+        self.pending.insert(challenge, PendingCeremony {
+            ceremony,
+            created_at: Instant::now(),
+        });
+        true
+    }
+
+    /// Removes the challenge so a response can't be replayed.
+    pub fn take(&self, challenge: &str) -> Option<Ceremony> {
+        let (_, pending) = self.pending.remove(challenge)?;
+
+        if pending.created_at.elapsed() >= self.ttl {
+            return None;
+        }
+
+        Some(pending.ceremony)
+    }
+
+    // Cleanup abandoned ceremonies periodically
+    pub fn cleanup(&self) {
+        let now = Instant::now();
+        self.pending.retain(|_, pending| now.duration_since(pending.created_at) < self.ttl);
+    }
+}
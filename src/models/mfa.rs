@@ -0,0 +1,33 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+use diesel::prelude::*;
+
+/// A user's TOTP secret. Until `confirmed_at` is set the enrollment is
+/// pending and login does not ask for a code.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::totp_secrets)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TotpSecret {
+    pub user_id: Uuid,
+    pub secret: String,
+    pub confirmed_at: Option<NaiveDateTime>,
+    pub last_used_step: Option<i64>,
+    pub created_at: NaiveDateTime,
+}
+
+impl TotpSecret {
+    pub fn is_enabled(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::recovery_codes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RecoveryCode {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub code_hash: String,
+    pub used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
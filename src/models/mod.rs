@@ -1 +1,9 @@
 pub mod user;
+pub mod user_identity;
+pub mod refresh_token;
+pub mod oauth_client;
+pub mod signing_key;
+pub mod role;
+pub mod mfa;
+pub mod webauthn_credential;
+pub mod password_reset_token;
@@ -0,0 +1,34 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+use diesel::prelude::*;
+
+/// An application allowed to request tokens from `/oauth/authorize`.
+/// Clients without a secret are public and rely on PKCE alone.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::oauth_clients)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OAuthClient {
+    pub id: Uuid,
+    pub client_id: String,
+    pub name: String,
+    pub client_secret_hash: Option<String>,
+    pub redirect_uris: Vec<String>,
+    /// Scopes the client may request, e.g. `openid`
+    pub allowed_scopes: Vec<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl OAuthClient {
+    /// Redirect URIs must match an allowlisted value exactly.
+    pub fn allows_redirect_uri(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.iter().any(|uri| uri == redirect_uri)
+    }
+
+    /// Every scope in a space-separated `scope` parameter must be allowed.
+    pub fn allows_scope(&self, scope: &str) -> bool {
+        scope
+            .split(' ')
+            .filter(|s| !s.is_empty())
+            .all(|s| self.allowed_scopes.iter().any(|allowed| allowed == s))
+    }
+}
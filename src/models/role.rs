@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+/// What a user may do: the names of their roles and of every permission
+/// those roles grant. Embedded in access tokens when they are issued.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserGrants {
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
@@ -0,0 +1,19 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+/// A JWT signing key. Key material stays on disk; a row without paths stands
+/// for the key configured through `JWT_*` environment variables.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::signing_keys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SigningKey {
+    pub kid: String,
+    pub algorithm: String,
+    pub private_key_path: Option<String>,
+    pub public_key_path: Option<String>,
+    /// Signs new tokens; exactly one key is active
+    pub active: bool,
+    /// Tokens signed with this key are rejected from this instant on
+    pub not_after: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
@@ -11,10 +11,10 @@ pub struct User {
     pub email: String,
     pub name: String,
     pub password_hash: Option<String>,
-    pub oauth_provider: Option<String>,
-    pub oauth_id: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub tokens_valid_after: Option<NaiveDateTime>,
+    pub email_verified_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Deserialize)]
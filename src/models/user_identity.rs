@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+use diesel::prelude::*;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::user_identities)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UserIdentity {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub provider: String,
+    pub provider_user_id: String,
+    pub email: Option<String>,
+    pub created_at: NaiveDateTime,
+}
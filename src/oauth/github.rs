@@ -0,0 +1,137 @@
+use async_trait::async_trait;
+use oauth2::{TokenResponse, basic::BasicTokenResponse};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use crate::config::OAuthProviderConfig;
+use crate::error::AppError;
+use crate::oauth::{self, AuthorizationRequest, OAuthProvider, ProviderIdentity};
+
+const AUTH_URL: &str = "https://github.com/login/oauth/authorize";
+const TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+const API_URL: &str = "https://api.github.com";
+
+#[derive(Debug, Deserialize)]
+struct GitHubUser {
+    id: u64,
+    login: String,
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubEmail {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+/// The address GitHub sends mail to, provided the user has verified it.
+fn primary_email(emails: Vec<GitHubEmail>) -> Option<String> {
+    emails.into_iter().find(|e| e.primary && e.verified).map(|e| e.email)
+}
+
+/// GitHub is plain OAuth 2.0: there is no id_token, and `/user` only exposes
+/// the public email, so the verified primary address comes from `/user/emails`.
+pub struct GitHubProvider {
+    config: OAuthProviderConfig,
+    http: reqwest::Client,
+}
+
+impl GitHubProvider {
+    pub fn new(config: OAuthProviderConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    fn client(&self) -> Result<oauth::ProviderClient<BasicTokenResponse>, AppError> {
+        oauth::build_client(
+            &self.config,
+            self.config.auth_url.clone().unwrap_or_else(|| AUTH_URL.to_string()),
+            self.config.token_url.clone().unwrap_or_else(|| TOKEN_URL.to_string()),
+        )
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, access_token: &str) -> Result<T, AppError> {
+        let api_url = self.config.api_url.as_deref().unwrap_or(API_URL);
+
+        self.http
+            .get(format!("{}{}", api_url.trim_end_matches('/'), path))
+            .bearer_auth(access_token)
+            // GitHub rejects API requests without a User-Agent
+            .header(reqwest::header::USER_AGENT, "auth_session")
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| AppError::Internal(format!("Failed to fetch GitHub {}: {}", path, e)))?
+            .json()
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to parse GitHub {}: {}", path, e)))
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for GitHubProvider {
+    fn config(&self) -> &OAuthProviderConfig {
+        &self.config
+    }
+
+    async fn authorization_request(&self) -> Result<AuthorizationRequest, AppError> {
+        Ok(oauth::authorization_request(&self.client()?, &self.config, None))
+    }
+
+    async fn authenticate(
+        &self,
+        code: String,
+        pkce_verifier: Option<String>,
+        _nonce: Option<&str>,
+    ) -> Result<ProviderIdentity, AppError> {
+        let token = oauth::exchange_code(&self.client()?, &self.config, code, pkce_verifier).await?;
+        let access_token = token.access_token().secret();
+
+        let user: GitHubUser = self.get("/user", access_token).await?;
+        let emails: Vec<GitHubEmail> = self.get("/user/emails", access_token).await?;
+
+        let email = primary_email(emails)
+            .ok_or_else(|| AppError::BadRequest("GitHub account has no verified primary email".to_string()))?;
+
+        Ok(ProviderIdentity {
+            subject: user.id.to_string(),
+            email,
+            email_verified: true,
+            name: user.name.filter(|n| !n.is_empty()).unwrap_or(user.login),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emails(json: serde_json::Value) -> Vec<GitHubEmail> {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn picks_the_verified_primary_address() {
+        // As returned by GET /user/emails, including fields we don't read
+        let emails = emails(serde_json::json!([
+            { "email": "old@example.com", "primary": false, "verified": true, "visibility": null },
+            { "email": "octocat@example.com", "primary": true, "verified": true, "visibility": "private" },
+            { "email": "123+octocat@users.noreply.github.com", "primary": false, "verified": true, "visibility": null },
+        ]));
+
+        assert_eq!(primary_email(emails).as_deref(), Some("octocat@example.com"));
+    }
+
+    #[test]
+    fn ignores_an_unverified_primary_address() {
+        let emails = emails(serde_json::json!([
+            { "email": "octocat@example.com", "primary": true, "verified": false },
+            { "email": "other@example.com", "primary": false, "verified": true },
+        ]));
+
+        assert_eq!(primary_email(emails), None);
+    }
+}
@@ -0,0 +1,80 @@
+use async_trait::async_trait;
+use oauth2::{TokenResponse, basic::BasicTokenResponse};
+use serde::Deserialize;
+use crate::config::OAuthProviderConfig;
+use crate::error::AppError;
+use crate::oauth::{self, AuthorizationRequest, OAuthProvider, ProviderIdentity};
+
+const AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const USERINFO_URL: &str = "https://www.googleapis.com/oauth2/v2/userinfo";
+
+#[derive(Debug, Deserialize)]
+struct GoogleUserInfo {
+    id: String,
+    email: String,
+    verified_email: Option<bool>,
+    name: String,
+}
+
+/// Google sign-in through the v2 userinfo endpoint, so existing accounts keep
+/// matching on the `id` Google has always returned there.
+pub struct GoogleProvider {
+    config: OAuthProviderConfig,
+    http: reqwest::Client,
+}
+
+impl GoogleProvider {
+    pub fn new(config: OAuthProviderConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    fn client(&self) -> Result<oauth::ProviderClient<BasicTokenResponse>, AppError> {
+        oauth::build_client(
+            &self.config,
+            self.config.auth_url.clone().unwrap_or_else(|| AUTH_URL.to_string()),
+            self.config.token_url.clone().unwrap_or_else(|| TOKEN_URL.to_string()),
+        )
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for GoogleProvider {
+    fn config(&self) -> &OAuthProviderConfig {
+        &self.config
+    }
+
+    async fn authorization_request(&self) -> Result<AuthorizationRequest, AppError> {
+        Ok(oauth::authorization_request(&self.client()?, &self.config, None))
+    }
+
+    async fn authenticate(
+        &self,
+        code: String,
+        pkce_verifier: Option<String>,
+        _nonce: Option<&str>,
+    ) -> Result<ProviderIdentity, AppError> {
+        let token = oauth::exchange_code(&self.client()?, &self.config, code, pkce_verifier).await?;
+
+        let google_user: GoogleUserInfo = self
+            .http
+            .get(USERINFO_URL)
+            .bearer_auth(token.access_token().secret())
+            .send()
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to fetch Google user info: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to parse Google user info: {}", e)))?;
+
+        Ok(ProviderIdentity {
+            subject: google_user.id,
+            email: google_user.email,
+            email_verified: google_user.verified_email.unwrap_or(false),
+            name: google_user.name,
+        })
+    }
+}
@@ -0,0 +1,180 @@
+pub mod github;
+pub mod google;
+pub mod oidc;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use async_trait::async_trait;
+use oauth2::{
+    AuthUrl, AuthorizationCode, Client, ClientId, ClientSecret, CsrfToken, PkceCodeChallenge,
+    PkceCodeVerifier, RedirectUrl, Scope, StandardRevocableToken, TokenResponse, TokenUrl,
+    basic::{BasicErrorResponse, BasicRevocationErrorResponse, BasicTokenIntrospectionResponse, BasicTokenType},
+    reqwest::async_http_client,
+};
+use crate::config::{OAuthProviderConfig, OAuthProviderKind};
+use crate::error::AppError;
+
+pub use github::GitHubProvider;
+pub use google::GoogleProvider;
+pub use oidc::OidcProvider;
+
+/// An `oauth2` client whose token response type `TR` varies by provider.
+pub type ProviderClient<TR> = Client<
+    BasicErrorResponse,
+    TR,
+    BasicTokenType,
+    BasicTokenIntrospectionResponse,
+    StandardRevocableToken,
+    BasicRevocationErrorResponse,
+>;
+
+/// A user as asserted by an identity provider after a successful login.
+#[derive(Debug, Clone)]
+pub struct ProviderIdentity {
+    /// Stable account id at the provider, stored as `user_identities.provider_user_id`
+    pub subject: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub name: String,
+}
+
+/// The parts of an authorization request the caller must remember until the
+/// callback arrives.
+#[derive(Debug)]
+pub struct AuthorizationRequest {
+    pub url: String,
+    pub csrf_token: CsrfToken,
+    pub pkce_verifier: Option<String>,
+    pub nonce: Option<String>,
+}
+
+/// A login provider reachable under `/api/auth/{provider}`. Each
+/// implementation owns the mapping from its userinfo to a `ProviderIdentity`.
+#[async_trait]
+pub trait OAuthProvider: Send + Sync {
+    fn config(&self) -> &OAuthProviderConfig;
+
+    async fn authorization_request(&self) -> Result<AuthorizationRequest, AppError>;
+
+    async fn authenticate(
+        &self,
+        code: String,
+        pkce_verifier: Option<String>,
+        nonce: Option<&str>,
+    ) -> Result<ProviderIdentity, AppError>;
+}
+
+/// Named identity providers, built once from `AppConfig::oauth_providers`.
+#[derive(Clone, Default)]
+pub struct ProviderRegistry {
+    providers: Arc<HashMap<String, Arc<dyn OAuthProvider>>>,
+}
+
+impl ProviderRegistry {
+    pub fn new(configs: &[OAuthProviderConfig]) -> Self {
+        let providers = configs
+            .iter()
+            .map(|config| {
+                let provider: Arc<dyn OAuthProvider> = match config.kind {
+                    OAuthProviderKind::Google => Arc::new(GoogleProvider::new(config.clone())),
+                    OAuthProviderKind::GitHub => Arc::new(GitHubProvider::new(config.clone())),
+                    OAuthProviderKind::Oidc => Arc::new(OidcProvider::new(config.clone())),
+                };
+                (config.name.clone(), provider)
+            })
+            .collect();
+
+        Self {
+            providers: Arc::new(providers),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn OAuthProvider>> {
+        self.providers.get(name).cloned()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.providers.keys().map(String::as_str)
+    }
+}
+
+pub(crate) fn build_client<TR>(
+    config: &OAuthProviderConfig,
+    auth_url: String,
+    token_url: String,
+) -> Result<ProviderClient<TR>, AppError>
+where
+    TR: TokenResponse<BasicTokenType>,
+{
+    Ok(ProviderClient::<TR>::new(
+        ClientId::new(config.client_id.clone()),
+        Some(ClientSecret::new(config.client_secret.clone())),
+        AuthUrl::new(auth_url)
+            .map_err(|e| AppError::Internal(format!("Invalid auth URL: {}", e)))?,
+        Some(TokenUrl::new(token_url)
+            .map_err(|e| AppError::Internal(format!("Invalid token URL: {}", e)))?),
+    )
+    .set_redirect_uri(
+        RedirectUrl::new(config.redirect_url.clone())
+            .map_err(|e| AppError::Internal(format!("Invalid redirect URL: {}", e)))?,
+    ))
+}
+
+/// Builds the redirect to the provider, adding a PKCE challenge when the
+/// provider is configured for it and a `nonce` for OpenID Connect.
+pub(crate) fn authorization_request<TR>(
+    client: &ProviderClient<TR>,
+    config: &OAuthProviderConfig,
+    nonce: Option<String>,
+) -> AuthorizationRequest
+where
+    TR: TokenResponse<BasicTokenType>,
+{
+    let mut auth_request = client
+        .authorize_url(CsrfToken::new_random)
+        .add_scopes(config.scopes.iter().cloned().map(Scope::new));
+
+    if let Some(nonce) = &nonce {
+        auth_request = auth_request.add_extra_param("nonce", nonce.clone());
+    }
+
+    let pkce_verifier = if config.use_pkce {
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        auth_request = auth_request.set_pkce_challenge(pkce_challenge);
+        Some(pkce_verifier.secret().clone())
+    } else {
+        None
+    };
+
+    let (url, csrf_token) = auth_request.url();
+
+    AuthorizationRequest {
+        url: url.to_string(),
+        csrf_token,
+        pkce_verifier,
+        nonce,
+    }
+}
+
+pub(crate) async fn exchange_code<TR>(
+    client: &ProviderClient<TR>,
+    config: &OAuthProviderConfig,
+    code: String,
+    pkce_verifier: Option<String>,
+) -> Result<TR, AppError>
+where
+    TR: TokenResponse<BasicTokenType>,
+{
+    let mut token_request = client.exchange_code(AuthorizationCode::new(code));
+    if let Some(pkce_verifier) = pkce_verifier {
+        token_request = token_request.set_pkce_verifier(PkceCodeVerifier::new(pkce_verifier));
+    }
+
+    token_request
+        .request_async(async_http_client)
+        .await
+        .map_err(|e| {
+            tracing::warn!("OAuth code exchange failed for {}: {}", config.name, e);
+            AppError::Unauthorized
+        })
+}
@@ -0,0 +1,262 @@
+use async_trait::async_trait;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use oauth2::{ExtraTokenFields, StandardTokenResponse, TokenResponse, basic::BasicTokenType};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use crate::config::OAuthProviderConfig;
+use crate::error::AppError;
+use crate::oauth::{self, AuthorizationRequest, OAuthProvider, ProviderClient, ProviderIdentity};
+use crate::utils::token;
+
+// Multi-tenant Microsoft endpoints publish this placeholder in their issuer
+const TENANT_PLACEHOLDER: &str = "{tenantid}";
+
+/// The subset of `.well-known/openid-configuration` we rely on.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: Option<String>,
+    pub jwks_uri: String,
+}
+
+/// Token endpoint response fields beyond plain OAuth 2.0.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdTokenFields {
+    pub id_token: Option<String>,
+}
+
+impl ExtraTokenFields for IdTokenFields {}
+
+type OidcTokenResponse = StandardTokenResponse<IdTokenFields, BasicTokenType>;
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    iss: String,
+    sub: String,
+    email: Option<String>,
+    email_verified: Option<bool>,
+    name: Option<String>,
+    nonce: Option<String>,
+    // Microsoft tenant id, used to resolve a templated issuer
+    tid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserInfoClaims {
+    sub: String,
+    email: Option<String>,
+    email_verified: Option<bool>,
+    name: Option<String>,
+}
+
+/// An OpenID Connect provider configured from its issuer URL. Discovery and
+/// JWKS documents are fetched on first use and cached; the JWKS is refetched
+/// when an `id_token` names a key we haven't seen, so key rotation at the
+/// provider needs no restart.
+pub struct OidcProvider {
+    config: OAuthProviderConfig,
+    http: reqwest::Client,
+    metadata: RwLock<Option<ProviderMetadata>>,
+    jwks: RwLock<Option<JwkSet>>,
+}
+
+impl OidcProvider {
+    pub fn new(config: OAuthProviderConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+            metadata: RwLock::new(None),
+            jwks: RwLock::new(None),
+        }
+    }
+
+    fn issuer_url(&self) -> &str {
+        self.config.issuer_url.as_deref().unwrap_or_default().trim_end_matches('/')
+    }
+
+    pub async fn metadata(&self) -> Result<ProviderMetadata, AppError> {
+        if let Some(metadata) = self.metadata.read().await.as_ref() {
+            return Ok(metadata.clone());
+        }
+
+        let issuer = self.issuer_url();
+        let discovery_url = format!("{}/.well-known/openid-configuration", issuer);
+        let metadata: ProviderMetadata = self
+            .http
+            .get(&discovery_url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| AppError::Internal(format!("OIDC discovery failed for {}: {}", self.config.name, e)))?
+            .json()
+            .await
+            .map_err(|e| AppError::Internal(format!("Invalid OIDC discovery document for {}: {}", self.config.name, e)))?;
+
+        // OpenID Connect Discovery 1.0 §4.3: the issuer must match exactly,
+        // except for the tenant placeholder of multi-tenant endpoints
+        let discovered = metadata.issuer.trim_end_matches('/');
+        if discovered != issuer && !discovered.contains(TENANT_PLACEHOLDER) {
+            return Err(AppError::Internal(format!(
+                "OIDC issuer mismatch for {}: expected {}, got {}",
+                self.config.name, issuer, metadata.issuer
+            )));
+        }
+
+        *self.metadata.write().await = Some(metadata.clone());
+        tracing::debug!("OIDC discovery completed for {}", self.config.name);
+        Ok(metadata)
+    }
+
+    async fn client(&self) -> Result<ProviderClient<OidcTokenResponse>, AppError> {
+        let metadata = self.metadata().await?;
+        oauth::build_client(&self.config, metadata.authorization_endpoint, metadata.token_endpoint)
+    }
+
+    async fn verify_id_token(&self, id_token: &str, nonce: &str) -> Result<IdTokenClaims, AppError> {
+        let header = decode_header(id_token)?;
+
+        // Never let the token pick a symmetric algorithm keyed by public data
+        if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            return Err(AppError::Unauthorized);
+        }
+
+        let jwk = self.find_jwk(header.kid.as_deref()).await?;
+        let key = DecodingKey::from_jwk(&jwk)?;
+
+        let metadata = self.metadata().await?;
+        let templated_issuer = metadata.issuer.contains(TENANT_PLACEHOLDER);
+
+        let mut validation = Validation::new(header.alg);
+        if !templated_issuer {
+            validation.set_issuer(&[&metadata.issuer]);
+        }
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)?.claims;
+
+        if templated_issuer {
+            let tenant = claims.tid.as_deref().ok_or(AppError::Unauthorized)?;
+            if claims.iss != metadata.issuer.replace(TENANT_PLACEHOLDER, tenant) {
+                return Err(AppError::Unauthorized);
+            }
+        }
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            tracing::warn!("OIDC id_token nonce mismatch for {}", self.config.name);
+            return Err(AppError::Unauthorized);
+        }
+
+        Ok(claims)
+    }
+
+    async fn find_jwk(&self, kid: Option<&str>) -> Result<Jwk, AppError> {
+        if let Some(jwk) = self.jwks.read().await.as_ref().and_then(|jwks| select_jwk(jwks, kid)) {
+            return Ok(jwk);
+        }
+
+        // Unknown key id: the provider may have rotated keys since our last fetch
+        let jwks = self.fetch_jwks().await?;
+        let jwk = select_jwk(&jwks, kid).ok_or(AppError::Unauthorized);
+        *self.jwks.write().await = Some(jwks);
+        jwk
+    }
+
+    async fn fetch_jwks(&self) -> Result<JwkSet, AppError> {
+        let metadata = self.metadata().await?;
+
+        self.http
+            .get(&metadata.jwks_uri)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| AppError::Internal(format!("Failed to fetch JWKS for {}: {}", self.config.name, e)))?
+            .json()
+            .await
+            .map_err(|e| AppError::Internal(format!("Invalid JWKS for {}: {}", self.config.name, e)))
+    }
+
+    async fn fetch_userinfo(&self, access_token: &str) -> Result<UserInfoClaims, AppError> {
+        let metadata = self.metadata().await?;
+        let userinfo_endpoint = metadata.userinfo_endpoint.ok_or_else(|| {
+            AppError::BadRequest(format!("{} did not provide an email address", self.config.name))
+        })?;
+
+        self.http
+            .get(&userinfo_endpoint)
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| AppError::Internal(format!("Failed to fetch user info from {}: {}", self.config.name, e)))?
+            .json()
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to parse user info from {}: {}", self.config.name, e)))
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for OidcProvider {
+    fn config(&self) -> &OAuthProviderConfig {
+        &self.config
+    }
+
+    async fn authorization_request(&self) -> Result<AuthorizationRequest, AppError> {
+        let nonce = token::generate_opaque_token();
+        Ok(oauth::authorization_request(&self.client().await?, &self.config, Some(nonce)))
+    }
+
+    /// Exchanges the authorization code and validates the returned `id_token`
+    /// (signature, `iss`, `aud`, `exp` and `nonce`).
+    async fn authenticate(
+        &self,
+        code: String,
+        pkce_verifier: Option<String>,
+        nonce: Option<&str>,
+    ) -> Result<ProviderIdentity, AppError> {
+        let nonce = nonce.ok_or(AppError::Unauthorized)?;
+        let token = oauth::exchange_code(&self.client().await?, &self.config, code, pkce_verifier).await?;
+
+        let id_token = token
+            .extra_fields()
+            .id_token
+            .as_deref()
+            .ok_or(AppError::Unauthorized)?;
+        let claims = self.verify_id_token(id_token, nonce).await?;
+
+        // Some providers only put `sub` in the id_token and leave the profile
+        // to the userinfo endpoint
+        let (email, email_verified, name) = match claims.email {
+            Some(email) => (email, claims.email_verified.unwrap_or(false), claims.name),
+            None => {
+                let userinfo = self.fetch_userinfo(token.access_token().secret()).await?;
+                if userinfo.sub != claims.sub {
+                    return Err(AppError::Unauthorized);
+                }
+                let email = userinfo.email.ok_or_else(|| {
+                    AppError::BadRequest(format!("{} did not provide an email address", self.config.name))
+                })?;
+                (email, userinfo.email_verified.unwrap_or(false), userinfo.name.or(claims.name))
+            }
+        };
+
+        Ok(ProviderIdentity {
+            subject: claims.sub,
+            name: name.unwrap_or_else(|| email.clone()),
+            email,
+            email_verified,
+        })
+    }
+}
+
+fn select_jwk(jwks: &JwkSet, kid: Option<&str>) -> Option<Jwk> {
+    match kid {
+        Some(kid) => jwks.find(kid).cloned(),
+        // Without a kid we can only pick a key if there's exactly one
+        None if jwks.keys.len() == 1 => jwks.keys.first().cloned(),
+        None => None,
+    }
+}
@@ -0,0 +1,17 @@
+use axum::{routing::{get, put}, Router};
+use crate::handlers::{admin_handler, auth_handler::AppState};
+use crate::middleware::authorization::RequirePermission;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/admin/users/{id}/roles",
+            get(admin_handler::get_user_roles).route_layer(RequirePermission("users:read")),
+        )
+        .route(
+            "/api/admin/users/{id}/roles/{role}",
+            put(admin_handler::assign_role)
+                .delete(admin_handler::revoke_role)
+                .route_layer(RequirePermission("roles:write")),
+        )
+}
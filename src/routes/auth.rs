@@ -1,11 +1,25 @@
 use axum::{routing::{post, get}, Router};
-use crate::handlers::auth_handler::{self, AppState};
+use crate::handlers::{magic_link_handler, mfa_handler, oauth_handler, password_handler, verification_handler, webauthn_handler, auth_handler::{self, AppState}};
 
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/api/auth/register", post(auth_handler::register))
         .route("/api/auth/login", post(auth_handler::login))
+        .route("/api/auth/refresh", post(auth_handler::refresh))
         .route("/api/auth/logout", post(auth_handler::logout))
-        .route("/api/auth/google", get(auth_handler::google_oauth))
-        .route("/api/auth/google/callback", get(auth_handler::google_oauth_callback))
+        .route("/api/auth/logout-all", post(auth_handler::logout_all))
+        .route("/api/auth/magic-link", post(magic_link_handler::request_magic_link))
+        .route("/api/auth/magic-link/consume", post(magic_link_handler::consume_magic_link))
+        .route("/api/auth/verify-email", post(verification_handler::verify_email))
+        .route("/api/auth/verify-email/resend", post(verification_handler::resend_verification))
+        .route("/api/auth/password/forgot", post(password_handler::forgot_password))
+        .route("/api/auth/password/reset", post(password_handler::reset_password))
+        .route("/api/auth/mfa/verify", post(mfa_handler::verify))
+        .route("/api/auth/mfa/webauthn", post(webauthn_handler::second_factor_options))
+        .route("/api/auth/passkey/options", post(webauthn_handler::login_options))
+        .route("/api/auth/passkey/login", post(webauthn_handler::login))
+        .route("/api/auth/link/email", post(oauth_handler::request_link_email))
+        .route("/api/auth/link/confirm", post(oauth_handler::confirm_link))
+        .route("/api/auth/{provider}", get(oauth_handler::authorize))
+        .route("/api/auth/{provider}/callback", get(oauth_handler::callback))
 }
@@ -1,5 +1,7 @@
+pub mod admin;
 pub mod auth;
 pub mod home;
+pub mod oauth;
 pub mod profile;
 
 use axum::Router;
@@ -9,5 +11,7 @@ pub fn app_routes() -> Router<AppState> {
     Router::new()
         .merge(home::routes())
         .merge(auth::routes())
+        .merge(oauth::routes())
         .merge(profile::routes())
+        .merge(admin::routes())
 }
@@ -0,0 +1,14 @@
+use axum::{routing::{get, post}, Router};
+use crate::handlers::{authorization_handler, oidc_handler, auth_handler::AppState};
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/oauth/authorize",
+            get(authorization_handler::authorize).post(authorization_handler::decide),
+        )
+        .route("/oauth/token", post(authorization_handler::token))
+        .route("/.well-known/openid-configuration", get(oidc_handler::discovery))
+        .route("/.well-known/jwks.json", get(oidc_handler::jwks))
+        .route("/userinfo", get(oidc_handler::userinfo).post(oidc_handler::userinfo))
+}
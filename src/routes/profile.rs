@@ -1,8 +1,26 @@
-use axum::{routing::get, Router};
-use crate::handlers::user_handler;
+use axum::{routing::{delete, get, post}, Router};
+use crate::handlers::{mfa_handler, oauth_handler, user_handler, webauthn_handler};
 use crate::handlers::auth_handler::AppState;
 
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/api/profile", get(user_handler::get_profile))
+        .route("/api/profile/identities", get(user_handler::list_identities))
+        .route(
+            "/api/profile/identities/{provider}",
+            post(oauth_handler::start_link).delete(user_handler::unlink_identity),
+        )
+        .route("/api/profile/mfa", get(mfa_handler::status))
+        .route(
+            "/api/profile/mfa/totp",
+            post(mfa_handler::enroll_totp).delete(mfa_handler::disable_totp),
+        )
+        .route("/api/profile/mfa/totp/confirm", post(mfa_handler::confirm_totp))
+        .route("/api/profile/mfa/recovery-codes", post(mfa_handler::regenerate_recovery_codes))
+        .route(
+            "/api/profile/passkeys",
+            get(webauthn_handler::list_passkeys).post(webauthn_handler::register),
+        )
+        .route("/api/profile/passkeys/options", post(webauthn_handler::registration_options))
+        .route("/api/profile/passkeys/{id}", delete(webauthn_handler::delete_passkey))
 }
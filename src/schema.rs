@@ -1,19 +1,226 @@
 // @generated automatically by Diesel CLI.
 
 diesel::table! {
-    users (id) {
+    email_verification_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 64]
+        token_hash -> Varchar,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    magic_link_tokens (id) {
         id -> Uuid,
         #[max_length = 255]
         email -> Varchar,
+        #[max_length = 64]
+        token_hash -> Varchar,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    oauth_clients (id) {
+        id -> Uuid,
+        #[max_length = 100]
+        client_id -> Varchar,
         #[max_length = 255]
         name -> Varchar,
         #[max_length = 255]
-        password_hash -> Nullable<Varchar>,
+        client_secret_hash -> Nullable<Varchar>,
+        redirect_uris -> Array<Text>,
+        allowed_scopes -> Array<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    password_reset_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 64]
+        token_hash -> Varchar,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    permissions (id) {
+        id -> Uuid,
+        #[max_length = 100]
+        name -> Varchar,
+        #[max_length = 255]
+        description -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    recovery_codes (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 64]
+        code_hash -> Varchar,
+        used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    refresh_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        family_id -> Uuid,
+        #[max_length = 64]
+        token_hash -> Varchar,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    revoked_tokens (jti) {
+        jti -> Uuid,
+        user_id -> Uuid,
+        expires_at -> Timestamp,
+        revoked_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    role_permissions (role_id, permission_id) {
+        role_id -> Uuid,
+        permission_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    roles (id) {
+        id -> Uuid,
+        #[max_length = 50]
+        name -> Varchar,
+        #[max_length = 255]
+        description -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    signing_keys (kid) {
+        #[max_length = 100]
+        kid -> Varchar,
+        #[max_length = 10]
+        algorithm -> Varchar,
+        #[max_length = 500]
+        private_key_path -> Nullable<Varchar>,
+        #[max_length = 500]
+        public_key_path -> Nullable<Varchar>,
+        active -> Bool,
+        not_after -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    totp_secrets (user_id) {
+        user_id -> Uuid,
+        #[max_length = 64]
+        secret -> Varchar,
+        confirmed_at -> Nullable<Timestamp>,
+        last_used_step -> Nullable<Int8>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    user_identities (id) {
+        id -> Uuid,
+        user_id -> Uuid,
         #[max_length = 50]
-        oauth_provider -> Nullable<Varchar>,
+        provider -> Varchar,
+        #[max_length = 255]
+        provider_user_id -> Varchar,
         #[max_length = 255]
-        oauth_id -> Nullable<Varchar>,
+        email -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    user_roles (user_id, role_id) {
+        user_id -> Uuid,
+        role_id -> Uuid,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    users (id) {
+        id -> Uuid,
+        #[max_length = 255]
+        email -> Varchar,
+        #[max_length = 255]
+        name -> Varchar,
+        #[max_length = 255]
+        password_hash -> Nullable<Varchar>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        tokens_valid_after -> Nullable<Timestamp>,
+        email_verified_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    webauthn_credentials (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 1366]
+        credential_id -> Varchar,
+        public_key -> Bytea,
+        sign_count -> Int8,
+        #[max_length = 255]
+        name -> Varchar,
+        created_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
     }
 }
+
+diesel::joinable!(email_verification_tokens -> users (user_id));
+diesel::joinable!(password_reset_tokens -> users (user_id));
+diesel::joinable!(recovery_codes -> users (user_id));
+diesel::joinable!(refresh_tokens -> users (user_id));
+diesel::joinable!(revoked_tokens -> users (user_id));
+diesel::joinable!(role_permissions -> permissions (permission_id));
+diesel::joinable!(role_permissions -> roles (role_id));
+diesel::joinable!(totp_secrets -> users (user_id));
+diesel::joinable!(user_identities -> users (user_id));
+diesel::joinable!(user_roles -> roles (role_id));
+diesel::joinable!(user_roles -> users (user_id));
+diesel::joinable!(webauthn_credentials -> users (user_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    email_verification_tokens,
+    magic_link_tokens,
+    oauth_clients,
+    password_reset_tokens,
+    permissions,
+    recovery_codes,
+    refresh_tokens,
+    revoked_tokens,
+    role_permissions,
+    roles,
+    signing_keys,
+    totp_secrets,
+    user_identities,
+    user_roles,
+    users,
+    webauthn_credentials,
+);
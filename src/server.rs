@@ -3,15 +3,17 @@ use std::net::SocketAddr;
 use std::fs::File;
 use std::io::BufReader;
 use std::sync::Arc;
-use tower_http::cors::CorsLayer;
 use tower_http::services::ServeDir;
 use tower::ServiceBuilder;
 use crate::config::AppConfig;
 use crate::error::AppError;
+use crate::mail::{self, MailTemplates};
 use crate::routes;
+use crate::utils::jwt::JwtKeys;
+use crate::oauth::ProviderRegistry;
 use crate::db::{DieselStore, create_pool};
 use crate::handlers::auth_handler::AppState;
-use crate::middleware::{timing, rate_limit::RateLimiter};
+use crate::middleware::{auth_middleware, cors, timing, account_link::AccountLinkStore, authorization_code::AuthorizationCodeStore, mfa_challenge::MfaChallengeStore, webauthn_challenge::WebAuthnChallengeStore, oauth_state::OAuthStateStore, rate_limit::RateLimiter, revocation::RevocationList};
 
 pub async fn run(config: AppConfig) -> Result<(), AppError> {
     tracing::debug!("Creating database connection pool...");
@@ -35,7 +37,11 @@ pub async fn run(config: AppConfig) -> Result<(), AppError> {
     }
     tracing::debug!("Connection pool pre-warmed in {}ms", warm_start.elapsed().as_millis());
 
-    let diesel_store = DieselStore::new(pool);
+    let diesel_store = Arc::new(DieselStore::new(pool));
+
+    let jwt_keys = JwtKeys::new(&config.jwt, diesel_store.clone())?;
+    jwt_keys.sync().await?;
+    tracing::info!("JWT signing algorithm: {:?}", jwt_keys.algorithm());
 
     // Initialize rate limiter
     // 10 attempts per IP per 3 minutes
@@ -47,22 +53,92 @@ pub async fn run(config: AppConfig) -> Result<(), AppError> {
     );
     tracing::info!("Rate limiter initialized: 10 attempts/IP, 5 attempts/email per 3 minutes");
 
+    let revocations = RevocationList::new(diesel_store.clone());
+    revocations.sync().await?;
+
+    // Pick up revocations and key rotations made by other instances and drop
+    // expired entries
+    let revocations_sync = revocations.clone();
+    let jwt_keys_sync = jwt_keys.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+        loop {
+            interval.tick().await;
+            if let Err(e) = revocations_sync.sync().await {
+                tracing::warn!("Failed to sync revocation list: {}", e);
+            }
+            if let Err(e) = revocations_sync.cleanup().await {
+                tracing::warn!("Failed to purge expired revocations: {}", e);
+            }
+            if let Err(e) = jwt_keys_sync.sync().await {
+                tracing::warn!("Failed to sync JWT signing keys: {}", e);
+            }
+        }
+    });
+
+    // Pending OAuth authorizations expire after 10 minutes; at most 10,000
+    // can be waiting at once
+    let oauth_states = OAuthStateStore::new(600, 10_000);
+    // Logins waiting for the account owner to confirm a link expire after 15 minutes
+    let account_links = AccountLinkStore::new(900);
+    // Codes we issue as an authorization server are exchanged within a minute
+    let authorization_codes = AuthorizationCodeStore::new(60);
+    // Logins waiting for their second factor expire after 5 minutes
+    let mfa_challenges = MfaChallengeStore::new(300);
+    // WebAuthn ceremonies must finish within 5 minutes, as the browser is
+    // told; at most 10,000 can be pending at once
+    let webauthn_challenges = WebAuthnChallengeStore::new(300, 10_000);
+    let oauth_states_cleanup = oauth_states.clone();
+    let account_links_cleanup = account_links.clone();
+    let authorization_codes_cleanup = authorization_codes.clone();
+    let mfa_challenges_cleanup = mfa_challenges.clone();
+    let webauthn_challenges_cleanup = webauthn_challenges.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            oauth_states_cleanup.cleanup();
+            account_links_cleanup.cleanup();
+            authorization_codes_cleanup.cleanup();
+            mfa_challenges_cleanup.cleanup();
+            webauthn_challenges_cleanup.cleanup();
+        }
+    });
+
+    let mailer = mail::from_config(&config.mail)?;
+    let mail_templates = MailTemplates::new(&config.mail)?;
+
+    let providers = ProviderRegistry::new(&config.oauth_providers);
+    for name in providers.names() {
+        tracing::info!("OAuth provider configured: {}", name);
+    }
+
     let app_state = AppState {
         config: config.clone(),
-        store: diesel_store,
+        jwt_keys,
+        store: diesel_store.clone(),
+        identities: diesel_store.clone(),
+        refresh_tokens: diesel_store.clone(),
+        roles: diesel_store.clone(),
+        mfa: diesel_store.clone(),
+        webauthn: diesel_store.clone(),
+        password_resets: diesel_store.clone(),
+        email_verifications: diesel_store.clone(),
+        magic_links: diesel_store.clone(),
+        mailer,
+        mail_templates,
         rate_limiter,
+        revocations,
+        oauth_states,
+        account_links,
+        mfa_challenges,
+        webauthn_challenges,
+        providers,
+        oauth_clients: diesel_store,
+        authorization_codes,
     };
 
-    let app = Router::new()
-        .nest_service("/static", ServeDir::new("src/static"))
-        .merge(routes::app_routes())
-        .layer(
-            ServiceBuilder::new()
-                .layer(CorsLayer::permissive())
-                .layer(axum::middleware::from_fn(timing::timing_middleware))
-        )
-        .with_state(app_state)
-        .into_make_service_with_connect_info::<SocketAddr>();
+    let app = router(app_state).into_make_service_with_connect_info::<SocketAddr>();
 
     let addr = format!("{}:{}", config.server.host, config.server.port)
         .parse::<SocketAddr>()
@@ -90,6 +166,21 @@ pub async fn run(config: AppConfig) -> Result<(), AppError> {
     Ok(())
 }
 
+/// Every route with the middleware stack, for serving or for driving
+/// handlers directly in tests.
+pub fn router(app_state: AppState) -> Router {
+    Router::new()
+        .nest_service("/static", ServeDir::new("src/static"))
+        .merge(routes::app_routes())
+        .layer(
+            ServiceBuilder::new()
+                .layer(cors::cors_layer(&app_state.config.cors))
+                .layer(axum::middleware::from_fn(timing::timing_middleware))
+                .layer(axum::middleware::from_fn_with_state(app_state.clone(), auth_middleware::identify))
+        )
+        .with_state(app_state)
+}
+
 fn load_tls_config() -> Result<rustls::ServerConfig, AppError> {
     // Load certificate file
     let cert_file = File::open("certs/server.crt")
@@ -0,0 +1,81 @@
+// Session handling shared by the pages. With SESSION_COOKIES=true the
+// server keeps the tokens in httpOnly cookies, so they are not copied to
+// localStorage; requests echo the readable `<name>_csrf` cookie in the
+// X-CSRF-Token header instead of sending a bearer token.
+
+function csrfToken() {
+    const cookie = document.cookie
+        .split('; ')
+        .find((c) => c.split('=')[0].endsWith('_csrf'));
+    return cookie ? decodeURIComponent(cookie.slice(cookie.indexOf('=') + 1)) : null;
+}
+
+function saveSession(data) {
+    if (csrfToken()) {
+        localStorage.removeItem('token');
+        localStorage.removeItem('refresh_token');
+    } else {
+        localStorage.setItem('token', data.token);
+        localStorage.setItem('refresh_token', data.refresh_token);
+    }
+    localStorage.setItem('user', JSON.stringify(data.user));
+}
+
+function clearSession() {
+    localStorage.removeItem('token');
+    localStorage.removeItem('refresh_token');
+    localStorage.removeItem('user');
+}
+
+function withAuth(options = {}) {
+    const headers = { ...(options.headers || {}) };
+    const token = localStorage.getItem('token');
+    const csrf = csrfToken();
+    if (token) {
+        headers['Authorization'] = `Bearer ${token}`;
+    } else if (csrf) {
+        headers['X-CSRF-Token'] = csrf;
+    }
+    return { ...options, headers, credentials: 'same-origin' };
+}
+
+// Access tokens are short-lived, so a 401 is retried once after a refresh
+async function authFetch(url, options = {}) {
+    const response = await fetch(url, withAuth(options));
+    if (response.status !== 401 || !(await refreshSession())) {
+        return response;
+    }
+    return fetch(url, withAuth(options));
+}
+
+async function refreshSession() {
+    const refreshToken = localStorage.getItem('refresh_token');
+    const options = refreshToken
+        ? {
+            method: 'POST',
+            headers: { 'Content-Type': 'application/json' },
+            body: JSON.stringify({ refresh_token: refreshToken }),
+        }
+        : withAuth({ method: 'POST' });
+
+    const response = await fetch('/api/auth/refresh', options);
+    if (!response.ok) {
+        return false;
+    }
+    saveSession(await response.json());
+    return true;
+}
+
+// Revokes the session on the server, then forgets it locally either way
+async function endSession() {
+    const refreshToken = localStorage.getItem('refresh_token');
+    try {
+        await authFetch('/api/auth/logout', {
+            method: 'POST',
+            headers: { 'Content-Type': 'application/json' },
+            body: JSON.stringify(refreshToken ? { refresh_token: refreshToken } : {}),
+        });
+    } finally {
+        clearSession();
+    }
+}
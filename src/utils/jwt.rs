@@ -1,8 +1,20 @@
-use jsonwebtoken::{encode, decode, Header, Validation, EncodingKey, DecodingKey};
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+use jsonwebtoken::{encode, decode, decode_header, Algorithm, Header, Validation, EncodingKey, DecodingKey};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, EllipticCurve, EllipticCurveKeyParameters, EllipticCurveKeyType, Jwk, JwkSet,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType, ThumbprintHash,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use serde::{Deserialize, Serialize};
 use chrono::{Utc, Duration};
 use uuid::Uuid;
+use crate::config::JwtConfig;
+use crate::db::SigningKeyStore;
 use crate::error::AppError;
+use crate::models::role::UserGrants;
+use crate::models::signing_key::SigningKey;
+use crate::models::user::User;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
@@ -11,42 +23,616 @@ pub struct Claims {
     pub name: String,
     pub exp: i64,
     pub iat: i64,
+    pub nbf: i64,
+    pub jti: String,
+    pub iss: String,
+    /// `JWT_AUDIENCE`, or the `client_id` for tokens issued through `/oauth/token`
+    pub aud: String,
+    /// The OAuth client a token was issued to (RFC 9068 §2.2)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// Roles and permissions at issue time; changes apply from the next refresh
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+impl Claims {
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|p| p == permission)
+    }
+}
+
+struct LoadedKey {
+    algorithm: Algorithm,
+    // Absent for verify-only keys whose private half isn't on this host
+    encoding: Option<EncodingKey>,
+    decoding: DecodingKey,
+    // Public JWK, absent for HMAC secrets which must never be published
+    jwk: Option<Jwk>,
+}
+
+struct KeyEntry {
+    key: Arc<LoadedKey>,
+    // Unix seconds after which tokens signed with this key are rejected
+    not_after: Option<i64>,
+}
+
+struct KeyRing {
+    signing_kid: String,
+    keys: HashMap<String, KeyEntry>,
+}
+
+/// The keyset tokens are signed and verified with, indexed by `kid`: one
+/// active signing key plus verify-only keys that are still inside their
+/// overlap window. Mirrors the `signing_keys` table; `sync` picks up keys
+/// promoted or retired with `auth_session keys ...` without a restart.
+#[derive(Clone)]
+pub struct JwtKeys {
+    store: Arc<dyn SigningKeyStore>,
+    // Key from the JWT_* variables, used by rows without key paths
+    configured_kid: Arc<str>,
+    configured: Arc<LoadedKey>,
+    ring: Arc<RwLock<KeyRing>>,
+    issuer: Arc<str>,
+    audience: Arc<str>,
+    allowed_audiences: Arc<[String]>,
+    leeway: u64,
+}
+
+impl JwtKeys {
+    /// Starts out with the configured key only. HS* algorithms use
+    /// `JWT_SECRET`; RS256, ES256 and EdDSA load PKCS#8 PEM files.
+    pub fn new(config: &JwtConfig, store: Arc<dyn SigningKeyStore>) -> Result<Self, AppError> {
+        let algorithm = config.algorithm;
+
+        let key = if is_hmac(algorithm) {
+            if config.secret.is_empty() {
+                return Err(AppError::Internal(format!("JWT_SECRET must be set for {:?}", algorithm)));
+            }
+            hmac_key(algorithm, config.secret.as_bytes())
+        } else {
+            let private_pem = read_pem(config.private_key_path.as_deref(), "JWT_PRIVATE_KEY_PATH")?;
+            let public_pem = read_pem(config.public_key_path.as_deref(), "JWT_PUBLIC_KEY_PATH")?;
+            load_key_pair(algorithm, Some(&private_pem), &public_pem)?
+        };
+
+        // Default to the RFC 7638 thumbprint so the kid changes with the key
+        let kid = match (&config.key_id, &key.jwk) {
+            (Some(kid), _) => kid.clone(),
+            (None, Some(jwk)) => jwk.thumbprint(ThumbprintHash::SHA256),
+            (None, None) => "default".to_string(),
+        };
+        let key = Arc::new(with_kid(key, &kid));
+        check_key_pair(&key)?;
+
+        let ring = KeyRing {
+            signing_kid: kid.clone(),
+            keys: HashMap::from([(kid.clone(), KeyEntry { key: key.clone(), not_after: None })]),
+        };
+
+        Ok(Self {
+            store,
+            configured_kid: kid.into(),
+            configured: key,
+            ring: Arc::new(RwLock::new(ring)),
+            issuer: config.issuer.as_str().into(),
+            audience: config.audience.as_str().into(),
+            allowed_audiences: config.allowed_audiences.as_slice().into(),
+            leeway: config.leeway,
+        })
+    }
+
+    /// Reloads the keyset from the store. On first use the configured key is
+    /// recorded as the active one. Key files are only read for new `kid`s.
+    pub async fn sync(&self) -> Result<(), AppError> {
+        let mut records = self.store.list_signing_keys().await?;
+        if records.is_empty() {
+            let record = self.store.create_signing_key(
+                self.configured_kid.to_string(),
+                format!("{:?}", self.configured.algorithm),
+                None,
+                None,
+                true,
+            ).await?;
+            tracing::info!("Recorded configured JWT key {} as the active signing key", record.kid);
+            records.push(record);
+        }
+
+        let current: HashMap<String, Arc<LoadedKey>> = self
+            .read_ring()
+            .keys
+            .iter()
+            .map(|(kid, entry)| (kid.clone(), entry.key.clone()))
+            .collect();
+
+        let mut keys = HashMap::new();
+        let mut signing_kid = None;
+        for record in records {
+            let key = match current.get(&record.kid) {
+                Some(key) => key.clone(),
+                None => match self.load_record(&record) {
+                    Ok(key) => key,
+                    Err(e) => {
+                        tracing::warn!("Skipping JWT key {}: {}", record.kid, e);
+                        continue;
+                    }
+                },
+            };
+
+            if record.active && key.encoding.is_some() {
+                signing_kid = Some(record.kid.clone());
+            } else if record.active {
+                tracing::warn!("Active JWT key {} has no private key on this host", record.kid);
+            }
+
+            keys.insert(record.kid, KeyEntry {
+                key,
+                not_after: record.not_after.map(|t| t.and_utc().timestamp()),
+            });
+        }
+
+        // Keep signing with the previous key rather than failing every login
+        let signing_kid = signing_kid
+            .ok_or_else(|| AppError::Internal("No usable active JWT signing key".to_string()))?;
+
+        *self.ring.write().unwrap_or_else(PoisonError::into_inner) = KeyRing { signing_kid, keys };
+        Ok(())
+    }
+
+    /// Loads the key a `signing_keys` row points at and checks that its
+    /// halves belong together.
+    pub fn load(&self, record: &SigningKey) -> Result<(), AppError> {
+        self.load_record(record).map(|_| ())
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        let ring = self.read_ring();
+        ring.keys[&ring.signing_kid].key.algorithm
+    }
+
+    /// Public keys for `/.well-known/jwks.json`: every key that can still
+    /// verify tokens. Empty when signing with a shared secret.
+    pub fn jwks(&self) -> JwkSet {
+        let now = Utc::now().timestamp();
+
+        JwkSet {
+            keys: self
+                .read_ring()
+                .keys
+                .values()
+                .filter(|entry| entry.not_after.is_none_or(|not_after| now < not_after))
+                .filter_map(|entry| entry.key.jwk.clone())
+                .collect(),
+        }
+    }
+
+    /// Checks every token we accept: our issuer, an allowed audience and
+    /// `exp`/`nbf` within the configured leeway.
+    fn validation(&self) -> Validation {
+        let mut validation = Validation::default();
+        validation.set_issuer(&[&*self.issuer]);
+        validation.set_audience(&self.allowed_audiences);
+        validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud", "sub"]);
+        validation.validate_nbf = true;
+        validation.leeway = self.leeway;
+        validation
+    }
+
+    fn read_ring(&self) -> std::sync::RwLockReadGuard<'_, KeyRing> {
+        self.ring.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn load_record(&self, record: &SigningKey) -> Result<Arc<LoadedKey>, AppError> {
+        let algorithm: Algorithm = record
+            .algorithm
+            .parse()
+            .map_err(|_| AppError::Internal(format!("Unsupported JWT algorithm: {}", record.algorithm)))?;
+
+        let key = match (&record.private_key_path, &record.public_key_path) {
+            (None, None) if *self.configured_kid == record.kid => return Ok(self.configured.clone()),
+            (None, None) => {
+                return Err(AppError::Internal("No key files and not the configured JWT key".to_string()));
+            }
+            (Some(secret_path), _) if is_hmac(algorithm) => {
+                let secret = std::fs::read(secret_path)
+                    .map_err(|e| AppError::Internal(format!("Failed to read {}: {}", secret_path, e)))?;
+                hmac_key(algorithm, secret.trim_ascii())
+            }
+            (private_path, Some(public_path)) => {
+                let private_pem = private_path.as_deref().map(|p| read_pem(Some(p), p)).transpose()?;
+                let public_pem = read_pem(Some(public_path), public_path)?;
+                load_key_pair(algorithm, private_pem.as_deref(), &public_pem)?
+            }
+            (Some(_), None) => {
+                return Err(AppError::Internal(format!("A public key is required for {:?}", algorithm)));
+            }
+        };
+
+        let key = with_kid(key, &record.kid);
+        check_key_pair(&key)?;
+        Ok(Arc::new(key))
+    }
+
+    fn sign<T: Serialize>(&self, claims: &T) -> Result<String, AppError> {
+        let ring = self.read_ring();
+        let key = &ring.keys[&ring.signing_kid].key;
+        let encoding = key
+            .encoding
+            .as_ref()
+            .ok_or_else(|| AppError::Internal("Active JWT key cannot sign".to_string()))?;
+
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(ring.signing_kid.clone());
+
+        Ok(encode(&header, claims, encoding)?)
+    }
+
+    fn verify<T: serde::de::DeserializeOwned>(&self, token: &str, validation: Validation) -> Result<T, AppError> {
+        let header = decode_header(token)?;
+        let ring = self.read_ring();
+
+        // Tokens from before key ids were added carry none and were signed
+        // with the configured key
+        let kid = header.kid.as_deref().unwrap_or(&self.configured_kid);
+        let entry = ring.keys.get(kid).ok_or(AppError::Unauthorized)?;
+
+        if entry.not_after.is_some_and(|not_after| Utc::now().timestamp() >= not_after) {
+            return Err(AppError::Unauthorized);
+        }
+
+        verify_with(&entry.key, token, header.alg, validation)
+    }
+}
+
+fn verify_with<T: serde::de::DeserializeOwned>(
+    key: &LoadedKey,
+    token: &str,
+    alg: Algorithm,
+    mut validation: Validation,
+) -> Result<T, AppError> {
+    // The key decides the algorithm, never the token header
+    if alg != key.algorithm {
+        return Err(AppError::Unauthorized);
+    }
+    validation.algorithms = vec![key.algorithm];
+
+    Ok(decode::<T>(token, &key.decoding, &validation)?.claims)
+}
+
+// Catches a public key that doesn't belong to the private key when it is
+// loaded rather than on the first login
+fn check_key_pair(key: &LoadedKey) -> Result<(), AppError> {
+    let Some(encoding) = &key.encoding else {
+        return Ok(());
+    };
+
+    let probe = encode(&Header::new(key.algorithm), &serde_json::json!({ "exp": i64::MAX }), encoding)?;
+    verify_with::<serde_json::Value>(key, &probe, key.algorithm, Validation::default())
+        .map(|_| ())
+        .map_err(|_| AppError::Internal("JWT public key does not match the private key".to_string()))
+}
+
+fn is_hmac(algorithm: Algorithm) -> bool {
+    matches!(algorithm, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+fn hmac_key(algorithm: Algorithm, secret: &[u8]) -> LoadedKey {
+    LoadedKey {
+        algorithm,
+        encoding: Some(EncodingKey::from_secret(secret)),
+        decoding: DecodingKey::from_secret(secret),
+        jwk: None,
+    }
+}
+
+fn with_kid(mut key: LoadedKey, kid: &str) -> LoadedKey {
+    if let Some(jwk) = key.jwk.as_mut() {
+        jwk.common.key_id = Some(kid.to_string());
+        jwk.common.public_key_use = Some(PublicKeyUse::Signature);
+    }
+    key
+}
+
+fn read_pem(path: Option<&str>, variable: &str) -> Result<Vec<u8>, AppError> {
+    let path = path.ok_or_else(|| AppError::Internal(format!("{} must be set", variable)))?;
+    std::fs::read(path)
+        .map_err(|e| AppError::Internal(format!("Failed to read {} ({}): {}", variable, path, e)))
+}
+
+fn load_key_pair(
+    algorithm: Algorithm,
+    private_pem: Option<&[u8]>,
+    public_pem: &[u8],
+) -> Result<LoadedKey, AppError> {
+    let invalid = |e: jsonwebtoken::errors::Error| AppError::Internal(format!("Invalid {:?} key: {}", algorithm, e));
+
+    let (encoding, decoding) = match algorithm {
+        Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512
+        | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512 => (
+            private_pem.map(EncodingKey::from_rsa_pem).transpose().map_err(invalid)?,
+            DecodingKey::from_rsa_pem(public_pem).map_err(invalid)?,
+        ),
+        Algorithm::ES256 | Algorithm::ES384 => (
+            private_pem.map(EncodingKey::from_ec_pem).transpose().map_err(invalid)?,
+            DecodingKey::from_ec_pem(public_pem).map_err(invalid)?,
+        ),
+        Algorithm::EdDSA => (
+            private_pem.map(EncodingKey::from_ed_pem).transpose().map_err(invalid)?,
+            DecodingKey::from_ed_pem(public_pem).map_err(invalid)?,
+        ),
+        _ => return Err(AppError::Internal(format!("Unsupported JWT algorithm: {:?}", algorithm))),
+    };
+
+    let jwk = public_jwk(algorithm, &decoding)
+        .ok_or_else(|| AppError::Internal(format!("Invalid {:?} public key", algorithm)))?;
+
+    Ok(LoadedKey { algorithm, encoding, decoding, jwk: Some(jwk) })
+}
+
+/// Builds the public JWK from the decoded public key alone, so verify-only
+/// keys can be published without their private half.
+fn public_jwk(algorithm: Algorithm, decoding: &DecodingKey) -> Option<Jwk> {
+    let b64 = |bytes: &[u8]| URL_SAFE_NO_PAD.encode(bytes);
+    let public_key = decoding.as_bytes();
+
+    let algorithm_parameters = match algorithm {
+        Algorithm::EdDSA => AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: b64(public_key),
+        }),
+        // Uncompressed SEC1 point: 0x04 || x || y
+        Algorithm::ES256 | Algorithm::ES384 => {
+            let (&tag, point) = public_key.split_first()?;
+            if tag != 0x04 || point.len() % 2 != 0 {
+                return None;
+            }
+            let (x, y) = point.split_at(point.len() / 2);
+            AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                key_type: EllipticCurveKeyType::EC,
+                curve: if algorithm == Algorithm::ES256 { EllipticCurve::P256 } else { EllipticCurve::P384 },
+                x: b64(x),
+                y: b64(y),
+            })
+        }
+        _ => {
+            let (n, e) = rsa_components(public_key)?;
+            AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: b64(n),
+                e: b64(e),
+            })
+        }
+    };
+
+    let mut jwk = Jwk {
+        common: Default::default(),
+        algorithm: algorithm_parameters,
+    };
+    jwk.common.key_algorithm = format!("{:?}", algorithm).parse().ok();
+    Some(jwk)
+}
+
+// RSAPublicKey ::= SEQUENCE { modulus INTEGER, publicExponent INTEGER } (RFC 8017 §A.1.1)
+fn rsa_components(der: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (0x30, sequence, _) = der_element(der)? else {
+        return None;
+    };
+    let (0x02, n, rest) = der_element(sequence)? else {
+        return None;
+    };
+    let (0x02, e, _) = der_element(rest)? else {
+        return None;
+    };
+
+    Some((unsigned(n), unsigned(e)))
+}
+
+// DER integers carry a leading zero byte when the high bit is set
+pub(crate) fn unsigned(int: &[u8]) -> &[u8] {
+    match int {
+        [0, rest @ ..] if !rest.is_empty() => rest,
+        _ => int,
+    }
+}
+
+/// Splits one DER element into `(tag, contents, remaining input)`.
+pub(crate) fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&length, rest) = rest.split_first()?;
+
+    let (length, rest) = if length & 0x80 == 0 {
+        (length as usize, rest)
+    } else {
+        let octets = (length & 0x7f) as usize;
+        if octets > 4 || rest.len() < octets {
+            return None;
+        }
+        let (length, rest) = rest.split_at(octets);
+        (length.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize), rest)
+    };
+
+    if rest.len() < length {
+        return None;
+    }
+    let (contents, rest) = rest.split_at(length);
+    Some((tag, contents, rest))
 }
 
 pub fn generate_token(
-    user_id: Uuid,
-    email: &str,
-    name: &str,
-    secret: &str,
+    user: &User,
+    grants: &UserGrants,
+    keys: &JwtKeys,
     expiration_seconds: i64,
+    client_id: Option<&str>,
 ) -> Result<String, AppError> {
     let now = Utc::now();
     let iat = now.timestamp();
     let exp = (now + Duration::seconds(expiration_seconds)).timestamp();
 
     let claims = Claims {
-        sub: user_id.to_string(),
-        email: email.to_string(),
-        name: name.to_string(),
+        sub: user.id.to_string(),
+        email: user.email.clone(),
+        name: user.name.clone(),
         exp,
         iat,
+        nbf: iat,
+        jti: Uuid::new_v4().to_string(),
+        iss: keys.issuer.to_string(),
+        // Client tokens are addressed to the client (RFC 9068 §3), so they
+        // never carry the API audience
+        aud: client_id.unwrap_or(&keys.audience).to_string(),
+        client_id: client_id.map(str::to_string),
+        roles: grants.roles.clone(),
+        permissions: grants.permissions.clone(),
     };
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )?;
+    keys.sign(&claims)
+}
+
+// Tokens issued to OAuth clients or other services are rejected here
+pub fn verify_token(token: &str, keys: &JwtKeys) -> Result<Claims, AppError> {
+    let claims: Claims = keys.verify(token, keys.validation())?;
 
-    Ok(token)
+    // Even if a client was registered under one of our own audiences
+    if claims.client_id.is_some() {
+        return Err(AppError::Unauthorized);
+    }
+    Ok(claims)
 }
 
-pub fn verify_token(token: &str, secret: &str) -> Result<Claims, AppError> {
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::default(),
-    )?;
+/// Like `verify_token`, but also accepts the tokens issued to OAuth
+/// clients, whose audience is their `client_id`. Only endpoints meant for
+/// clients, such as `/userinfo`, should use it.
+pub fn verify_client_token(token: &str, keys: &JwtKeys) -> Result<Claims, AppError> {
+    let mut validation = keys.validation();
+    // Client ids aren't known up front, so the audience is checked below
+    validation.validate_aud = false;
+    let claims: Claims = keys.verify(token, validation)?;
+
+    let audience_ok = match &claims.client_id {
+        Some(client_id) => claims.aud == *client_id,
+        None => keys.allowed_audiences.contains(&claims.aud),
+    };
+    if !audience_ok {
+        return Err(AppError::Unauthorized);
+    }
+    Ok(claims)
+}
+
+/// Claims of an OpenID Connect ID token (OIDC Core §2).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    pub email: String,
+    pub name: String,
+}
+
+pub fn generate_id_token(
+    user: &User,
+    client_id: &str,
+    nonce: Option<String>,
+    keys: &JwtKeys,
+    expiration_seconds: i64,
+) -> Result<String, AppError> {
+    let now = Utc::now();
+
+    let claims = IdTokenClaims {
+        iss: keys.issuer.to_string(),
+        sub: user.id.to_string(),
+        aud: client_id.to_string(),
+        exp: (now + Duration::seconds(expiration_seconds)).timestamp(),
+        iat: now.timestamp(),
+        nonce,
+        email: user.email.clone(),
+        name: user.name.clone(),
+    };
+
+    keys.sign(&claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Store;
+
+    fn keys(algorithm: Algorithm, private_key: &str, public_key: &str) -> Result<JwtKeys, AppError> {
+        let config = JwtConfig {
+            secret: String::new(),
+            algorithm,
+            private_key_path: Some(format!("tests/fixtures/{}", private_key)),
+            public_key_path: Some(format!("tests/fixtures/{}", public_key)),
+            key_id: None,
+            expiration: 60,
+            refresh_expiration: 60,
+            issuer: "http://localhost:8000".into(),
+            audience: "auth_session".into(),
+            allowed_audiences: vec!["auth_session".into()],
+            leeway: 0,
+        };
+        JwtKeys::new(&config, Arc::new(Store::new()))
+    }
+
+    fn round_trip(keys: &JwtKeys) {
+        let now = Utc::now().timestamp();
+        let claims = serde_json::json!({
+            "sub": "alice",
+            "iss": "http://localhost:8000",
+            "aud": "auth_session",
+            "nbf": now,
+            "exp": now + 60,
+        });
+
+        let token = keys.sign(&claims).unwrap();
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.alg, keys.algorithm());
+        assert_eq!(header.kid.as_deref(), Some(&*keys.configured_kid));
+
+        let verified: serde_json::Value = keys.verify(&token, keys.validation()).unwrap();
+        assert_eq!(verified["sub"], "alice");
+    }
+
+    #[test]
+    fn es256_tokens_round_trip() {
+        let keys = keys(Algorithm::ES256, "ec_private.pem", "ec_public.pem").unwrap();
+        round_trip(&keys);
+
+        let jwks = keys.jwks();
+        assert!(matches!(
+            &jwks.keys[0].algorithm,
+            AlgorithmParameters::EllipticCurve(ec) if ec.curve == EllipticCurve::P256
+        ));
+    }
+
+    #[test]
+    fn eddsa_tokens_round_trip() {
+        let keys = keys(Algorithm::EdDSA, "ed25519_private.pem", "ed25519_public.pem").unwrap();
+        round_trip(&keys);
+
+        let jwks = keys.jwks();
+        assert!(matches!(
+            &jwks.keys[0].algorithm,
+            AlgorithmParameters::OctetKeyPair(okp) if okp.curve == EllipticCurve::Ed25519
+        ));
+    }
 
-    Ok(token_data.claims)
+    #[test]
+    fn rejects_a_public_key_from_another_pair() {
+        let Err(AppError::Internal(message)) = keys(Algorithm::ES256, "ec_private.pem", "ec_other_public.pem") else {
+            panic!("a mismatched key pair was loaded");
+        };
+        assert_eq!(message, "JWT public key does not match the private key");
+    }
 }
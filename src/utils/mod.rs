@@ -1,2 +1,6 @@
 pub mod hashing;
 pub mod jwt;
+pub mod token;
+pub mod session;
+pub mod totp;
+pub mod webauthn;
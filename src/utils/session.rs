@@ -0,0 +1,92 @@
+use axum_extra::extract::cookie::{Cookie, CookieJar};
+use std::time::Duration;
+use crate::config::SessionConfig;
+use crate::utils::token;
+
+/// Header that must echo the CSRF cookie on cookie-authenticated writes.
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+// Only the endpoints that rotate or end a session ever see the refresh token
+const REFRESH_COOKIE_PATH: &str = "/api/auth";
+
+/// Sets the session cookies for freshly issued tokens, plus a new CSRF
+/// token that scripts can read. A no-op unless session cookies are enabled.
+pub fn add(config: &SessionConfig, jar: CookieJar, access_token: &str, refresh_token: &str) -> CookieJar {
+    if !config.enabled {
+        return jar;
+    }
+
+    let mut csrf = cookie(config, csrf_cookie_name(config), token::generate_opaque_token(), "/");
+    csrf.set_http_only(false);
+
+    jar.add(cookie(config, config.cookie_name.clone(), access_token.to_string(), "/"))
+        .add(cookie(config, refresh_cookie_name(config), refresh_token.to_string(), REFRESH_COOKIE_PATH))
+        .add(csrf)
+}
+
+/// Expires the session and CSRF cookies.
+pub fn remove(config: &SessionConfig, jar: CookieJar) -> CookieJar {
+    if !config.enabled {
+        return jar;
+    }
+
+    jar.remove(removal(config, config.cookie_name.clone(), "/"))
+        .remove(removal(config, refresh_cookie_name(config), REFRESH_COOKIE_PATH))
+        .remove(removal(config, csrf_cookie_name(config), "/"))
+}
+
+pub fn csrf_token(config: &SessionConfig, jar: &CookieJar) -> Option<String> {
+    jar.get(&csrf_cookie_name(config)).map(|c| c.value().to_string())
+}
+
+pub fn access_token(config: &SessionConfig, jar: &CookieJar) -> Option<String> {
+    config
+        .enabled
+        .then(|| jar.get(&config.cookie_name))
+        .flatten()
+        .map(|c| c.value().to_string())
+}
+
+pub fn refresh_token(config: &SessionConfig, jar: &CookieJar) -> Option<String> {
+    config
+        .enabled
+        .then(|| jar.get(&refresh_cookie_name(config)))
+        .flatten()
+        .map(|c| c.value().to_string())
+}
+
+fn refresh_cookie_name(config: &SessionConfig) -> String {
+    format!("{}_refresh", config.cookie_name)
+}
+
+fn csrf_cookie_name(config: &SessionConfig) -> String {
+    format!("{}_csrf", config.cookie_name)
+}
+
+fn cookie(config: &SessionConfig, name: String, value: String, path: &'static str) -> Cookie<'static> {
+    let mut cookie = Cookie::build((name, value))
+        .path(path)
+        .http_only(true)
+        .secure(config.secure)
+        .same_site(config.same_site);
+
+    if let Some(domain) = &config.domain {
+        cookie = cookie.domain(domain.clone());
+    }
+    if let Some(max_age) = config.max_age {
+        cookie = cookie.max_age(Duration::from_secs(max_age).try_into().unwrap_or_default());
+    }
+
+    cookie.build()
+}
+
+// Removal only matches a cookie with the same path and domain
+fn removal(config: &SessionConfig, name: String, path: &'static str) -> Cookie<'static> {
+    let mut cookie = Cookie::build(name).path(path);
+
+    if let Some(domain) = &config.domain {
+        cookie = cookie.domain(domain.clone());
+    }
+
+    cookie.build()
+}
@@ -0,0 +1,107 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use reqwest::Url;
+use sha1::Sha1;
+
+// RFC 6238 defaults, the only parameters every authenticator app supports
+const DIGITS: u32 = 6;
+const PERIOD: i64 = 30;
+// Accept codes from one step either side to absorb clock drift
+const SKEW: i64 = 1;
+
+/// Generates a 160-bit secret (RFC 4226 §4), base32-encoded as authenticator
+/// apps expect it.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    BASE32_NOPAD.encode(&bytes)
+}
+
+/// The `otpauth://` URI authenticator apps import, usually from a QR code.
+pub fn provisioning_uri(issuer: &str, account: &str, secret: &str) -> String {
+    let mut url = Url::parse("otpauth://totp/").expect("static URL is valid");
+    url.path_segments_mut()
+        .expect("otpauth URL has a path")
+        .pop_if_empty()
+        .push(&format!("{}:{}", issuer, account));
+    url.query_pairs_mut()
+        .append_pair("secret", secret)
+        .append_pair("issuer", issuer)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &DIGITS.to_string())
+        .append_pair("period", &PERIOD.to_string());
+
+    // Some apps show a form-encoded `+` literally; spaces must be `%20`
+    let query = url.query().unwrap_or_default().replace('+', "%20");
+    url.set_query(Some(&query));
+
+    url.to_string()
+}
+
+/// Checks `code` against the steps around `now` (a Unix timestamp) and
+/// returns the matching step. Callers must reject steps at or before the
+/// last one used so a code can't be replayed.
+pub fn verify(secret: &str, code: &str, now: i64) -> Option<i64> {
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let current = now / PERIOD;
+    (current - SKEW..=current + SKEW).find(|&step| hotp(&key, step as u64) == code)
+}
+
+/// RFC 4226 §5.3 truncation of HMAC-SHA1 over the counter.
+fn hotp(key: &[u8], counter: u64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset], digest[offset + 1], digest[offset + 2], digest[offset + 3]])
+        & 0x7fff_ffff;
+
+    format!("{:0width$}", binary % 10u32.pow(DIGITS), width = DIGITS as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "12345678901234567890", the SHA1 seed of RFC 6238 Appendix B
+    const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn rfc_6238_sha1_vectors() {
+        // The appendix lists 8-digit codes; 6-digit codes are their last digits
+        let vectors = [
+            (59, "94287082"),
+            (1111111109, "07081804"),
+            (1111111111, "14050471"),
+            (1234567890, "89005924"),
+            (2000000000, "69279037"),
+            (20000000000, "65353130"),
+        ];
+
+        for (time, code) in vectors {
+            assert_eq!(verify(SECRET, &code[2..], time), Some(time / PERIOD), "T = {}", time);
+        }
+    }
+
+    #[test]
+    fn accepts_one_step_of_drift() {
+        assert_eq!(verify(SECRET, "287082", 59 + PERIOD), Some(1));
+        assert_eq!(verify(SECRET, "287082", 59 - PERIOD), Some(1));
+        assert_eq!(verify(SECRET, "287082", 59 + 2 * PERIOD), None);
+    }
+
+    #[test]
+    fn rejects_malformed_codes() {
+        assert_eq!(verify(SECRET, "28708", 59), None);
+        assert_eq!(verify(SECRET, "2870820", 59), None);
+        assert_eq!(verify(SECRET, "28708a", 59), None);
+        assert_eq!(verify("not base32!", "287082", 59), None);
+    }
+}
@@ -0,0 +1,505 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use jsonwebtoken::{Algorithm, DecodingKey, crypto};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::config::WebAuthnConfig;
+use crate::error::AppError;
+use crate::utils::jwt::{der_element, unsigned};
+
+// COSE algorithm identifiers (RFC 9053) we accept, in order of preference
+pub const ES256: i64 = -7;
+pub const EDDSA: i64 = -8;
+pub const RS256: i64 = -257;
+
+// Authenticator data flags (WebAuthn §6.1)
+const USER_PRESENT: u8 = 0x01;
+const USER_VERIFIED: u8 = 0x04;
+const ATTESTED_CREDENTIAL: u8 = 0x40;
+
+/// Options for `navigator.credentials.create()`, in the JSON form accepted
+/// by `PublicKeyCredential.parseCreationOptionsFromJSON()`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreationOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: UserEntity,
+    pub pub_key_cred_params: Vec<CredentialParameters>,
+    pub timeout: u64,
+    pub attestation: &'static str,
+    pub authenticator_selection: AuthenticatorSelection,
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+}
+
+/// Options for `navigator.credentials.get()`, in the JSON form accepted by
+/// `PublicKeyCredential.parseRequestOptionsFromJSON()`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub timeout: u64,
+    pub user_verification: &'static str,
+    pub allow_credentials: Vec<CredentialDescriptor>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserEntity {
+    /// The user handle, base64url
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CredentialParameters {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub alg: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: &'static str,
+    pub user_verification: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Credential id, base64url
+    pub id: String,
+}
+
+/// The result of `navigator.credentials.create()` as produced by
+/// `PublicKeyCredential.toJSON()`; binary fields are base64url.
+#[derive(Debug, Deserialize)]
+pub struct RegistrationCredential {
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+/// The result of `navigator.credentials.get()` as produced by
+/// `PublicKeyCredential.toJSON()`; binary fields are base64url.
+#[derive(Debug, Deserialize)]
+pub struct AuthenticationCredential {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(rename = "userHandle")]
+    pub user_handle: Option<String>,
+}
+
+/// A credential that passed registration, ready to be stored.
+#[derive(Debug)]
+pub struct VerifiedRegistration {
+    /// Credential id, base64url
+    pub credential_id: String,
+    /// COSE_Key bytes exactly as the authenticator sent them
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+#[derive(Debug)]
+pub struct VerifiedAssertion {
+    pub sign_count: u32,
+    pub user_verified: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+}
+
+struct AuthenticatorData<'a> {
+    rp_id_hash: &'a [u8],
+    flags: u8,
+    sign_count: u32,
+    // (credential id, COSE_Key bytes) when the AT flag is set
+    attested_credential: Option<(&'a [u8], &'a [u8])>,
+}
+
+pub fn creation_options(
+    config: &WebAuthnConfig,
+    challenge: String,
+    user_handle: &[u8],
+    name: &str,
+    display_name: &str,
+    exclude_credentials: Vec<String>,
+    timeout_ms: u64,
+) -> CreationOptions {
+    CreationOptions {
+        challenge,
+        rp: RelyingParty {
+            id: config.rp_id.clone(),
+            name: config.rp_name.clone(),
+        },
+        user: UserEntity {
+            id: URL_SAFE_NO_PAD.encode(user_handle),
+            name: name.to_string(),
+            display_name: display_name.to_string(),
+        },
+        pub_key_cred_params: [ES256, EDDSA, RS256]
+            .into_iter()
+            .map(|alg| CredentialParameters { kind: "public-key", alg })
+            .collect(),
+        timeout: timeout_ms,
+        // We don't check attestation statements, so don't ask for them
+        attestation: "none",
+        authenticator_selection: AuthenticatorSelection {
+            resident_key: "preferred",
+            user_verification: "preferred",
+        },
+        exclude_credentials: descriptors(exclude_credentials),
+    }
+}
+
+/// `allow_credentials` is empty for passkey login, where the authenticator
+/// offers whichever discoverable credential the user picks.
+pub fn request_options(
+    config: &WebAuthnConfig,
+    challenge: String,
+    allow_credentials: Vec<String>,
+    user_verification: &'static str,
+    timeout_ms: u64,
+) -> RequestOptions {
+    RequestOptions {
+        challenge,
+        rp_id: config.rp_id.clone(),
+        timeout: timeout_ms,
+        user_verification,
+        allow_credentials: descriptors(allow_credentials),
+    }
+}
+
+fn descriptors(credential_ids: Vec<String>) -> Vec<CredentialDescriptor> {
+    credential_ids
+        .into_iter()
+        .map(|id| CredentialDescriptor { kind: "public-key", id })
+        .collect()
+}
+
+impl RegistrationCredential {
+    /// The challenge the client claims to answer, used to find the pending
+    /// ceremony. Verification checks it again.
+    pub fn challenge(&self) -> Option<String> {
+        client_challenge(&self.response.client_data_json)
+    }
+}
+
+impl AuthenticationCredential {
+    pub fn challenge(&self) -> Option<String> {
+        client_challenge(&self.response.client_data_json)
+    }
+}
+
+fn client_challenge(client_data_json: &str) -> Option<String> {
+    let bytes = decode(client_data_json).ok()?;
+    serde_json::from_slice::<ClientData>(&bytes).ok().map(|c| c.challenge)
+}
+
+/// Registration ceremony checks (WebAuthn §7.1) for "none" attestation:
+/// the attestation statement is not verified, so the key is trusted as the
+/// user's without any claim about the authenticator's make.
+pub fn verify_registration(
+    config: &WebAuthnConfig,
+    credential: &RegistrationCredential,
+    challenge: &str,
+) -> Result<VerifiedRegistration, AppError> {
+    check_registration(config, credential, challenge)
+        .map_err(|reason| AppError::BadRequest(format!("Invalid passkey registration: {}", reason)))
+}
+
+fn check_registration(
+    config: &WebAuthnConfig,
+    credential: &RegistrationCredential,
+    challenge: &str,
+) -> Result<VerifiedRegistration, &'static str> {
+    let client_data_json = decode(&credential.response.client_data_json)?;
+    check_client_data(config, &client_data_json, "webauthn.create", challenge)?;
+
+    let attestation_object = decode(&credential.response.attestation_object)?;
+    let attestation: Value = ciborium::from_reader(attestation_object.as_slice())
+        .map_err(|_| "malformed attestation object")?;
+    let auth_data = map_entry(&attestation, &Value::Text("authData".to_string()))
+        .and_then(Value::as_bytes)
+        .ok_or("attestation object without authenticator data")?;
+
+    let auth_data = parse_authenticator_data(auth_data)?;
+    check_authenticator_data(config, &auth_data, false)?;
+
+    let (credential_id, public_key) = auth_data
+        .attested_credential
+        .ok_or("no attested credential")?;
+    if URL_SAFE_NO_PAD.encode(credential_id) != credential.id {
+        return Err("credential id mismatch");
+    }
+
+    // Refuse keys we couldn't verify assertions with later
+    decoding_key(public_key)?;
+
+    Ok(VerifiedRegistration {
+        credential_id: credential.id.clone(),
+        public_key: public_key.to_vec(),
+        sign_count: auth_data.sign_count,
+    })
+}
+
+/// Authentication ceremony checks (WebAuthn §7.2) against the stored
+/// public key. `user_handle`, when given, must match the handle the
+/// authenticator returns. A signature counter that fails to increase
+/// suggests a cloned authenticator and is rejected.
+pub fn verify_assertion(
+    config: &WebAuthnConfig,
+    credential: &AuthenticationCredential,
+    challenge: &str,
+    public_key: &[u8],
+    stored_sign_count: u32,
+    user_handle: &[u8],
+    require_user_verification: bool,
+) -> Result<VerifiedAssertion, AppError> {
+    check_assertion(
+        config,
+        credential,
+        challenge,
+        public_key,
+        stored_sign_count,
+        user_handle,
+        require_user_verification,
+    )
+    .map_err(|reason| {
+        tracing::warn!("Rejected WebAuthn assertion for credential {}: {}", credential.id, reason);
+        AppError::Unauthorized
+    })
+}
+
+fn check_assertion(
+    config: &WebAuthnConfig,
+    credential: &AuthenticationCredential,
+    challenge: &str,
+    public_key: &[u8],
+    stored_sign_count: u32,
+    user_handle: &[u8],
+    require_user_verification: bool,
+) -> Result<VerifiedAssertion, &'static str> {
+    if let Some(returned) = &credential.response.user_handle
+        && decode(returned)? != user_handle
+    {
+        return Err("user handle mismatch");
+    }
+
+    let client_data_json = decode(&credential.response.client_data_json)?;
+    check_client_data(config, &client_data_json, "webauthn.get", challenge)?;
+
+    let raw_auth_data = decode(&credential.response.authenticator_data)?;
+    let auth_data = parse_authenticator_data(&raw_auth_data)?;
+    check_authenticator_data(config, &auth_data, require_user_verification)?;
+
+    // The signature covers authenticatorData || SHA-256(clientDataJSON)
+    let mut message = raw_auth_data.clone();
+    message.extend_from_slice(&Sha256::digest(&client_data_json));
+
+    let (algorithm, key) = decoding_key(public_key)?;
+    let signature = decode(&credential.response.signature)?;
+    let signature = match algorithm {
+        // WebAuthn ECDSA signatures are DER; JOSE verifiers take r || s
+        Algorithm::ES256 => ecdsa_raw_signature(&signature, 32).ok_or("malformed signature")?,
+        _ => signature,
+    };
+
+    let valid = crypto::verify(&URL_SAFE_NO_PAD.encode(&signature), &message, &key, algorithm)
+        .map_err(|_| "signature verification failed")?;
+    if !valid {
+        return Err("invalid signature");
+    }
+
+    // Authenticators without a counter always report zero
+    if (auth_data.sign_count != 0 || stored_sign_count != 0) && auth_data.sign_count <= stored_sign_count {
+        return Err("signature counter did not increase");
+    }
+
+    Ok(VerifiedAssertion {
+        sign_count: auth_data.sign_count,
+        user_verified: auth_data.flags & USER_VERIFIED != 0,
+    })
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, &'static str> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| "invalid base64url")
+}
+
+fn check_client_data(
+    config: &WebAuthnConfig,
+    client_data_json: &[u8],
+    kind: &str,
+    challenge: &str,
+) -> Result<(), &'static str> {
+    let client_data: ClientData = serde_json::from_slice(client_data_json)
+        .map_err(|_| "malformed client data")?;
+
+    if client_data.kind != kind {
+        return Err("wrong ceremony type");
+    }
+    if client_data.challenge != challenge {
+        return Err("challenge mismatch");
+    }
+    if !config.origins.contains(&client_data.origin) {
+        return Err("origin not allowed");
+    }
+
+    Ok(())
+}
+
+fn check_authenticator_data(
+    config: &WebAuthnConfig,
+    auth_data: &AuthenticatorData,
+    require_user_verification: bool,
+) -> Result<(), &'static str> {
+    if auth_data.rp_id_hash != &Sha256::digest(config.rp_id.as_bytes())[..] {
+        return Err("credential is for another relying party");
+    }
+    if auth_data.flags & USER_PRESENT == 0 {
+        return Err("user not present");
+    }
+    if require_user_verification && auth_data.flags & USER_VERIFIED == 0 {
+        return Err("user not verified");
+    }
+
+    Ok(())
+}
+
+/// Splits authenticator data (WebAuthn §6.1): rpIdHash (32), flags (1),
+/// signCount (4), then the attested credential data if the AT flag is set.
+fn parse_authenticator_data(data: &[u8]) -> Result<AuthenticatorData<'_>, &'static str> {
+    if data.len() < 37 {
+        return Err("authenticator data too short");
+    }
+    let (rp_id_hash, rest) = data.split_at(32);
+    let flags = rest[0];
+    let sign_count = u32::from_be_bytes([rest[1], rest[2], rest[3], rest[4]]);
+    let rest = &rest[5..];
+
+    let attested_credential = if flags & ATTESTED_CREDENTIAL != 0 {
+        // aaguid (16), credentialIdLength (2), credentialId, credentialPublicKey
+        if rest.len() < 18 {
+            return Err("attested credential data too short");
+        }
+        let id_length = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+        let rest = &rest[18..];
+        if rest.len() < id_length {
+            return Err("attested credential data too short");
+        }
+        let (credential_id, rest) = rest.split_at(id_length);
+
+        // The key is one CBOR item; extensions may follow it
+        let mut reader = rest;
+        let _: Value = ciborium::from_reader(&mut reader).map_err(|_| "malformed credential public key")?;
+        let public_key = &rest[..rest.len() - reader.len()];
+
+        Some((credential_id, public_key))
+    } else {
+        None
+    };
+
+    Ok(AuthenticatorData {
+        rp_id_hash,
+        flags,
+        sign_count,
+        attested_credential,
+    })
+}
+
+/// Reads a COSE_Key (RFC 9052 §7) for one of the algorithms we offer.
+fn decoding_key(cose_key: &[u8]) -> Result<(Algorithm, DecodingKey), &'static str> {
+    let key: Value = ciborium::from_reader(cose_key).map_err(|_| "malformed credential public key")?;
+    let int = |label: i64| map_entry(&key, &Value::Integer(label.into())).and_then(Value::as_integer).map(i128::from);
+    let bytes = |label: i64| map_entry(&key, &Value::Integer(label.into())).and_then(Value::as_bytes);
+
+    // kty (1), alg (3); the meaning of negative labels depends on kty
+    match (int(1), int(3).map(|alg| alg as i64)) {
+        // EC2, crv P-256 (1)
+        (Some(2), Some(ES256)) if int(-1) == Some(1) => {
+            let (x, y) = bytes(-2).zip(bytes(-3)).ok_or("incomplete EC key")?;
+            if x.len() != 32 || y.len() != 32 {
+                return Err("incomplete EC key");
+            }
+            let key = DecodingKey::from_ec_components(&URL_SAFE_NO_PAD.encode(x), &URL_SAFE_NO_PAD.encode(y))
+                .map_err(|_| "invalid EC key")?;
+            Ok((Algorithm::ES256, key))
+        }
+        // OKP, crv Ed25519 (6)
+        (Some(1), Some(EDDSA)) if int(-1) == Some(6) => {
+            let x = bytes(-2).filter(|x| x.len() == 32).ok_or("incomplete Ed25519 key")?;
+            let key = DecodingKey::from_ed_components(&URL_SAFE_NO_PAD.encode(x))
+                .map_err(|_| "invalid Ed25519 key")?;
+            Ok((Algorithm::EdDSA, key))
+        }
+        // RSA
+        (Some(3), Some(RS256)) => {
+            let (n, e) = bytes(-1).zip(bytes(-2)).ok_or("incomplete RSA key")?;
+            Ok((Algorithm::RS256, DecodingKey::from_rsa_raw_components(n, e)))
+        }
+        _ => Err("unsupported credential algorithm"),
+    }
+}
+
+fn map_entry<'a>(map: &'a Value, label: &Value) -> Option<&'a Value> {
+    map.as_map()?
+        .iter()
+        .find(|(key, _)| key == label)
+        .map(|(_, value)| value)
+}
+
+/// Converts a DER `ECDSA-Sig-Value` into fixed-width `r || s`.
+fn ecdsa_raw_signature(der: &[u8], width: usize) -> Option<Vec<u8>> {
+    let (0x30, sequence, _) = der_element(der)? else {
+        return None;
+    };
+    let (0x02, r, rest) = der_element(sequence)? else {
+        return None;
+    };
+    let (0x02, s, _) = der_element(rest)? else {
+        return None;
+    };
+
+    let mut raw = vec![0u8; width * 2];
+    for (int, offset) in [(unsigned(r), 0), (unsigned(s), width)] {
+        if int.len() > width {
+            return None;
+        }
+        raw[offset + width - int.len()..offset + width].copy_from_slice(int);
+    }
+
+    Some(raw)
+}
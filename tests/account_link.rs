@@ -0,0 +1,151 @@
+mod common;
+
+use std::time::Instant;
+use auth_session::middleware::account_link::PendingLink;
+use auth_session::models::role::UserGrants;
+use auth_session::utils::{jwt, token};
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use serde_json::json;
+use uuid::Uuid;
+
+/// What the OAuth callback leaves behind when a provider login matches an
+/// existing account by email.
+fn pending_link(app: &TestApp, user_id: &str, email: &str) -> String {
+    let link_token = token::generate_opaque_token();
+    app.state.account_links.insert(token::hash_token(&link_token), PendingLink {
+        user_id: Uuid::parse_str(user_id).unwrap(),
+        email: email.to_string(),
+        provider: "google".to_string(),
+        provider_user_id: "google-subject".to_string(),
+        email_code_hash: None,
+        created_at: Instant::now(),
+    });
+    link_token
+}
+
+async fn emailed_code(app: &TestApp, link_token: &str) -> String {
+    let res = app.post("/api/auth/link/email", json!({ "link_token": link_token })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    let email = app.mailer.sent().into_iter().rfind(|e| e.subject.starts_with("Confirm your")).unwrap();
+    email.text.split_whitespace().find(|word| word.len() >= 32).unwrap().to_string()
+}
+
+#[tokio::test]
+async fn linking_an_unverified_account_locks_out_its_registrant() {
+    let app = TestApp::new(common::config()).await;
+
+    // Someone registers the address before its owner ever signs in
+    let squatter = app
+        .post("/api/auth/register", json!({ "email": "victim@example.com", "password": "squatted", "name": "x" }))
+        .await
+        .body;
+    let user_id = squatter["user"]["id"].as_str().unwrap();
+
+    let link_token = pending_link(&app, user_id, "victim@example.com");
+
+    // The registrant's password can't confirm the link
+    let by_password = app
+        .post("/api/auth/link/confirm", json!({ "link_token": link_token, "password": "squatted" }))
+        .await;
+    assert_eq!(by_password.status, StatusCode::BAD_REQUEST, "{}", by_password.body);
+
+    let code = emailed_code(&app, &link_token).await;
+    let confirmed = app
+        .post("/api/auth/link/confirm", json!({ "link_token": link_token, "code": code }))
+        .await;
+    assert_eq!(confirmed.status, StatusCode::OK, "{}", confirmed.body);
+
+    let login = app
+        .post("/api/auth/login", json!({ "email": "victim@example.com", "password": "squatted" }))
+        .await;
+    assert!(!login.status.is_success(), "{}", login.body);
+
+    let refresh = app.post("/api/auth/refresh", json!({ "refresh_token": squatter["refresh_token"] })).await;
+    assert_eq!(refresh.status, StatusCode::UNAUTHORIZED);
+
+    // Even an access token issued within the same second
+    let squatter_bearer = format!("Bearer {}", squatter["token"].as_str().unwrap());
+    let profile = app.request(Method::GET, "/api/profile", None, &[("authorization", &squatter_bearer)]).await;
+    assert_eq!(profile.status, StatusCode::UNAUTHORIZED);
+
+    let bearer = format!("Bearer {}", confirmed.body["token"].as_str().unwrap());
+    let profile = app.request(Method::GET, "/api/profile", None, &[("authorization", &bearer)]).await;
+    assert_eq!(profile.status, StatusCode::OK);
+    assert_eq!(profile.body["email_verified"], true);
+}
+
+#[tokio::test]
+async fn verified_account_keeps_its_password() {
+    let app = TestApp::new(common::config()).await;
+    let owner = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "mine", "name": "Alice" }))
+        .await
+        .body;
+    let user_id = owner["user"]["id"].as_str().unwrap();
+    app.state.store.mark_email_verified(Uuid::parse_str(user_id).unwrap()).await.unwrap();
+
+    let link_token = pending_link(&app, user_id, "alice@example.com");
+
+    let wrong = app
+        .post("/api/auth/link/confirm", json!({ "link_token": link_token, "password": "guess" }))
+        .await;
+    assert_eq!(wrong.status, StatusCode::UNAUTHORIZED, "{}", wrong.body);
+
+    let confirmed = app
+        .post("/api/auth/link/confirm", json!({ "link_token": link_token, "password": "mine" }))
+        .await;
+    assert_eq!(confirmed.status, StatusCode::OK, "{}", confirmed.body);
+    assert_eq!(confirmed.body["user"]["id"], user_id);
+
+    let identities = app.state.identities.find_identities_by_user(Uuid::parse_str(user_id).unwrap()).await.unwrap();
+    assert!(identities.iter().any(|i| i.provider == "google" && i.provider_user_id == "google-subject"));
+
+    // The link token is single-use
+    let replay = app
+        .post("/api/auth/link/confirm", json!({ "link_token": link_token, "password": "mine" }))
+        .await;
+    assert_eq!(replay.status, StatusCode::UNAUTHORIZED, "{}", replay.body);
+
+    let login = app
+        .post("/api/auth/login", json!({ "email": "alice@example.com", "password": "mine" }))
+        .await;
+    assert_eq!(login.status, StatusCode::OK, "{}", login.body);
+}
+
+#[tokio::test]
+async fn the_last_login_method_cannot_be_unlinked() {
+    let app = TestApp::new(common::config()).await;
+
+    // Signed up through Google, then linked GitHub; no password
+    let user = app
+        .state
+        .store
+        .create_user("octocat@example.com".into(), "Octocat".into(), None, None, None)
+        .await
+        .unwrap();
+    app.state.identities.link_identity(user.id, "google", "google-subject", None).await.unwrap();
+    app.state.identities.link_identity(user.id, "github", "583231", None).await.unwrap();
+
+    let token = jwt::generate_token(&user, &UserGrants::default(), &app.state.jwt_keys, 60, None).unwrap();
+    let bearer = format!("Bearer {}", token);
+    let unlink = |provider: &str| {
+        let path = format!("/api/profile/identities/{}", provider);
+        let bearer = bearer.clone();
+        let app = &app;
+        async move { app.request(Method::DELETE, &path, None, &[("authorization", &bearer)]).await }
+    };
+
+    let res = unlink("github").await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    let res = unlink("google").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+    assert_eq!(app.state.identities.find_identities_by_user(user.id).await.unwrap().len(), 1);
+
+    // Once a password is set, the last identity can go
+    app.state.store.update_user_password(user.id, "hash".into()).await.unwrap();
+    let res = unlink("google").await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+}
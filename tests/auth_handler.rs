@@ -0,0 +1,128 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use serde_json::json;
+
+async fn register(app: &TestApp, email: &str) -> serde_json::Value {
+    let res = app
+        .post("/api/auth/register", json!({ "email": email, "password": "correct horse", "name": "Alice" }))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    res.body
+}
+
+async fn profile(app: &TestApp, token: &str) -> StatusCode {
+    let bearer = format!("Bearer {}", token);
+    app.request(Method::GET, "/api/profile", None, &[("authorization", &bearer)]).await.status
+}
+
+#[tokio::test]
+async fn register_then_login() {
+    let app = TestApp::new(common::config()).await;
+
+    let registered = register(&app, "alice@example.com").await;
+    assert_eq!(registered["user"]["email"], "alice@example.com");
+    assert_eq!(profile(&app, registered["token"].as_str().unwrap()).await, StatusCode::OK);
+
+    let duplicate = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "x", "name": "Eve" }))
+        .await;
+    assert!(!duplicate.status.is_success());
+
+    let login = app
+        .post("/api/auth/login", json!({ "email": "alice@example.com", "password": "correct horse" }))
+        .await;
+    assert_eq!(login.status, StatusCode::OK, "{}", login.body);
+    assert_eq!(login.body["user"]["id"], registered["user"]["id"]);
+
+    let wrong = app
+        .post("/api/auth/login", json!({ "email": "alice@example.com", "password": "wrong" }))
+        .await;
+    assert_eq!(wrong.status, StatusCode::UNAUTHORIZED);
+
+    let unknown = app
+        .post("/api/auth/login", json!({ "email": "bob@example.com", "password": "correct horse" }))
+        .await;
+    assert_eq!(unknown.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn closed_registration_is_refused() {
+    let mut config = common::config();
+    config.registration.open = false;
+    let app = TestApp::new(config).await;
+
+    let res = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn refresh_rotates_and_detects_reuse() {
+    let app = TestApp::new(common::config()).await;
+    let first = register(&app, "alice@example.com").await;
+    let first_refresh = first["refresh_token"].as_str().unwrap();
+
+    let second = app.post("/api/auth/refresh", json!({ "refresh_token": first_refresh })).await;
+    assert_eq!(second.status, StatusCode::OK, "{}", second.body);
+    let second_refresh = second.body["refresh_token"].as_str().unwrap();
+    assert_ne!(second_refresh, first_refresh);
+    assert_eq!(profile(&app, second.body["token"].as_str().unwrap()).await, StatusCode::OK);
+
+    // Replaying the rotated token revokes the whole family, including the
+    // token it was exchanged for
+    let replay = app.post("/api/auth/refresh", json!({ "refresh_token": first_refresh })).await;
+    assert_eq!(replay.status, StatusCode::UNAUTHORIZED);
+    let after_replay = app.post("/api/auth/refresh", json!({ "refresh_token": second_refresh })).await;
+    assert_eq!(after_replay.status, StatusCode::UNAUTHORIZED);
+
+    let garbage = app.post("/api/auth/refresh", json!({ "refresh_token": "nope" })).await;
+    assert_eq!(garbage.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn logout_revokes_access_and_refresh_tokens() {
+    let app = TestApp::new(common::config()).await;
+    let session = register(&app, "alice@example.com").await;
+    let token = session["token"].as_str().unwrap();
+    let bearer = format!("Bearer {}", token);
+
+    let unauthenticated = app.post("/api/auth/logout", json!({})).await;
+    assert_eq!(unauthenticated.status, StatusCode::UNAUTHORIZED);
+
+    let res = app
+        .request(
+            Method::POST,
+            "/api/auth/logout",
+            Some(json!({ "refresh_token": session["refresh_token"] })),
+            &[("authorization", &bearer)],
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    assert_eq!(profile(&app, token).await, StatusCode::UNAUTHORIZED);
+    let refresh = app.post("/api/auth/refresh", json!({ "refresh_token": session["refresh_token"] })).await;
+    assert_eq!(refresh.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn logout_all_ends_every_session() {
+    let app = TestApp::new(common::config()).await;
+    let first = register(&app, "alice@example.com").await;
+    let second = app
+        .post("/api/auth/login", json!({ "email": "alice@example.com", "password": "correct horse" }))
+        .await
+        .body;
+
+    let bearer = format!("Bearer {}", second["token"].as_str().unwrap());
+    let res = app.request(Method::POST, "/api/auth/logout-all", None, &[("authorization", &bearer)]).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    for session in [&first, &second] {
+        let refresh = app.post("/api/auth/refresh", json!({ "refresh_token": session["refresh_token"] })).await;
+        assert_eq!(refresh.status, StatusCode::UNAUTHORIZED);
+    }
+    assert_eq!(profile(&app, second["token"].as_str().unwrap()).await, StatusCode::UNAUTHORIZED);
+}
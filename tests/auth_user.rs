@@ -0,0 +1,47 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use serde_json::json;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn protected_handlers_need_a_valid_bearer_token() {
+    let app = TestApp::new(common::config()).await;
+    let registered = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await
+        .body;
+    let bearer = format!("Bearer {}", registered["token"].as_str().unwrap());
+
+    let missing = app.request(Method::GET, "/api/profile", None, &[]).await;
+    assert_eq!(missing.status, StatusCode::UNAUTHORIZED);
+
+    for header in ["Bearer not-a-jwt", "Basic YWxpY2U6cHc=", registered["token"].as_str().unwrap()] {
+        let res = app.request(Method::GET, "/api/profile", None, &[("authorization", header)]).await;
+        assert_eq!(res.status, StatusCode::UNAUTHORIZED, "{}", header);
+    }
+
+    let profile = app.request(Method::GET, "/api/profile", None, &[("authorization", &bearer)]).await;
+    assert_eq!(profile.status, StatusCode::OK, "{}", profile.body);
+    assert_eq!(profile.body["email"], "alice@example.com");
+
+    let identities = app.request(Method::GET, "/api/profile/identities", None, &[("authorization", &bearer)]).await;
+    assert_eq!(identities.status, StatusCode::OK, "{}", identities.body);
+}
+
+#[tokio::test]
+async fn tokens_of_deleted_users_are_rejected() {
+    let app = TestApp::new(common::config()).await;
+    let registered = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await
+        .body;
+    let bearer = format!("Bearer {}", registered["token"].as_str().unwrap());
+    let user_id = Uuid::parse_str(registered["user"]["id"].as_str().unwrap()).unwrap();
+
+    app.state.store.delete_user(user_id).await.unwrap();
+
+    let profile = app.request(Method::GET, "/api/profile", None, &[("authorization", &bearer)]).await;
+    assert_eq!(profile.status, StatusCode::UNAUTHORIZED, "{}", profile.body);
+}
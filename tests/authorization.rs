@@ -0,0 +1,99 @@
+mod common;
+
+use auth_session::middleware::{auth_middleware, authorization::RequireRole};
+use axum::{
+    Router,
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+    routing::get,
+};
+use common::TestApp;
+use serde_json::json;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+/// Registers a user and returns their id and refresh token, plus an access
+/// token issued after granting `roles`.
+async fn user(app: &TestApp, email: &str, roles: &[&str]) -> (Uuid, String, String) {
+    let registered = app
+        .post("/api/auth/register", json!({ "email": email, "password": "pw", "name": "User" }))
+        .await
+        .body;
+    let user_id = Uuid::parse_str(registered["user"]["id"].as_str().unwrap()).unwrap();
+    for role in roles {
+        app.state.roles.assign_role(user_id, role).await.unwrap();
+    }
+
+    let login = app.post("/api/auth/login", json!({ "email": email, "password": "pw" })).await.body;
+    (
+        user_id,
+        login["token"].as_str().unwrap().to_string(),
+        login["refresh_token"].as_str().unwrap().to_string(),
+    )
+}
+
+async fn get_as(app: &TestApp, path: &str, token: Option<&str>) -> StatusCode {
+    let bearer = token.map(|t| format!("Bearer {}", t));
+    let headers: Vec<(&str, &str)> = bearer.iter().map(|b| ("authorization", b.as_str())).collect();
+    app.request(Method::GET, path, None, &headers).await.status
+}
+
+#[tokio::test]
+async fn require_permission_tells_missing_from_insufficient_credentials() {
+    let app = TestApp::new(common::config()).await;
+    let (user_id, user_token, _) = user(&app, "user@example.com", &[]).await;
+    let (_, admin_token, _) = user(&app, "admin@example.com", &["admin"]).await;
+    let path = format!("/api/admin/users/{}/roles", user_id);
+
+    assert_eq!(get_as(&app, &path, None).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(get_as(&app, &path, Some("not-a-token")).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(get_as(&app, &path, Some(&user_token)).await, StatusCode::FORBIDDEN);
+    assert_eq!(get_as(&app, &path, Some(&admin_token)).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn require_role_tells_missing_from_insufficient_credentials() {
+    let app = TestApp::new(common::config()).await;
+    let (_, user_token, _) = user(&app, "user@example.com", &[]).await;
+    let (_, admin_token, _) = user(&app, "admin@example.com", &["admin"]).await;
+
+    let router = Router::new()
+        .route("/admin-only", get(|| async { "ok" }).route_layer(RequireRole("admin")))
+        .layer(axum::middleware::from_fn_with_state(app.state.clone(), auth_middleware::identify));
+    let status = |token: Option<&str>| {
+        let mut request = Request::builder().uri("/admin-only");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let router = router.clone();
+        async move { router.oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status() }
+    };
+
+    assert_eq!(status(None).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status(Some(&user_token)).await, StatusCode::FORBIDDEN);
+    assert_eq!(status(Some(&admin_token)).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn revoking_a_role_ends_tokens_that_carry_it() {
+    let app = TestApp::new(common::config()).await;
+    let (_, admin_token, _) = user(&app, "admin@example.com", &["admin"]).await;
+    let (demoted_id, demoted_token, refresh_token) = user(&app, "demoted@example.com", &["admin"]).await;
+    let path = format!("/api/admin/users/{}/roles", demoted_id);
+    assert_eq!(get_as(&app, &path, Some(&demoted_token)).await, StatusCode::OK);
+
+    let bearer = format!("Bearer {}", admin_token);
+    let res = app
+        .request(Method::DELETE, &format!("{}/admin", path), None, &[("authorization", &bearer)])
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    assert_eq!(get_as(&app, &path, Some(&demoted_token)).await, StatusCode::UNAUTHORIZED);
+
+    // Refreshing still works, without the role
+    let refreshed = app.post("/api/auth/refresh", json!({ "refresh_token": refresh_token })).await;
+    assert_eq!(refreshed.status, StatusCode::OK, "{}", refreshed.body);
+    let token = refreshed.body["token"].as_str().unwrap();
+    assert_eq!(get_as(&app, &path, Some(token)).await, StatusCode::FORBIDDEN);
+    assert_eq!(get_as(&app, "/api/profile", Some(token)).await, StatusCode::OK);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use auth_session::models::role::UserGrants;
+use auth_session::utils::jwt;
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use jsonwebtoken::{EncodingKey, Header};
+use serde_json::json;
+use uuid::Uuid;
+
+async fn get(app: &TestApp, path: &str, token: &str) -> StatusCode {
+    let bearer = format!("Bearer {}", token);
+    app.request(Method::GET, path, None, &[("authorization", &bearer)]).await.status
+}
+
+#[tokio::test]
+async fn client_tokens_are_limited_to_userinfo() {
+    let app = TestApp::new(common::config()).await;
+    let registered = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await
+        .body;
+    let user_id = Uuid::parse_str(registered["user"]["id"].as_str().unwrap()).unwrap();
+    let user = app.state.store.find_user_by_id(user_id).await.unwrap().unwrap();
+
+    let client_token = jwt::generate_token(&user, &UserGrants::default(), &app.state.jwt_keys, 60, Some("my-app")).unwrap();
+    let claims = jwt::verify_client_token(&client_token, &app.state.jwt_keys).unwrap();
+    assert_eq!(claims.aud, "my-app");
+    assert_eq!(claims.client_id.as_deref(), Some("my-app"));
+
+    assert_eq!(get(&app, "/userinfo", &client_token).await, StatusCode::OK);
+    assert_eq!(get(&app, "/api/profile", &client_token).await, StatusCode::UNAUTHORIZED);
+
+    // First-party tokens work on both
+    let token = registered["token"].as_str().unwrap();
+    assert_eq!(get(&app, "/userinfo", token).await, StatusCode::OK);
+    assert_eq!(get(&app, "/api/profile", token).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn a_client_named_like_our_audience_gets_no_api_access() {
+    let app = TestApp::new(common::config()).await;
+    let registered = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await
+        .body;
+    let user_id = Uuid::parse_str(registered["user"]["id"].as_str().unwrap()).unwrap();
+    let user = app.state.store.find_user_by_id(user_id).await.unwrap().unwrap();
+
+    let client_token =
+        jwt::generate_token(&user, &UserGrants::default(), &app.state.jwt_keys, 60, Some("auth_session")).unwrap();
+    assert_eq!(get(&app, "/userinfo", &client_token).await, StatusCode::OK);
+    assert_eq!(get(&app, "/api/profile", &client_token).await, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn userinfo_rejects_foreign_audiences() {
+    let app = TestApp::new(common::config()).await;
+    let registered = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await
+        .body;
+
+    // Signed with our key, but meant for another service
+    let now = chrono::Utc::now().timestamp();
+    let claims = json!({
+        "sub": registered["user"]["id"],
+        "email": "alice@example.com",
+        "name": "Alice",
+        "exp": now + 60,
+        "iat": now,
+        "nbf": now,
+        "jti": Uuid::new_v4().to_string(),
+        "iss": "http://localhost:8000",
+        "aud": "billing-service",
+    });
+    let token = jsonwebtoken::encode(&Header::default(), &claims, &EncodingKey::from_secret(b"test-secret")).unwrap();
+
+    assert_eq!(get(&app, "/userinfo", &token).await, StatusCode::UNAUTHORIZED);
+}
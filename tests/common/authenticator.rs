@@ -0,0 +1,147 @@
+//! A software ES256 authenticator, so every field the browser and
+//! authenticator sign can be tampered with.
+
+use auth_session::config::WebAuthnConfig;
+use auth_session::utils::webauthn::{AuthenticationCredential, RegistrationCredential};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use ciborium::Value;
+use p256::ecdsa::{Signature, SigningKey, signature::Signer};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+pub const RP_ID: &str = "localhost";
+pub const ORIGIN: &str = "http://localhost:8000";
+pub const USER_HANDLE: &[u8] = b"user-handle";
+
+// Authenticator data flags (WebAuthn §6.1)
+pub const USER_PRESENT: u8 = 0x01;
+pub const USER_VERIFIED: u8 = 0x04;
+pub const ATTESTED_CREDENTIAL: u8 = 0x40;
+
+pub fn webauthn_config() -> WebAuthnConfig {
+    WebAuthnConfig {
+        rp_id: RP_ID.into(),
+        rp_name: "auth_session".into(),
+        origins: vec![ORIGIN.into()],
+    }
+}
+
+pub fn cbor(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+/// What the browser wraps around a challenge. Tests override fields to
+/// play a phishing page or a replayed response.
+pub struct ClientData<'a> {
+    pub kind: &'a str,
+    pub challenge: &'a str,
+    pub origin: &'a str,
+}
+
+impl ClientData<'_> {
+    pub fn encode(&self) -> Vec<u8> {
+        json!({ "type": self.kind, "challenge": self.challenge, "origin": self.origin })
+            .to_string()
+            .into_bytes()
+    }
+}
+
+pub struct SoftAuthenticator {
+    key: SigningKey,
+    pub credential_id: Vec<u8>,
+    /// Sent back as `userHandle`; the server's handle is the user id
+    pub user_handle: Vec<u8>,
+}
+
+impl SoftAuthenticator {
+    pub fn new() -> Self {
+        Self {
+            key: SigningKey::random(&mut rand::thread_rng()),
+            credential_id: vec![7; 16],
+            user_handle: USER_HANDLE.to_vec(),
+        }
+    }
+
+    /// The credential public key as a COSE_Key (RFC 9053 §7.1.1).
+    pub fn cose_key(&self) -> Vec<u8> {
+        let point = self.key.verifying_key().to_encoded_point(false);
+        cbor(&Value::Map(vec![
+            (Value::Integer(1.into()), Value::Integer(2.into())),
+            (Value::Integer(3.into()), Value::Integer((-7).into())),
+            (Value::Integer((-1).into()), Value::Integer(1.into())),
+            (Value::Integer((-2).into()), Value::Bytes(point.x().unwrap().to_vec())),
+            (Value::Integer((-3).into()), Value::Bytes(point.y().unwrap().to_vec())),
+        ]))
+    }
+
+    pub fn authenticator_data(rp_id: &str, flags: u8, sign_count: u32) -> Vec<u8> {
+        let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend(sign_count.to_be_bytes());
+        data
+    }
+
+    pub fn register(&self, challenge: &str) -> RegistrationCredential {
+        let mut auth_data = Self::authenticator_data(RP_ID, USER_PRESENT | USER_VERIFIED | ATTESTED_CREDENTIAL, 0);
+        auth_data.extend([0; 16]);
+        auth_data.extend((self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend(&self.credential_id);
+        auth_data.extend(self.cose_key());
+
+        let attestation = cbor(&Value::Map(vec![
+            (Value::Text("fmt".into()), Value::Text("none".into())),
+            (Value::Text("attStmt".into()), Value::Map(vec![])),
+            (Value::Text("authData".into()), Value::Bytes(auth_data)),
+        ]));
+        let client_data = ClientData { kind: "webauthn.create", challenge, origin: ORIGIN }.encode();
+
+        registration(&self.credential_id, &client_data, &attestation)
+    }
+
+    pub fn sign(&self, auth_data: Vec<u8>, client_data: &ClientData) -> AuthenticationCredential {
+        serde_json::from_value(self.sign_json(auth_data, client_data)).unwrap()
+    }
+
+    /// The assertion as the browser posts it.
+    pub fn sign_json(&self, auth_data: Vec<u8>, client_data: &ClientData) -> serde_json::Value {
+        let client_data = client_data.encode();
+        let mut message = auth_data.clone();
+        message.extend(Sha256::digest(&client_data));
+        let signature: Signature = self.key.sign(&message);
+
+        json!({
+            "id": URL_SAFE_NO_PAD.encode(&self.credential_id),
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(&client_data),
+                "authenticatorData": URL_SAFE_NO_PAD.encode(&auth_data),
+                "signature": URL_SAFE_NO_PAD.encode(signature.to_der().as_bytes()),
+                "userHandle": URL_SAFE_NO_PAD.encode(&self.user_handle),
+            }
+        })
+    }
+
+    /// A well-formed assertion with user verification.
+    pub fn assert(&self, challenge: &str, sign_count: u32) -> AuthenticationCredential {
+        serde_json::from_value(self.assert_json(challenge, sign_count)).unwrap()
+    }
+
+    pub fn assert_json(&self, challenge: &str, sign_count: u32) -> serde_json::Value {
+        self.sign_json(
+            Self::authenticator_data(RP_ID, USER_PRESENT | USER_VERIFIED, sign_count),
+            &ClientData { kind: "webauthn.get", challenge, origin: ORIGIN },
+        )
+    }
+}
+
+pub fn registration(credential_id: &[u8], client_data: &[u8], attestation: &[u8]) -> RegistrationCredential {
+    serde_json::from_value(json!({
+        "id": URL_SAFE_NO_PAD.encode(credential_id),
+        "response": {
+            "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data),
+            "attestationObject": URL_SAFE_NO_PAD.encode(attestation),
+        }
+    }))
+    .unwrap()
+}
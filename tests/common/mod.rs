@@ -0,0 +1,207 @@
+//! An `AppState` backed by the in-memory `Store`, so handlers run through
+//! the full router without Postgres or a mail server.
+#![allow(dead_code)]
+
+pub mod authenticator;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use auth_session::config::*;
+use auth_session::db::Store;
+use auth_session::handlers::auth_handler::AppState;
+use auth_session::mail::{Email, MailTemplates, MemoryMailer};
+use auth_session::middleware::{
+    account_link::AccountLinkStore, authorization_code::AuthorizationCodeStore, mfa_challenge::MfaChallengeStore,
+    oauth_state::OAuthStateStore, rate_limit::RateLimiter, revocation::RevocationList,
+    webauthn_challenge::WebAuthnChallengeStore,
+};
+use auth_session::oauth::ProviderRegistry;
+use auth_session::server;
+use auth_session::utils::jwt::JwtKeys;
+use axum::{
+    Router,
+    body::{Body, to_bytes},
+    extract::ConnectInfo,
+    http::{HeaderMap, Method, Request, StatusCode, header},
+};
+use axum_extra::extract::cookie::SameSite;
+use jsonwebtoken::Algorithm;
+use tower::ServiceExt;
+
+pub fn config() -> AppConfig {
+    AppConfig {
+        server: ServerConfig { host: "127.0.0.1".into(), port: 0 },
+        database: DatabaseConfig { url: String::new(), max_connections: 1 },
+        jwt: JwtConfig {
+            secret: "test-secret".into(),
+            algorithm: Algorithm::HS256,
+            private_key_path: None,
+            public_key_path: None,
+            key_id: None,
+            expiration: 900,
+            refresh_expiration: 3600,
+            issuer: "http://localhost:8000".into(),
+            audience: "auth_session".into(),
+            allowed_audiences: vec!["auth_session".into()],
+            leeway: 0,
+        },
+        session: SessionConfig {
+            enabled: false,
+            cookie_name: "session".into(),
+            domain: None,
+            max_age: None,
+            secure: false,
+            same_site: SameSite::Lax,
+        },
+        cors: CorsConfig {
+            allowed_origins: vec![],
+            allowed_methods: vec![Method::GET, Method::POST],
+            allowed_headers: vec![header::CONTENT_TYPE],
+            allow_credentials: false,
+            max_age: 600,
+        },
+        mfa: MfaConfig { issuer: "auth_session".into() },
+        webauthn: WebAuthnConfig {
+            rp_id: "localhost".into(),
+            rp_name: "auth_session".into(),
+            origins: vec!["http://localhost:8000".into()],
+        },
+        registration: RegistrationConfig { open: true },
+        password_reset: PasswordResetConfig { expiration: 3600 },
+        email_verification: EmailVerificationConfig { required: false, expiration: 86400 },
+        magic_link: MagicLinkConfig { expiration: 900 },
+        mail: MailConfig {
+            transport: MailTransport::Log,
+            from: "AuthSession <no-reply@localhost>".into(),
+            templates_dir: "src/templates/email".into(),
+            app_url: "http://localhost:8000".into(),
+        },
+        oauth_providers: vec![],
+    }
+}
+
+pub struct TestApp {
+    pub state: AppState,
+    pub mailer: MemoryMailer,
+    router: Router,
+}
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: serde_json::Value,
+}
+
+impl TestApp {
+    pub async fn new(config: AppConfig) -> Self {
+        let store = Arc::new(Store::new());
+        let jwt_keys = JwtKeys::new(&config.jwt, store.clone()).unwrap();
+        jwt_keys.sync().await.unwrap();
+        let mailer = MemoryMailer::new();
+
+        let state = AppState {
+            jwt_keys,
+            store: store.clone(),
+            identities: store.clone(),
+            refresh_tokens: store.clone(),
+            roles: store.clone(),
+            mfa: store.clone(),
+            webauthn: store.clone(),
+            password_resets: store.clone(),
+            email_verifications: store.clone(),
+            magic_links: store.clone(),
+            mailer: Arc::new(mailer.clone()),
+            mail_templates: MailTemplates::new(&config.mail).unwrap(),
+            rate_limiter: RateLimiter::new(100, 5, 180),
+            revocations: RevocationList::new(store.clone()),
+            oauth_states: OAuthStateStore::new(600, 10_000),
+            account_links: AccountLinkStore::new(900),
+            mfa_challenges: MfaChallengeStore::new(300),
+            webauthn_challenges: WebAuthnChallengeStore::new(300, 10_000),
+            providers: ProviderRegistry::new(&config.oauth_providers),
+            oauth_clients: store,
+            authorization_codes: AuthorizationCodeStore::new(60),
+            config,
+        };
+
+        let router = server::router(state.clone());
+
+        Self { state, mailer, router }
+    }
+
+    /// The latest email whose subject starts with `subject`. Some handlers
+    /// send from a background task, so this waits for it briefly.
+    pub async fn email(&self, subject: &str) -> Email {
+        for _ in 0..100 {
+            if let Some(email) = self.mailer.sent().into_iter().rfind(|e| e.subject.starts_with(subject)) {
+                return email;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("no email with subject {:?} was sent", subject);
+    }
+
+    pub async fn post(&self, path: &str, body: serde_json::Value) -> TestResponse {
+        self.request(Method::POST, path, Some(body), &[]).await
+    }
+
+    pub async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+        headers: &[(&str, &str)],
+    ) -> TestResponse {
+        self.request_from([127, 0, 0, 1], method, path, body, headers).await
+    }
+
+    /// Like `request`, but from another client address, for rate limits.
+    pub async fn request_from(
+        &self,
+        ip: [u8; 4],
+        method: Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+        headers: &[(&str, &str)],
+    ) -> TestResponse {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(path)
+            .extension(ConnectInfo(SocketAddr::from((ip, 4000))));
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+
+        self.send(request).await
+    }
+
+    /// POSTs an `application/x-www-form-urlencoded` body, as OAuth clients do.
+    pub async fn post_form(&self, path: &str, form: &str) -> TestResponse {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(path)
+            .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))))
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(form.to_string()))
+            .unwrap();
+
+        self.send(request).await
+    }
+
+    async fn send(&self, request: Request<Body>) -> TestResponse {
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+
+        TestResponse { status, headers, body }
+    }
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn required_verification_is_met_by_the_emailed_link() {
+    let mut config = common::config();
+    config.email_verification.required = true;
+    let app = TestApp::new(config).await;
+
+    let res = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await;
+    assert_eq!(res.status, StatusCode::CREATED, "{}", res.body);
+    assert!(res.body.get("token").is_none());
+
+    let login = app.post("/api/auth/login", json!({ "email": "alice@example.com", "password": "pw" })).await;
+    assert_eq!(login.status, StatusCode::CONFLICT);
+    assert_eq!(login.body["code"], "email_not_verified");
+
+    let email = app.email("Verify your").await;
+    let verify_token = email.text.split("verify_token=").nth(1).unwrap().split_whitespace().next().unwrap().to_string();
+
+    let res = app.post("/api/auth/verify-email", json!({ "token": verify_token })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    let login = app.post("/api/auth/login", json!({ "email": "alice@example.com", "password": "pw" })).await;
+    assert_eq!(login.status, StatusCode::OK, "{}", login.body);
+
+    let bearer = format!("Bearer {}", login.body["token"].as_str().unwrap());
+    let profile = app.request(Method::GET, "/api/profile", None, &[("authorization", &bearer)]).await;
+    assert_eq!(profile.status, StatusCode::OK, "{}", profile.body);
+    assert_eq!(profile.body["email_verified"], true);
+
+    // The link works once
+    let reused = app.post("/api/auth/verify-email", json!({ "token": verify_token })).await;
+    assert_eq!(reused.status, StatusCode::BAD_REQUEST, "{}", reused.body);
+}
+
+#[tokio::test]
+async fn required_verification_is_met_by_a_magic_link() {
+    let mut config = common::config();
+    config.email_verification.required = true;
+    let app = TestApp::new(config).await;
+
+    let res = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await;
+    assert_eq!(res.status, StatusCode::CREATED, "{}", res.body);
+    assert!(res.body.get("token").is_none());
+
+    let login = app.post("/api/auth/login", json!({ "email": "alice@example.com", "password": "pw" })).await;
+    assert_eq!(login.status, StatusCode::CONFLICT);
+    assert_eq!(login.body["code"], "email_not_verified");
+
+    let res = app.post("/api/auth/magic-link", json!({ "email": "alice@example.com" })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let email = app.email("Your login link").await;
+    let magic_token = email.text.split("magic_token=").nth(1).unwrap().split_whitespace().next().unwrap().to_string();
+
+    let consumed = app.post("/api/auth/magic-link/consume", json!({ "token": magic_token })).await;
+    assert_eq!(consumed.status, StatusCode::OK, "{}", consumed.body);
+
+    // The link proved the address and started a session. The unverified
+    // password is dropped, as it may not have been set by the owner.
+    let bearer = format!("Bearer {}", consumed.body["token"].as_str().unwrap());
+    let profile = app.request(Method::GET, "/api/profile", None, &[("authorization", &bearer)]).await;
+    assert_eq!(profile.status, StatusCode::OK, "{}", profile.body);
+    assert_eq!(profile.body["email_verified"], true);
+    assert_eq!(profile.body["has_password"], false);
+}
+
+#[tokio::test]
+async fn resending_verification_does_not_lock_out_password_login() {
+    let app = TestApp::new(common::config()).await;
+    app.post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await;
+
+    // Someone else keeps asking for new verification links
+    let mut limited = false;
+    for _ in 0..10 {
+        let res = app.post("/api/auth/verify-email/resend", json!({ "email": "alice@example.com" })).await;
+        limited |= res.status == StatusCode::TOO_MANY_REQUESTS;
+    }
+    assert!(limited);
+
+    let login = app.post("/api/auth/login", json!({ "email": "alice@example.com", "password": "pw" })).await;
+    assert_eq!(login.status, StatusCode::OK, "{}", login.body);
+}
@@ -0,0 +1,131 @@
+//! Checks the JWKS built from the fixture RSA key, whose public DER is
+//! taken apart by hand to find the modulus and exponent, and rotating away
+//! from it.
+
+use std::sync::Arc;
+use auth_session::config::JwtConfig;
+use auth_session::db::{SigningKeyStore, Store, UserStore};
+use auth_session::models::role::UserGrants;
+use auth_session::utils::jwt::{self, JwtKeys};
+use chrono::{Duration, Utc};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, jwk::AlgorithmParameters};
+use serde_json::json;
+
+/// `openssl rsa -pubin -in tests/fixtures/rsa_public.pem -noout -modulus`
+const MODULUS: &str = "\
+    B1029AF19256631CB3DBB1A1B2346FC8988ED9D58CD658B75623ACF8E8E68FF2\
+    ED4B03E14484F46124E806D8605D0AE45B4F743ABC255DBFE9FDCA84EA84EF98\
+    BA1F3EF62A26AE5C0982B2CC51E6336519187CEC9A9B8AC3D620BFEC4FD84E14\
+    FFE2544C84EDA4BAC33B92100491AB75584C225419465459962051258D61D99D\
+    18D1FF5D1F5D82FF82A38FEA20DFCBB997F0F13D08CC1BDD9374D37AF79355A8\
+    5FA3D220E0A864C9D2EF2C98E0766EFBB8C0A64741C6F2AAD04CABD96B0ADE74\
+    42606CA5DCF43C06A8FCDC193275FA3F55E53314746CF7A23C799413B30D3C0C\
+    6A2FB17161AB93E8783C175275232003DA2CDFBE919D9E11A95EB7A9C83AF415";
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+async fn fixture_keys() -> JwtKeys {
+    fixture_keys_in(Arc::new(Store::new())).await
+}
+
+async fn fixture_keys_in(store: Arc<Store>) -> JwtKeys {
+    let config = JwtConfig {
+        secret: String::new(),
+        algorithm: Algorithm::RS256,
+        private_key_path: Some("tests/fixtures/rsa_private.pem".into()),
+        public_key_path: Some("tests/fixtures/rsa_public.pem".into()),
+        key_id: Some("fixture".into()),
+        expiration: 60,
+        refresh_expiration: 60,
+        issuer: "http://localhost:8000".into(),
+        audience: "auth_session".into(),
+        allowed_audiences: vec!["auth_session".into()],
+        leeway: 0,
+    };
+    let keys = JwtKeys::new(&config, store).unwrap();
+    keys.sync().await.unwrap();
+    keys
+}
+
+#[tokio::test]
+async fn rsa_jwk_matches_the_public_key() {
+    let jwks = fixture_keys().await.jwks();
+    assert_eq!(jwks.keys.len(), 1);
+
+    let jwk = &jwks.keys[0];
+    assert_eq!(jwk.common.key_id.as_deref(), Some("fixture"));
+    let AlgorithmParameters::RSA(rsa) = &jwk.algorithm else {
+        panic!("expected an RSA key, got {:?}", jwk.algorithm);
+    };
+
+    // The modulus has its high bit set, so DER prefixes it with a zero byte
+    // that must not reach the JWK
+    let n = URL_SAFE_NO_PAD.decode(&rsa.n).unwrap();
+    assert_eq!(n.len(), 256);
+    assert_eq!(n, from_hex(MODULUS));
+    assert_eq!(rsa.e, "AQAB");
+
+    // A verifier holding only the published JWK accepts our signatures
+    let key = EncodingKey::from_rsa_pem(&std::fs::read("tests/fixtures/rsa_private.pem").unwrap()).unwrap();
+    let token = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &json!({ "sub": "alice" }), &key).unwrap();
+
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.required_spec_claims.clear();
+    validation.validate_exp = false;
+    let decoded = jsonwebtoken::decode::<serde_json::Value>(&token, &DecodingKey::from_jwk(jwk).unwrap(), &validation).unwrap();
+    assert_eq!(decoded.claims["sub"], "alice");
+}
+
+#[tokio::test]
+async fn rotation_keeps_old_tokens_valid_until_the_key_is_retired() {
+    let store = Arc::new(Store::new());
+    let keys = fixture_keys_in(store.clone()).await;
+    let user = store
+        .create_user("alice@example.com".into(), "Alice".into(), None, None, None)
+        .await
+        .unwrap();
+    let token = || jwt::generate_token(&user, &UserGrants::default(), &keys, 60, None).unwrap();
+    let kid = |token: &str| jsonwebtoken::decode_header(token).unwrap().kid.unwrap();
+
+    let old_token = token();
+    assert_eq!(kid(&old_token), "fixture");
+
+    // `auth_session keys add` followed by `keys promote`
+    store
+        .create_signing_key(
+            "ec".into(),
+            "ES256".into(),
+            Some("tests/fixtures/ec_private.pem".into()),
+            Some("tests/fixtures/ec_public.pem".into()),
+            false,
+        )
+        .await
+        .unwrap();
+    keys.sync().await.unwrap();
+    assert_eq!(kid(&token()), "fixture");
+
+    store.promote_signing_key("ec", (Utc::now() + Duration::seconds(60)).naive_utc()).await.unwrap();
+    keys.sync().await.unwrap();
+
+    let new_token = token();
+    assert_eq!(kid(&new_token), "ec");
+    assert_eq!(keys.algorithm(), Algorithm::ES256);
+    assert!(jwt::verify_token(&new_token, &keys).is_ok());
+    assert!(jwt::verify_token(&old_token, &keys).is_ok(), "old tokens verify during the overlap");
+    assert_eq!(keys.jwks().keys.len(), 2);
+
+    // `auth_session keys retire fixture`
+    store.retire_signing_key("fixture", Utc::now().naive_utc()).await.unwrap();
+    keys.sync().await.unwrap();
+
+    let published: Vec<_> = keys.jwks().keys.into_iter().filter_map(|jwk| jwk.common.key_id).collect();
+    assert_eq!(published, ["ec"]);
+    assert!(jwt::verify_token(&old_token, &keys).is_err());
+    assert!(jwt::verify_token(&new_token, &keys).is_ok());
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use serde_json::json;
+
+async fn request_link(app: &TestApp, email: &str) -> String {
+    let sent_before = app.mailer.sent().len();
+    let res = app.post("/api/auth/magic-link", json!({ "email": email })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    // Wait for this request's email rather than an earlier one
+    while app.mailer.sent().len() == sent_before {
+        tokio::task::yield_now().await;
+    }
+    let email = app.email("Your login link").await;
+    email.text.split("magic_token=").nth(1).unwrap().split_whitespace().next().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn links_work_once_and_only_the_latest() {
+    let app = TestApp::new(common::config()).await;
+
+    let first = request_link(&app, "alice@example.com").await;
+    let second = request_link(&app, "alice@example.com").await;
+    assert_ne!(first, second);
+
+    let res = app.post("/api/auth/magic-link/consume", json!({ "token": first })).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+
+    let res = app.post("/api/auth/magic-link/consume", json!({ "token": second })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["user"]["email"], "alice@example.com");
+
+    let res = app.post("/api/auth/magic-link/consume", json!({ "token": second })).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn requesting_links_does_not_lock_out_password_login() {
+    let app = TestApp::new(common::config()).await;
+    app.post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await;
+
+    // Someone else keeps requesting links for the address
+    let mut limited = false;
+    for _ in 0..10 {
+        let res = app.post("/api/auth/magic-link", json!({ "email": "alice@example.com" })).await;
+        limited |= res.status == StatusCode::TOO_MANY_REQUESTS;
+    }
+    assert!(limited);
+
+    let login = app.post("/api/auth/login", json!({ "email": "alice@example.com", "password": "pw" })).await;
+    assert_eq!(login.status, StatusCode::OK, "{}", login.body);
+}
+
+#[tokio::test]
+async fn a_link_locks_out_whoever_registered_the_address_first() {
+    let app = TestApp::new(common::config()).await;
+
+    // Someone registers the address before its owner ever signs in
+    let squatter = app
+        .post("/api/auth/register", json!({ "email": "victim@example.com", "password": "squatted", "name": "x" }))
+        .await
+        .body;
+
+    let magic_token = request_link(&app, "victim@example.com").await;
+    let owner = app.post("/api/auth/magic-link/consume", json!({ "token": magic_token })).await;
+    assert_eq!(owner.status, StatusCode::OK, "{}", owner.body);
+
+    let login = app
+        .post("/api/auth/login", json!({ "email": "victim@example.com", "password": "squatted" }))
+        .await;
+    assert!(!login.status.is_success(), "{}", login.body);
+
+    let refresh = app.post("/api/auth/refresh", json!({ "refresh_token": squatter["refresh_token"] })).await;
+    assert_eq!(refresh.status, StatusCode::UNAUTHORIZED);
+
+    let squatter_bearer = format!("Bearer {}", squatter["token"].as_str().unwrap());
+    let profile = app.request(Method::GET, "/api/profile", None, &[("authorization", &squatter_bearer)]).await;
+    assert_eq!(profile.status, StatusCode::UNAUTHORIZED);
+
+    let owner_bearer = format!("Bearer {}", owner.body["token"].as_str().unwrap());
+    let profile = app.request(Method::GET, "/api/profile", None, &[("authorization", &owner_bearer)]).await;
+    assert_eq!(profile.status, StatusCode::OK, "{}", profile.body);
+    assert_eq!(profile.body["has_password"], false);
+}
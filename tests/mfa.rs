@@ -0,0 +1,114 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::{TestApp, TestResponse};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha1::Sha1;
+
+/// What an authenticator app shows for `secret` at Unix time `time`.
+fn totp_code(secret: &str, time: i64) -> String {
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).unwrap();
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).unwrap();
+    mac.update(&((time / 30) as u64).to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[19] & 0x0f) as usize;
+    let binary = u32::from_be_bytes(digest[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+    format!("{:06}", binary % 1_000_000)
+}
+
+/// Registers Alice and enables TOTP with a code from `now`. Returns the
+/// secret and her recovery codes.
+async fn enroll(app: &TestApp, now: i64) -> (String, Vec<String>) {
+    let registered = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await
+        .body;
+    let bearer = format!("Bearer {}", registered["token"].as_str().unwrap());
+
+    let enrollment = app.request(Method::POST, "/api/profile/mfa/totp", None, &[("authorization", &bearer)]).await;
+    assert_eq!(enrollment.status, StatusCode::OK, "{}", enrollment.body);
+    let secret = enrollment.body["secret"].as_str().unwrap().to_string();
+
+    let confirmed = app
+        .request(
+            Method::POST,
+            "/api/profile/mfa/totp/confirm",
+            Some(json!({ "code": totp_code(&secret, now) })),
+            &[("authorization", &bearer)],
+        )
+        .await;
+    assert_eq!(confirmed.status, StatusCode::OK, "{}", confirmed.body);
+    let recovery_codes = serde_json::from_value(confirmed.body["recovery_codes"].clone()).unwrap();
+
+    (secret, recovery_codes)
+}
+
+async fn login(app: &TestApp) -> String {
+    let res = app.post("/api/auth/login", json!({ "email": "alice@example.com", "password": "pw" })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["code"], "mfa_required");
+    res.body["mfa_token"].as_str().unwrap().to_string()
+}
+
+async fn verify(app: &TestApp, mfa_token: &str, factor: serde_json::Value) -> TestResponse {
+    let mut body = json!({ "mfa_token": mfa_token });
+    body.as_object_mut().unwrap().extend(factor.as_object().unwrap().clone());
+    app.post("/api/auth/mfa/verify", body).await
+}
+
+#[tokio::test]
+async fn a_totp_code_works_once() {
+    let app = TestApp::new(common::config()).await;
+    let now = chrono::Utc::now().timestamp();
+    let (secret, _) = enroll(&app, now).await;
+
+    // The code that confirmed enrollment is already spent
+    let mfa_token = login(&app).await;
+    let res = verify(&app, &mfa_token, json!({ "code": totp_code(&secret, now) })).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+
+    // The next step's code is accepted, within the allowed drift
+    let next = totp_code(&secret, now + 30);
+    let res = verify(&app, &mfa_token, json!({ "code": next })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert!(res.body["token"].is_string());
+
+    // Replaying it, even on a fresh login, is not
+    let mfa_token = login(&app).await;
+    let res = verify(&app, &mfa_token, json!({ "code": next })).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn a_recovery_code_works_once() {
+    let app = TestApp::new(common::config()).await;
+    let (_, recovery_codes) = enroll(&app, chrono::Utc::now().timestamp()).await;
+    assert_eq!(recovery_codes.len(), 10);
+
+    let mfa_token = login(&app).await;
+    let res = verify(&app, &mfa_token, json!({ "recovery_code": recovery_codes[0] })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    let mfa_token = login(&app).await;
+    let res = verify(&app, &mfa_token, json!({ "recovery_code": recovery_codes[0] })).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+
+    let res = verify(&app, &mfa_token, json!({ "recovery_code": recovery_codes[1] })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+}
+
+#[tokio::test]
+async fn the_mfa_token_is_single_use() {
+    let app = TestApp::new(common::config()).await;
+    let (_, recovery_codes) = enroll(&app, chrono::Utc::now().timestamp()).await;
+
+    let mfa_token = login(&app).await;
+    let res = verify(&app, &mfa_token, json!({ "recovery_code": recovery_codes[0] })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    let res = verify(&app, &mfa_token, json!({ "recovery_code": recovery_codes[1] })).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,192 @@
+//! Drives the OAuth login flow against a local mock of GitHub's
+//! authorization server and API.
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use auth_session::config::{OAuthProviderConfig, OAuthProviderKind};
+use axum::{
+    Form, Json, Router,
+    extract::State,
+    http::{Method, StatusCode, header},
+    routing::{get, post},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use common::{TestApp, TestResponse};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+/// Authorization codes the mock has issued, with the PKCE challenge each
+/// was issued for.
+#[derive(Default)]
+struct Mock {
+    codes: Mutex<HashMap<String, String>>,
+}
+
+async fn token(
+    State(mock): State<Arc<Mock>>,
+    Form(form): Form<HashMap<String, String>>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let challenge = mock.codes.lock().unwrap().remove(&form["code"]);
+    let verifier = form.get("code_verifier").map(|v| URL_SAFE_NO_PAD.encode(Sha256::digest(v)));
+
+    if challenge.is_none() || challenge != verifier {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "invalid_grant" })));
+    }
+    (StatusCode::OK, Json(json!({ "access_token": "gh-access", "token_type": "bearer" })))
+}
+
+async fn user() -> Json<serde_json::Value> {
+    Json(json!({ "id": 583231, "login": "octocat", "name": "The Octocat" }))
+}
+
+async fn user_emails() -> Json<serde_json::Value> {
+    Json(json!([
+        { "email": "octocat@example.com", "primary": true, "verified": true },
+    ]))
+}
+
+async fn start_github() -> (Arc<Mock>, OAuthProviderConfig) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+
+    let mock = Arc::new(Mock::default());
+    let app = Router::new()
+        .route("/token", post(token))
+        .route("/user", get(user))
+        .route("/user/emails", get(user_emails))
+        .with_state(mock.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let config = OAuthProviderConfig {
+        name: "github".into(),
+        kind: OAuthProviderKind::GitHub,
+        client_id: "client".into(),
+        client_secret: "secret".into(),
+        redirect_url: "http://localhost:8000/api/auth/github/callback".into(),
+        scopes: vec!["read:user".into(), "user:email".into()],
+        use_pkce: true,
+        issuer_url: None,
+        auth_url: Some(format!("{}/authorize", base)),
+        token_url: Some(format!("{}/token", base)),
+        api_url: Some(base),
+    };
+    (mock, config)
+}
+
+async fn github_app() -> (TestApp, Arc<Mock>) {
+    let (mock, provider) = start_github().await;
+    let mut config = common::config();
+    config.oauth_providers = vec![provider];
+    (TestApp::new(config).await, mock)
+}
+
+/// What the browser keeps from `/api/auth/github`: the `state` and PKCE
+/// challenge sent to the provider, and the binding cookie.
+struct Started {
+    state: String,
+    code_challenge: String,
+    cookie: String,
+}
+
+fn started(res: &TestResponse) -> Started {
+    assert_eq!(res.status, StatusCode::SEE_OTHER);
+    let location = res.headers[header::LOCATION].to_str().unwrap();
+    let params: HashMap<String, String> = reqwest::Url::parse(location).unwrap().query_pairs().into_owned().collect();
+    let cookie = res.headers[header::SET_COOKIE].to_str().unwrap().split(';').next().unwrap().to_string();
+
+    Started {
+        state: params["state"].clone(),
+        code_challenge: params["code_challenge"].clone(),
+        cookie,
+    }
+}
+
+async fn start(app: &TestApp) -> Started {
+    started(&app.request(Method::GET, "/api/auth/github", None, &[]).await)
+}
+
+/// The provider approves the request and redirects back with a code.
+fn approve(mock: &Mock, started: &Started) -> String {
+    let code = format!("code-{}", started.state);
+    mock.codes.lock().unwrap().insert(code.clone(), started.code_challenge.clone());
+    code
+}
+
+async fn callback(app: &TestApp, code: &str, state: &str, cookie: Option<&str>) -> TestResponse {
+    let path = format!("/api/auth/github/callback?code={}&state={}", code, state);
+    let headers: Vec<(&str, &str)> = cookie.map(|c| ("cookie", c)).into_iter().collect();
+    app.request(Method::GET, &path, None, &headers).await
+}
+
+#[tokio::test]
+async fn completes_a_login_from_the_browser_that_started_it() {
+    let (app, mock) = github_app().await;
+
+    let started = start(&app).await;
+    let code = approve(&mock, &started);
+    let res = callback(&app, &code, &started.state, Some(&started.cookie)).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert_eq!(res.body["user"]["email"], "octocat@example.com");
+
+    // The state is single-use
+    let code = approve(&mock, &started);
+    let replay = callback(&app, &code, &started.state, Some(&started.cookie)).await;
+    assert_eq!(replay.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn rejects_a_callback_without_the_binding_cookie() {
+    let (app, mock) = github_app().await;
+
+    let started = start(&app).await;
+    let code = approve(&mock, &started);
+    let res = callback(&app, &code, &started.state, None).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+}
+
+#[tokio::test]
+async fn rejects_a_callback_from_another_browser() {
+    let (app, mock) = github_app().await;
+
+    let victim = start(&app).await;
+    let attacker = start(&app).await;
+    let code = approve(&mock, &victim);
+    let res = callback(&app, &code, &victim.state, Some(&attacker.cookie)).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+}
+
+#[tokio::test]
+async fn rejects_a_code_issued_for_another_pkce_challenge() {
+    let (app, mock) = github_app().await;
+
+    // A code obtained through someone else's authorization is injected
+    // into this browser's callback
+    let other = start(&app).await;
+    let started = start(&app).await;
+    let code = approve(&mock, &other);
+    let res = callback(&app, &code, &started.state, Some(&started.cookie)).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED, "{}", res.body);
+}
+
+#[tokio::test]
+async fn authorization_requests_are_rate_limited_per_ip() {
+    let (app, mock) = github_app().await;
+
+    // The test limiter allows 100 requests per IP
+    for _ in 0..100 {
+        start(&app).await;
+    }
+    let res = app.request(Method::GET, "/api/auth/github", None, &[]).await;
+    assert_eq!(res.status, StatusCode::TOO_MANY_REQUESTS);
+
+    // Other clients can still log in
+    let started = started(&app.request_from([10, 0, 0, 2], Method::GET, "/api/auth/github", None, &[]).await);
+    let code = approve(&mock, &started);
+    let path = format!("/api/auth/github/callback?code={}&state={}", code, started.state);
+    let res = app
+        .request_from([10, 0, 0, 2], Method::GET, &path, None, &[("cookie", &started.cookie)])
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+}
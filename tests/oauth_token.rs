@@ -0,0 +1,116 @@
+mod common;
+
+use auth_session::utils::hashing;
+use axum::http::{Method, StatusCode, header};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use common::TestApp;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+async fn register_client(app: &TestApp) {
+    app.state
+        .oauth_clients
+        .create_oauth_client(
+            "my-app".into(),
+            "My App".into(),
+            Some(hashing::hash_password("s3cret").unwrap()),
+            vec!["https://app.example.com/callback".into()],
+            vec!["openid".into()],
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn only_registered_scopes_can_be_requested() {
+    let app = TestApp::new(common::config()).await;
+    register_client(&app).await;
+
+    let authorize = |scope: &str| {
+        format!(
+            "/oauth/authorize?response_type=code&client_id=my-app&redirect_uri=https://app.example.com/callback\
+             &scope={}&state=xyz&code_challenge=challenge&code_challenge_method=S256",
+            scope
+        )
+    };
+
+    let res = app.request(Method::GET, &authorize("openid"), None, &[]).await;
+    assert_eq!(res.status, StatusCode::SEE_OTHER);
+    let location = res.headers[header::LOCATION].to_str().unwrap();
+    assert!(!location.starts_with("https://app.example.com"), "{}", location);
+
+    let res = app.request(Method::GET, &authorize("openid%20email"), None, &[]).await;
+    assert_eq!(res.status, StatusCode::SEE_OTHER);
+    let location = res.headers[header::LOCATION].to_str().unwrap();
+    assert!(location.starts_with("https://app.example.com/callback?"), "{}", location);
+    assert!(location.contains("error=invalid_scope"), "{}", location);
+    assert!(location.contains("state=xyz"), "{}", location);
+}
+
+#[tokio::test]
+async fn the_code_flow_issues_tokens_for_userinfo() {
+    let app = TestApp::new(common::config()).await;
+    register_client(&app).await;
+    let registered = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await
+        .body;
+    let bearer = format!("Bearer {}", registered["token"].as_str().unwrap());
+
+    let verifier = "a-code-verifier-that-is-long-enough-for-pkce-0123456789";
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier));
+    let decision = json!({
+        "response_type": "code",
+        "client_id": "my-app",
+        "redirect_uri": "https://app.example.com/callback",
+        "scope": "openid",
+        "state": "xyz",
+        "code_challenge": challenge,
+        "code_challenge_method": "S256",
+        "approve": true,
+    });
+    let res = app.request(Method::POST, "/oauth/authorize", Some(decision), &[("authorization", &bearer)]).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let redirect_to = reqwest::Url::parse(res.body["redirect_to"].as_str().unwrap()).unwrap();
+    let code = redirect_to.query_pairs().find(|(k, _)| k == "code").unwrap().1.into_owned();
+
+    let form = format!(
+        "grant_type=authorization_code&code={}&redirect_uri=https://app.example.com/callback\
+         &client_id=my-app&client_secret=s3cret&code_verifier={}",
+        code, verifier
+    );
+    let res = app.post_form("/oauth/token", &form).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    assert!(res.body["id_token"].is_string(), "{}", res.body);
+
+    let client_bearer = format!("Bearer {}", res.body["access_token"].as_str().unwrap());
+    let userinfo = app.request(Method::GET, "/userinfo", None, &[("authorization", &client_bearer)]).await;
+    assert_eq!(userinfo.status, StatusCode::OK, "{}", userinfo.body);
+    assert_eq!(userinfo.body["email"], "alice@example.com");
+
+    // Codes are single-use
+    let res = app.post_form("/oauth/token", &form).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST, "{}", res.body);
+    assert_eq!(res.body["error"], "invalid_grant");
+
+    let discovery = app.request(Method::GET, "/.well-known/openid-configuration", None, &[]).await;
+    assert_eq!(discovery.body["issuer"], "http://localhost:8000");
+    assert_eq!(discovery.body["userinfo_endpoint"], "http://localhost:8000/userinfo");
+}
+
+#[tokio::test]
+async fn client_secret_guesses_are_rate_limited() {
+    let app = TestApp::new(common::config()).await;
+    register_client(&app).await;
+
+    let form = "grant_type=authorization_code&code=x&client_id=my-app&client_secret=guess";
+
+    // The test limiter allows 100 requests per IP
+    for _ in 0..100 {
+        let res = app.post_form("/oauth/token", form).await;
+        assert_eq!(res.status, StatusCode::UNAUTHORIZED, "{}", res.body);
+        assert_eq!(res.body["error"], "invalid_client");
+    }
+    let res = app.post_form("/oauth/token", form).await;
+    assert_eq!(res.status, StatusCode::TOO_MANY_REQUESTS);
+}
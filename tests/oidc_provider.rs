@@ -0,0 +1,182 @@
+//! Drives `OidcProvider` against a local mock issuer serving discovery,
+//! JWKS and token endpoints.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use auth_session::config::{OAuthProviderConfig, OAuthProviderKind};
+use auth_session::oauth::{OAuthProvider, OidcProvider};
+use axum::{Json, Router, extract::State, routing::{get, post}};
+use jsonwebtoken::{Algorithm, EncodingKey, Header, jwk::JwkSet};
+use serde_json::json;
+
+const CLIENT_ID: &str = "test-client";
+const NONCE: &str = "expected-nonce";
+
+struct Mock {
+    issuer: String,
+    id_token: Mutex<String>,
+    jwks: Mutex<JwkSet>,
+    jwks_fetches: AtomicUsize,
+}
+
+async fn discovery(State(mock): State<Arc<Mock>>) -> Json<serde_json::Value> {
+    Json(json!({
+        "issuer": mock.issuer,
+        "authorization_endpoint": format!("{}/authorize", mock.issuer),
+        "token_endpoint": format!("{}/token", mock.issuer),
+        "jwks_uri": format!("{}/jwks", mock.issuer),
+    }))
+}
+
+async fn jwks(State(mock): State<Arc<Mock>>) -> Json<JwkSet> {
+    mock.jwks_fetches.fetch_add(1, Ordering::SeqCst);
+    Json(mock.jwks.lock().unwrap().clone())
+}
+
+async fn token(State(mock): State<Arc<Mock>>) -> Json<serde_json::Value> {
+    Json(json!({
+        "access_token": "access",
+        "token_type": "Bearer",
+        "expires_in": 300,
+        "id_token": *mock.id_token.lock().unwrap(),
+    }))
+}
+
+/// Modulus of tests/fixtures/rsa_public.pem, base64url-encoded
+const FIXTURE_N: &str = "\
+    sQKa8ZJWYxyz27GhsjRvyJiO2dWM1li3ViOs-Ojmj_LtSwPhRIT0YSToBthgXQrkW090OrwlXb_p_cqE6oTvmLofPvYqJq5cCYKyzFHmM2UZ\
+    GHzsmpuKw9Ygv-xP2E4U_-JUTITtpLrDO5IQBJGrdVhMIlQZRlRZliBRJY1h2Z0Y0f9dH12C_4Kjj-og38u5l_DxPQjMG92TdNN695NVqF-j\
+    0iDgqGTJ0u8smOB2bvu4wKZHQcbyqtBMq9lrCt50QmBspdz0PAao_NwZMnX6P1XlMxR0bPeiPHmUE7MNPAxqL7FxYauT6Hg8F1J1IyAD2izf\
+    vpGdnhGpXrepyDr0FQ";
+
+/// The public JWK of the fixture key, as the issuer would publish it.
+fn fixture_jwks(kid: &str) -> JwkSet {
+    serde_json::from_value(json!({
+        "keys": [{ "kty": "RSA", "kid": kid, "alg": "RS256", "use": "sig", "n": FIXTURE_N, "e": "AQAB" }],
+    }))
+    .unwrap()
+}
+
+async fn start_issuer() -> (Arc<Mock>, OidcProvider) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let issuer = format!("http://{}", listener.local_addr().unwrap());
+
+    let mock = Arc::new(Mock {
+        issuer: issuer.clone(),
+        id_token: Mutex::new(String::new()),
+        jwks: Mutex::new(fixture_jwks("key-1")),
+        jwks_fetches: AtomicUsize::new(0),
+    });
+    let app = Router::new()
+        .route("/.well-known/openid-configuration", get(discovery))
+        .route("/jwks", get(jwks))
+        .route("/token", post(token))
+        .with_state(mock.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let provider = OidcProvider::new(OAuthProviderConfig {
+        name: "mock".into(),
+        kind: OAuthProviderKind::Oidc,
+        client_id: CLIENT_ID.into(),
+        client_secret: "secret".into(),
+        redirect_url: "http://localhost:8000/api/auth/mock/callback".into(),
+        scopes: vec!["openid".into(), "email".into()],
+        use_pkce: true,
+        issuer_url: Some(issuer),
+        auth_url: None,
+        token_url: None,
+        api_url: None,
+    });
+
+    (mock, provider)
+}
+
+fn claims(mock: &Mock) -> serde_json::Value {
+    let now = chrono::Utc::now().timestamp();
+    json!({
+        "iss": mock.issuer,
+        "sub": "subject-1",
+        "aud": CLIENT_ID,
+        "exp": now + 300,
+        "iat": now,
+        "nonce": NONCE,
+        "email": "alice@example.com",
+        "email_verified": true,
+        "name": "Alice",
+    })
+}
+
+fn sign(claims: &serde_json::Value, kid: &str) -> String {
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(kid.into());
+    let key = EncodingKey::from_rsa_pem(&std::fs::read("tests/fixtures/rsa_private.pem").unwrap()).unwrap();
+    jsonwebtoken::encode(&header, claims, &key).unwrap()
+}
+
+async fn login(mock: &Mock, provider: &OidcProvider, id_token: String) -> bool {
+    *mock.id_token.lock().unwrap() = id_token;
+    provider.authenticate("code".into(), Some("verifier".into()), Some(NONCE)).await.is_ok()
+}
+
+#[tokio::test]
+async fn valid_id_token() {
+    let (mock, provider) = start_issuer().await;
+    *mock.id_token.lock().unwrap() = sign(&claims(&mock), "key-1");
+
+    let identity = provider
+        .authenticate("code".into(), Some("verifier".into()), Some(NONCE))
+        .await
+        .unwrap();
+    assert_eq!(identity.subject, "subject-1");
+    assert_eq!(identity.email, "alice@example.com");
+    assert!(identity.email_verified);
+    assert_eq!(identity.name, "Alice");
+}
+
+#[tokio::test]
+async fn wrong_issuer_or_audience() {
+    let (mock, provider) = start_issuer().await;
+
+    let mut wrong_iss = claims(&mock);
+    wrong_iss["iss"] = json!("https://evil.example.com");
+    assert!(!login(&mock, &provider, sign(&wrong_iss, "key-1")).await);
+
+    let mut wrong_aud = claims(&mock);
+    wrong_aud["aud"] = json!("another-client");
+    assert!(!login(&mock, &provider, sign(&wrong_aud, "key-1")).await);
+
+    let mut expired = claims(&mock);
+    expired["exp"] = json!(chrono::Utc::now().timestamp() - 3600);
+    assert!(!login(&mock, &provider, sign(&expired, "key-1")).await);
+
+    assert!(login(&mock, &provider, sign(&claims(&mock), "key-1")).await);
+}
+
+#[tokio::test]
+async fn nonce_mismatch() {
+    let (mock, provider) = start_issuer().await;
+
+    let mut replayed = claims(&mock);
+    replayed["nonce"] = json!("some-other-login");
+    assert!(!login(&mock, &provider, sign(&replayed, "key-1")).await);
+
+    let mut missing = claims(&mock);
+    missing.as_object_mut().unwrap().remove("nonce");
+    assert!(!login(&mock, &provider, sign(&missing, "key-1")).await);
+}
+
+#[tokio::test]
+async fn unknown_kid_refetches_jwks() {
+    let (mock, provider) = start_issuer().await;
+    assert!(login(&mock, &provider, sign(&claims(&mock), "key-1")).await);
+    assert_eq!(mock.jwks_fetches.load(Ordering::SeqCst), 1);
+
+    // A kid the issuer doesn't publish is refused after one refetch
+    assert!(!login(&mock, &provider, sign(&claims(&mock), "key-2")).await);
+    assert_eq!(mock.jwks_fetches.load(Ordering::SeqCst), 2);
+
+    // Once the issuer rotates to it, the same kid is accepted without a restart
+    *mock.jwks.lock().unwrap() = fixture_jwks("key-2");
+    assert!(login(&mock, &provider, sign(&claims(&mock), "key-2")).await);
+    assert_eq!(mock.jwks_fetches.load(Ordering::SeqCst), 3);
+}
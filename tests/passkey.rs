@@ -0,0 +1,104 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use common::TestApp;
+use common::authenticator::SoftAuthenticator;
+use serde_json::json;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn a_passkey_alone_enables_the_second_factor() {
+    let app = TestApp::new(common::config()).await;
+    let registered = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await
+        .body;
+    let user_id = Uuid::parse_str(registered["user"]["id"].as_str().unwrap()).unwrap();
+
+    app.state
+        .webauthn
+        .create_webauthn_credential(user_id, "credential".into(), vec![0xa0], 0, "Laptop".into())
+        .await
+        .unwrap();
+
+    let login = app.post("/api/auth/login", json!({ "email": "alice@example.com", "password": "pw" })).await;
+    assert_eq!(login.status, StatusCode::OK, "{}", login.body);
+    assert_eq!(login.body["code"], "mfa_required");
+    assert_eq!(login.body["methods"], json!(["webauthn"]));
+    assert!(login.body.get("token").is_none());
+}
+
+#[tokio::test]
+async fn login_options_are_rate_limited() {
+    let app = TestApp::new(common::config()).await;
+
+    // The test limiter allows 100 requests per IP
+    for _ in 0..100 {
+        let res = app.post("/api/auth/passkey/options", json!({})).await;
+        assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    }
+    let res = app.post("/api/auth/passkey/options", json!({})).await;
+    assert_eq!(res.status, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn passkey_login_requires_a_verified_email() {
+    let mut config = common::config();
+    config.email_verification.required = true;
+    let app = TestApp::new(config).await;
+    app.post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await;
+    let user = app.state.store.find_user_by_email("alice@example.com").await.unwrap().unwrap();
+
+    let mut authenticator = SoftAuthenticator::new();
+    authenticator.user_handle = user.id.as_bytes().to_vec();
+    app.state
+        .webauthn
+        .create_webauthn_credential(
+            user.id,
+            URL_SAFE_NO_PAD.encode(&authenticator.credential_id),
+            authenticator.cose_key(),
+            0,
+            "Laptop".into(),
+        )
+        .await
+        .unwrap();
+
+    let login = |sign_count: u32| {
+        let app = &app;
+        let authenticator = &authenticator;
+        async move {
+            let options = app.post("/api/auth/passkey/options", json!({})).await;
+            let challenge = options.body["challenge"].as_str().unwrap();
+            let credential = authenticator.assert_json(challenge, sign_count);
+            app.post("/api/auth/passkey/login", json!({ "credential": credential })).await
+        }
+    };
+
+    let res = login(1).await;
+    assert_eq!(res.status, StatusCode::CONFLICT, "{}", res.body);
+    assert_eq!(res.body["code"], "email_not_verified");
+
+    app.state.store.mark_email_verified(user.id).await.unwrap();
+    let res = login(2).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+}
+
+#[tokio::test]
+async fn registration_options_are_rate_limited() {
+    let app = TestApp::new(common::config()).await;
+    let registered = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await
+        .body;
+    let bearer = format!("Bearer {}", registered["token"].as_str().unwrap());
+
+    // The test limiter allows 100 requests per IP
+    for _ in 0..100 {
+        let res = app.request(Method::POST, "/api/profile/passkeys/options", None, &[("authorization", &bearer)]).await;
+        assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    }
+    let res = app.request(Method::POST, "/api/profile/passkeys/options", None, &[("authorization", &bearer)]).await;
+    assert_eq!(res.status, StatusCode::TOO_MANY_REQUESTS);
+}
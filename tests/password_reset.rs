@@ -0,0 +1,51 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn reset_ends_sessions_started_the_same_second() {
+    let app = TestApp::new(common::config()).await;
+    let session = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "old", "name": "Alice" }))
+        .await
+        .body;
+
+    let res = app.post("/api/auth/password/forgot", json!({ "email": "alice@example.com" })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let email = app.email("Reset your").await;
+    let reset_token = email.text.split("token=").nth(1).unwrap().split_whitespace().next().unwrap().to_string();
+
+    let res = app.post("/api/auth/password/reset", json!({ "token": reset_token, "password": "new" })).await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+
+    // Almost certainly issued within the same second as the reset
+    let bearer = format!("Bearer {}", session["token"].as_str().unwrap());
+    let profile = app.request(Method::GET, "/api/profile", None, &[("authorization", &bearer)]).await;
+    assert_eq!(profile.status, StatusCode::UNAUTHORIZED);
+
+    let refresh = app.post("/api/auth/refresh", json!({ "refresh_token": session["refresh_token"] })).await;
+    assert_eq!(refresh.status, StatusCode::UNAUTHORIZED);
+
+    let reused = app.post("/api/auth/password/reset", json!({ "token": reset_token, "password": "again" })).await;
+    assert_eq!(reused.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn requesting_resets_does_not_lock_out_password_login() {
+    let app = TestApp::new(common::config()).await;
+    app.post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await;
+
+    // Someone else keeps requesting resets for the address
+    let mut limited = false;
+    for _ in 0..10 {
+        let res = app.post("/api/auth/password/forgot", json!({ "email": "alice@example.com" })).await;
+        limited |= res.status == StatusCode::TOO_MANY_REQUESTS;
+    }
+    assert!(limited);
+
+    let login = app.post("/api/auth/login", json!({ "email": "alice@example.com", "password": "pw" })).await;
+    assert_eq!(login.status, StatusCode::OK, "{}", login.body);
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use axum::http::{Method, StatusCode, header};
+use common::{TestApp, TestResponse};
+use serde_json::json;
+
+/// The `Cookie` header a browser would send back, plus the CSRF token a
+/// page reads from the `<name>_csrf` cookie.
+fn cookies(res: &TestResponse) -> (String, String) {
+    let pairs: Vec<&str> = res
+        .headers
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .map(|value| value.to_str().unwrap().split(';').next().unwrap())
+        .collect();
+    let csrf = pairs
+        .iter()
+        .find_map(|pair| pair.strip_prefix("session_csrf="))
+        .unwrap()
+        .to_string();
+
+    (pairs.join("; "), csrf)
+}
+
+async fn session_app() -> (TestApp, String, String) {
+    let mut config = common::config();
+    config.session.enabled = true;
+    let app = TestApp::new(config).await;
+
+    let res = app
+        .post("/api/auth/register", json!({ "email": "alice@example.com", "password": "pw", "name": "Alice" }))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{}", res.body);
+    let (cookie, csrf) = cookies(&res);
+
+    (app, cookie, csrf)
+}
+
+#[tokio::test]
+async fn unauthenticated_forms_ignore_session_cookies() {
+    let (app, cookie, _) = session_app().await;
+
+    // A stale session doesn't get in the way of logging in again
+    let login = app
+        .request(
+            Method::POST,
+            "/api/auth/login",
+            Some(json!({ "email": "alice@example.com", "password": "pw" })),
+            &[("cookie", &cookie)],
+        )
+        .await;
+    assert_eq!(login.status, StatusCode::OK, "{}", login.body);
+
+    let forgot = app
+        .request(Method::POST, "/api/auth/password/forgot", Some(json!({ "email": "alice@example.com" })), &[("cookie", &cookie)])
+        .await;
+    assert_eq!(forgot.status, StatusCode::OK, "{}", forgot.body);
+}
+
+#[tokio::test]
+async fn cookie_authenticated_writes_need_the_csrf_header() {
+    let (app, cookie, csrf) = session_app().await;
+
+    let profile = app.request(Method::GET, "/api/profile", None, &[("cookie", &cookie)]).await;
+    assert_eq!(profile.status, StatusCode::OK);
+
+    let refresh = app.request(Method::POST, "/api/auth/refresh", None, &[("cookie", &cookie)]).await;
+    assert_eq!(refresh.status, StatusCode::FORBIDDEN);
+    let refresh = app
+        .request(Method::POST, "/api/auth/refresh", None, &[("cookie", &cookie), ("x-csrf-token", "guessed")])
+        .await;
+    assert_eq!(refresh.status, StatusCode::FORBIDDEN);
+    let refresh = app
+        .request(Method::POST, "/api/auth/refresh", None, &[("cookie", &cookie), ("x-csrf-token", &csrf)])
+        .await;
+    assert_eq!(refresh.status, StatusCode::OK, "{}", refresh.body);
+    let (cookie, csrf) = cookies(&refresh);
+
+    let logout = app.request(Method::POST, "/api/auth/logout", None, &[("cookie", &cookie)]).await;
+    assert_eq!(logout.status, StatusCode::FORBIDDEN);
+    let logout = app
+        .request(Method::POST, "/api/auth/logout", None, &[("cookie", &cookie), ("x-csrf-token", &csrf)])
+        .await;
+    assert_eq!(logout.status, StatusCode::OK, "{}", logout.body);
+
+    let profile = app.request(Method::GET, "/api/profile", None, &[("cookie", &cookie)]).await;
+    assert_eq!(profile.status, StatusCode::UNAUTHORIZED);
+}
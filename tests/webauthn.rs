@@ -0,0 +1,164 @@
+//! Runs the WebAuthn ceremony checks against a software ES256
+//! authenticator, so every field the browser and authenticator sign can be
+//! tampered with.
+
+mod common;
+
+use auth_session::utils::webauthn::{self, AuthenticationCredential};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use ciborium::Value;
+use common::authenticator::*;
+
+fn verify(
+    credential: &AuthenticationCredential,
+    challenge: &str,
+    public_key: &[u8],
+    stored_sign_count: u32,
+) -> Option<u32> {
+    webauthn::verify_assertion(&webauthn_config(), credential, challenge, public_key, stored_sign_count, USER_HANDLE, true)
+        .ok()
+        .map(|verified| verified.sign_count)
+}
+
+#[test]
+fn registration_stores_the_credential_key() {
+    let authenticator = SoftAuthenticator::new();
+    let credential = authenticator.register("register-challenge");
+    assert_eq!(credential.challenge().as_deref(), Some("register-challenge"));
+
+    let verified = webauthn::verify_registration(&webauthn_config(), &credential, "register-challenge").unwrap();
+    assert_eq!(verified.credential_id, URL_SAFE_NO_PAD.encode(&authenticator.credential_id));
+    assert_eq!(verified.public_key, authenticator.cose_key());
+    assert_eq!(verified.sign_count, 0);
+
+    assert!(webauthn::verify_registration(&webauthn_config(), &credential, "another-challenge").is_err());
+}
+
+#[test]
+fn valid_assertion() {
+    let authenticator = SoftAuthenticator::new();
+    let public_key = authenticator.cose_key();
+
+    let credential = authenticator.assert("login-challenge", 5);
+    assert_eq!(credential.challenge().as_deref(), Some("login-challenge"));
+    assert_eq!(verify(&credential, "login-challenge", &public_key, 4), Some(5));
+
+    // Another authenticator's key doesn't verify it
+    assert_eq!(verify(&credential, "login-challenge", &SoftAuthenticator::new().cose_key(), 4), None);
+}
+
+#[test]
+fn wrong_challenge_origin_or_rp_id() {
+    let authenticator = SoftAuthenticator::new();
+    let public_key = authenticator.cose_key();
+    let auth_data = || SoftAuthenticator::authenticator_data(RP_ID, USER_PRESENT | USER_VERIFIED, 1);
+
+    let replayed = authenticator.assert("old-challenge", 1);
+    assert_eq!(verify(&replayed, "login-challenge", &public_key, 0), None);
+
+    let phished = authenticator.sign(
+        auth_data(),
+        &ClientData { kind: "webauthn.get", challenge: "login-challenge", origin: "https://evil.example.com" },
+    );
+    assert_eq!(verify(&phished, "login-challenge", &public_key, 0), None);
+
+    let other_rp = authenticator.sign(
+        SoftAuthenticator::authenticator_data("evil.example.com", USER_PRESENT | USER_VERIFIED, 1),
+        &ClientData { kind: "webauthn.get", challenge: "login-challenge", origin: ORIGIN },
+    );
+    assert_eq!(verify(&other_rp, "login-challenge", &public_key, 0), None);
+
+    let registration_response = authenticator.sign(
+        auth_data(),
+        &ClientData { kind: "webauthn.create", challenge: "login-challenge", origin: ORIGIN },
+    );
+    assert_eq!(verify(&registration_response, "login-challenge", &public_key, 0), None);
+
+    // Signed correctly, but the authenticator data was altered afterwards
+    let mut tampered = authenticator.assert("login-challenge", 1);
+    let mut raw = URL_SAFE_NO_PAD.decode(&tampered.response.authenticator_data).unwrap();
+    raw[32] |= 0x08;
+    tampered.response.authenticator_data = URL_SAFE_NO_PAD.encode(raw);
+    assert_eq!(verify(&tampered, "login-challenge", &public_key, 0), None);
+}
+
+#[test]
+fn user_presence_and_verification() {
+    let authenticator = SoftAuthenticator::new();
+    let public_key = authenticator.cose_key();
+    let client_data = ClientData { kind: "webauthn.get", challenge: "login-challenge", origin: ORIGIN };
+
+    let unverified = authenticator.sign(SoftAuthenticator::authenticator_data(RP_ID, USER_PRESENT, 1), &client_data);
+    assert_eq!(verify(&unverified, "login-challenge", &public_key, 0), None);
+    // Enough when the passkey is only a second factor
+    assert!(webauthn::verify_assertion(&webauthn_config(), &unverified, "login-challenge", &public_key, 0, USER_HANDLE, false).is_ok());
+
+    let absent = authenticator.sign(SoftAuthenticator::authenticator_data(RP_ID, 0, 1), &client_data);
+    assert!(webauthn::verify_assertion(&webauthn_config(), &absent, "login-challenge", &public_key, 0, USER_HANDLE, false).is_err());
+
+    let other_user = authenticator.assert("login-challenge", 1);
+    assert!(webauthn::verify_assertion(&webauthn_config(), &other_user, "login-challenge", &public_key, 0, b"someone-else", true).is_err());
+}
+
+#[test]
+fn sign_count_regression() {
+    let authenticator = SoftAuthenticator::new();
+    let public_key = authenticator.cose_key();
+
+    assert_eq!(verify(&authenticator.assert("c", 10), "c", &public_key, 9), Some(10));
+    // A clone replaying the counter, or lagging behind it
+    assert_eq!(verify(&authenticator.assert("c", 10), "c", &public_key, 10), None);
+    assert_eq!(verify(&authenticator.assert("c", 3), "c", &public_key, 10), None);
+    // A counter that drops to zero is a regression too
+    assert_eq!(verify(&authenticator.assert("c", 0), "c", &public_key, 10), None);
+
+    // Authenticators without a counter always report zero
+    assert_eq!(verify(&authenticator.assert("c", 0), "c", &public_key, 0), Some(0));
+}
+
+#[test]
+fn malformed_cbor() {
+    let authenticator = SoftAuthenticator::new();
+    let client_data = ClientData { kind: "webauthn.create", challenge: "c", origin: ORIGIN }.encode();
+    let refused = |attestation: &[u8]| {
+        let credential = registration(&authenticator.credential_id, &client_data, attestation);
+        webauthn::verify_registration(&webauthn_config(), &credential, "c").is_err()
+    };
+
+    let valid = URL_SAFE_NO_PAD.decode(&authenticator.register("c").response.attestation_object).unwrap();
+    assert!(!refused(&valid));
+
+    assert!(refused(b""));
+    assert!(refused(&[0xff, 0x00, 0x13]));
+    assert!(refused(&valid[..valid.len() / 2]));
+    // Well-formed CBOR of the wrong shape
+    assert!(refused(&cbor(&Value::Array(vec![Value::Integer(1.into())]))));
+    assert!(refused(&cbor(&Value::Map(vec![(Value::Text("authData".into()), Value::Text("not bytes".into()))]))));
+
+    // Attested credential data whose COSE key is not CBOR, or not a key
+    let with_key = |cose_key: &[u8]| {
+        let mut auth_data = SoftAuthenticator::authenticator_data(RP_ID, USER_PRESENT | USER_VERIFIED | ATTESTED_CREDENTIAL, 0);
+        auth_data.extend([0; 16]);
+        auth_data.extend((authenticator.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend(&authenticator.credential_id);
+        auth_data.extend(cose_key);
+        cbor(&Value::Map(vec![
+            (Value::Text("fmt".into()), Value::Text("none".into())),
+            (Value::Text("attStmt".into()), Value::Map(vec![])),
+            (Value::Text("authData".into()), Value::Bytes(auth_data)),
+        ]))
+    };
+    assert!(!refused(&with_key(&authenticator.cose_key())));
+    assert!(refused(&with_key(&[0xa5, 0x01])));
+    assert!(refused(&with_key(&cbor(&Value::Map(vec![(Value::Integer(1.into()), Value::Integer(2.into()))])))));
+
+    // Assertions checked against a stored key that isn't a COSE key
+    let credential = authenticator.assert("c", 1);
+    assert_eq!(verify(&credential, "c", b"\xa1\x01", 0), None);
+    assert_eq!(verify(&credential, "c", b"", 0), None);
+
+    // Truncated authenticator data
+    let mut truncated = authenticator.assert("c", 1);
+    truncated.response.authenticator_data = URL_SAFE_NO_PAD.encode([0u8; 20]);
+    assert_eq!(verify(&truncated, "c", &authenticator.cose_key(), 0), None);
+}